@@ -0,0 +1,211 @@
+//! Checks that a set of calculated product costs accounts for all of the costs
+//! that went into it. If a company has X in costs and produced some products,
+//! then the sum over those products of (per-unit costs * units produced) should
+//! come out to X. Anything less means costs fell through the cracks somewhere,
+//! and anything more means some costs were counted twice.
+//!
+//! Because per-unit costs are rounded, the two sides will usually differ by a
+//! few billionths, so the check takes a tolerance.
+
+use std::collections::HashMap;
+use models::{
+    costs::Costs,
+    fixed::Fixed,
+    product::Product,
+};
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConservationReport {
+    /// The sum of all of the tagged input costs
+    pub inputs: Costs,
+    /// The sum over all products of per-unit costs * units produced
+    pub allocated: Costs,
+    /// Input costs that didn't end up in any product
+    pub unallocated: Costs,
+    /// Costs that were allocated on top of what went in
+    pub overallocated: Costs,
+}
+
+impl ConservationReport {
+    /// Whether or not each cost is within `tolerance` of being conserved
+    pub fn is_balanced(&self, tolerance: Fixed) -> bool {
+        self.unallocated.labor().values()
+            .chain(self.unallocated.products().values())
+            .chain(self.overallocated.labor().values())
+            .chain(self.overallocated.products().values())
+            .all(|x| x <= &tolerance)
+    }
+}
+
+/// Compare the costs that went into a calculation (`sum_costs`, bucketed by
+/// cost tag) against what came out of it (`final_costs`, the per-unit costs
+/// for each product).
+pub fn check_conservation(sum_costs: &HashMap<String, Costs>, sum_produced: &HashMap<String, Fixed>, final_costs: &HashMap<String, Costs>) -> ConservationReport {
+    let inputs = sum_costs.values()
+        .fold(Costs::new(), |acc, x| acc + x.clone());
+    let allocated = final_costs.iter()
+        .map(|(prod_id, costs)| {
+            let num_produced = sum_produced.get(prod_id).map(|x| x.clone()).unwrap_or(Fixed::zero());
+            costs.clone() * num_produced
+        })
+        .fold(Costs::new(), |acc, x| acc + x);
+    let diff = inputs.clone() - allocated.clone();
+    let mut unallocated = Costs::new();
+    let mut overallocated = Costs::new();
+    for (k, val) in diff.labor() {
+        if val > &Fixed::zero() {
+            unallocated.track_labor(k, val.clone());
+        } else if val < &Fixed::zero() {
+            overallocated.track_labor(k, val.abs());
+        }
+    }
+    for (k, val) in diff.products() {
+        if val > &Fixed::zero() {
+            unallocated.track(k, val.clone());
+        } else if val < &Fixed::zero() {
+            overallocated.track(k, val.abs());
+        }
+    }
+    ConservationReport {
+        inputs,
+        allocated,
+        unallocated,
+        overallocated,
+    }
+}
+
+/// How far off (per cost) a tag-weighted calculation can be from conserving
+/// costs purely because of rounding. Each product can be off by up to half a
+/// billionth per unit produced (from rounding its per-unit costs) plus half a
+/// billionth per cost tag (from rounding each tag's share).
+pub fn rounding_tolerance(products: &HashMap<String, Product>, sum_costs: &HashMap<String, Costs>, sum_produced: &HashMap<String, Fixed>) -> Fixed {
+    let one = Fixed::from(1i64).raw();
+    let raw = products.iter()
+        .map(|(prod_id, product)| {
+            let units = sum_produced.get(prod_id).map(|x| x.raw()).unwrap_or(0);
+            if units <= 0 {
+                return 0;
+            }
+            // products can pick up tags they don't carry (see
+            // assign_cost_tags()), so count those as well
+            let num_tags = (product.cost_tags.len() + sum_costs.len()) as i128;
+            ((units + one - 1) / one) + num_tags + 1
+        })
+        .sum::<i128>();
+    Fixed::from_raw(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc, Duration};
+    use exonum::crypto::Hash;
+    use proptest::prelude::*;
+    use models::{
+        cost_tag::CostTagEntry,
+        labor::Labor,
+        order::{Order, ProcessStatus, ProductEntry, Shipping},
+        product::{Product, Unit, Dimensions},
+    };
+    use util::time;
+    use crate::strategy::{self, CostingStrategy, CostingInputs};
+
+    const COMPANY_ID: &'static str = "b9eb0cc2-5b37-4fd1-83fd-8597625aee95";
+
+    fn make_tags(tags: &Vec<(usize, u64)>) -> Vec<CostTagEntry> {
+        tags.iter()
+            .map(|(idx, weight)| CostTagEntry::new(&format!("tag{}", idx), *weight))
+            .collect::<Vec<_>>()
+    }
+
+    fn make_product(id: &str, tags: &Vec<(usize, u64)>) -> Product {
+        let now = time::now();
+        Product::new(id, COMPANY_ID, "Widget", &Unit::Millimeter, 0.0, &Dimensions::new(0.0, 0.0, 0.0), &make_tags(tags), true, "", &now, &now, None, 1, &Hash::default())
+    }
+
+    fn make_order(id: &str, company_id_from: &str, company_id_to: &str, tags: &Vec<(usize, u64)>, product_id: &str, quantity: Fixed, costs: &Costs, now: &DateTime<Utc>) -> Order {
+        let mut entry = ProductEntry::new(product_id, quantity, costs, false);
+        entry.fulfilled = quantity;
+        Order::new(id, company_id_from, company_id_to, &make_tags(tags), &vec![entry], &ProcessStatus::Finalized, "", "", &Shipping::default(), now, now, 1, &Hash::default())
+    }
+
+    fn arb_tags(num_tags: usize) -> impl Strategy<Value = Vec<(usize, u64)>> {
+        prop::collection::vec((0..num_tags, 0..4u64), 0..3)
+    }
+
+    prop_compose! {
+        fn arb_company()(num_tags in 1..5usize)
+                        (products in prop::collection::vec((arb_tags(num_tags), 0..1000u64), 1..6),
+                         orders in prop::collection::vec((arb_tags(num_tags), 1..100u64, 1..50u64), 0..6),
+                         labor in prop::collection::vec((arb_tags(num_tags), 1..40i64, 1..100u64), 0..6))
+                        -> (Vec<(Vec<(usize, u64)>, u64)>, Vec<(Vec<(usize, u64)>, u64, u64)>, Vec<(Vec<(usize, u64)>, i64, u64)>) {
+            (products, orders, labor)
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn tag_weighted_conserves_costs((products_spec, orders_spec, labor_spec) in arb_company()) {
+            let now = time::now();
+            let mut products = HashMap::new();
+            let mut orders_incoming = Vec::new();
+            for (i, (tags, units)) in products_spec.iter().enumerate() {
+                let product = make_product(&format!("product{}", i), tags);
+                if *units > 0 {
+                    orders_incoming.push(make_order(&format!("incoming{}", i), "customer", COMPANY_ID, &vec![], &product.id, Fixed::from(*units), &Costs::new(), &now));
+                }
+                products.insert(product.id.clone(), product);
+            }
+            let orders_outgoing = orders_spec.iter().enumerate()
+                .map(|(i, (tags, quantity, price))| {
+                    make_order(&format!("outgoing{}", i), COMPANY_ID, "supplier", tags, "steel", Fixed::from(*quantity), &Costs::new_with_labor("smelter", *price), &now)
+                })
+                .collect::<Vec<_>>();
+            let labor = labor_spec.iter().enumerate()
+                .map(|(i, (tags, hours, wage))| {
+                    let start = now - Duration::hours(*hours);
+                    Labor::new(&format!("labor{}", i), COMPANY_ID, "worker", "machinist", *wage as f64, &make_tags(tags), Some(&start), Some(&now), &now, &now, None, "", 1, &Hash::default())
+                })
+                .collect::<Vec<_>>();
+
+            let (sum_costs, sum_produced) = crate::sum_inputs(&orders_incoming, &orders_outgoing, &labor, &HashMap::new(), &(now - Duration::days(365)), &now).unwrap();
+            let baseline = HashMap::new();
+            let inputs = CostingInputs {
+                products: &products,
+                sum_costs: &sum_costs,
+                sum_produced: &sum_produced,
+                baseline: &baseline,
+            };
+            let final_costs = strategy::TagWeighted.calculate(&inputs).unwrap();
+            let report = check_conservation(&sum_costs, &sum_produced, &final_costs);
+            if orders_incoming.len() > 0 {
+                let tolerance = rounding_tolerance(&products, &sum_costs, &sum_produced);
+                prop_assert!(report.is_balanced(tolerance), "costs not conserved: {:?}", report);
+            } else {
+                // nothing produced, so nothing can be allocated
+                prop_assert!(report.allocated.is_zero());
+                prop_assert_eq!(report.unallocated, report.inputs);
+            }
+        }
+    }
+
+    #[test]
+    fn reports_imbalances() {
+        let mut sum_costs = HashMap::new();
+        sum_costs.insert("tag0".to_owned(), Costs::new_with_labor("machinist", 10.0));
+        sum_costs.insert("tag1".to_owned(), Costs::new_with_product("steel", 10.0));
+        let mut sum_produced = HashMap::new();
+        sum_produced.insert("widget".to_owned(), Fixed::from(2.0));
+        let mut final_costs = HashMap::new();
+        let mut costs = Costs::new_with_labor("machinist", 3.0);
+        costs.track("steel", 5.5);
+        final_costs.insert("widget".to_owned(), costs);
+        let report = check_conservation(&sum_costs, &sum_produced, &final_costs);
+        assert_eq!(report.unallocated.get_labor("machinist"), 4.0);
+        assert_eq!(report.overallocated.get("steel"), 1.0);
+        assert!(!report.is_balanced(Fixed::from(0.5)));
+        assert!(report.is_balanced(Fixed::from(4.0)));
+    }
+}
+
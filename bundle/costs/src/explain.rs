@@ -0,0 +1,216 @@
+//! Explains how a product's costs came to be. This walks through the same steps
+//! as the tag-weighted costing algorithm, but instead of just spitting out
+//! per-unit costs it records, for each cost tag, where the costs came from
+//! (orders, labor, amortization) and how big a slice of them the product got.
+//!
+//! This is purely informational and is never used to assign costs.
+
+use std::collections::HashMap;
+use error::{BResult, BError};
+use models::{
+    costs::Costs,
+    fixed::Fixed,
+    product::Product,
+};
+use serde::{Serialize, Deserialize};
+
+/// What kind of record a cost came from
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceType {
+    Order,
+    Labor,
+    Amortization,
+}
+
+/// A single record that fed costs into a cost tag
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CostSource {
+    pub source_type: SourceType,
+    pub id: String,
+    pub costs: Costs,
+}
+
+impl CostSource {
+    pub fn new(source_type: SourceType, id: &str, costs: &Costs) -> Self {
+        Self {
+            source_type,
+            id: id.to_owned(),
+            costs: costs.clone(),
+        }
+    }
+}
+
+/// The costs that went into a single cost tag, split up by where they came
+/// from. The totals are what actually get used for costing, and `sources`
+/// lists the individual records behind them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TagCosts {
+    pub orders: Costs,
+    pub labor: Costs,
+    pub amortization: Costs,
+    pub sources: Vec<CostSource>,
+}
+
+impl TagCosts {
+    pub fn total(&self) -> Costs {
+        self.orders.clone() + self.labor.clone() + self.amortization.clone()
+    }
+}
+
+/// How one cost tag contributed to a product's costs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TagExplanation {
+    pub cost_tag_id: String,
+    /// The tag's costs, and where they came from
+    pub costs: TagCosts,
+    /// The product's weight for this tag
+    pub weight: u64,
+    /// The total weight for this tag across all products
+    pub total_weight: u64,
+    /// weight / total_weight
+    pub ratio: Fixed,
+    /// True if the product doesn't actually carry this tag, but was given it
+    /// because no produced product did (so the costs get spread evenly)
+    pub implicit: bool,
+    /// The slice of the tag's costs assigned to the product (before dividing
+    /// by the number of units produced)
+    pub share: Costs,
+}
+
+/// The full story behind a product's costs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CostExplanation {
+    pub product_id: String,
+    /// How many units of the product were produced within the costing window
+    pub units_produced: Fixed,
+    /// Each cost tag that contributed to the product, sorted by tag id
+    pub tags: Vec<TagExplanation>,
+    /// The sum of each tag's share
+    pub total: Costs,
+    /// The per-unit costs (total / units_produced)
+    pub costs: Costs,
+}
+
+/// Explain the tag-weighted costs for a single product. Takes the same inputs
+/// as `calculate_costs_with_aggregates()`, except the summed costs are broken
+/// out by source. Returns None if the product isn't in `products`.
+pub fn explain_costs_with_aggregates(product_id: &str, products: &HashMap<String, Product>, tag_costs: &HashMap<String, TagCosts>, sum_produced: &HashMap<String, Fixed>) -> BResult<Option<CostExplanation>> {
+    let original = match products.get(product_id) {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    let sum_costs = tag_costs.iter()
+        .map(|(tag_id, costs)| (tag_id.clone(), costs.total()))
+        .collect::<HashMap<_, _>>();
+    let (products, product_tag_totals) = crate::assign_cost_tags(products, &sum_costs, sum_produced);
+    let product = products.get(product_id).ok_or_else(|| BError::CostMissingTag)?;
+    let units_produced = sum_produced.get(product_id).map(|x| x.clone()).unwrap_or(Fixed::zero());
+
+    let mut tags = Vec::new();
+    if !units_produced.is_zero() {
+        for tag in &product.cost_tags {
+            if tag.weight == 0 {
+                continue;
+            }
+            let total_weight = product_tag_totals.get(&tag.id).ok_or_else(|| BError::CostMissingTag)?.clone();
+            let costs = tag_costs.get(&tag.id).map(|x| x.clone()).unwrap_or(Default::default());
+            let share = costs.total().checked_mul(Fixed::from(tag.weight))?.checked_div(Fixed::from(total_weight))?;
+            tags.push(TagExplanation {
+                cost_tag_id: tag.id.clone(),
+                costs,
+                weight: tag.weight,
+                total_weight,
+                ratio: Fixed::from(tag.weight) / Fixed::from(total_weight),
+                implicit: !original.cost_tags.iter().any(|x| x.id == tag.id),
+                share,
+            });
+        }
+    }
+    tags.sort_by(|a, b| a.cost_tag_id.cmp(&b.cost_tag_id));
+    let mut total = Costs::new();
+    for tag in &tags {
+        total = total.checked_add(&tag.share)?;
+    }
+    let costs = if units_produced.is_zero() {
+        Costs::new()
+    } else {
+        total.checked_div(units_produced)?
+    };
+    Ok(Some(CostExplanation {
+        product_id: product_id.to_owned(),
+        units_produced,
+        tags,
+        total,
+        costs,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exonum::crypto::Hash;
+    use models::{
+        cost_tag::CostTagEntry,
+        product::{Unit, Dimensions},
+    };
+    use util::time;
+
+    fn make_product(id: &str, tag_id: &str) -> Product {
+        let now = time::now();
+        Product::new(id, "b9eb0cc2-5b37-4fd1-83fd-8597625aee95", "Widget", &Unit::Millimeter, 0.0, &Dimensions::new(0.0, 0.0, 0.0), &vec![CostTagEntry::new(tag_id, 1)], true, "", &now, &now, None, 1, &Hash::new([1; 32]))
+    }
+
+    #[test]
+    fn explains() {
+        let mut products = HashMap::new();
+        products.insert("widget".to_owned(), make_product("widget", "widgets"));
+        products.insert("gadget".to_owned(), make_product("gadget", "gadgets"));
+        let mut tag_costs = HashMap::new();
+        let widget_order = Costs::new_with_product("steel", 20.0);
+        let widget_labor = Costs::new_with_labor("machinist", 30.0);
+        tag_costs.insert("widgets".to_owned(), TagCosts {
+            orders: widget_order.clone(),
+            labor: widget_labor.clone(),
+            amortization: Costs::new(),
+            sources: vec![
+                CostSource::new(SourceType::Order, "order1", &widget_order),
+                CostSource::new(SourceType::Labor, "labor1", &widget_labor),
+            ],
+        });
+        // nobody carries this tag, so it gets split between everything
+        tag_costs.insert("rent".to_owned(), TagCosts {
+            amortization: Costs::new_with_product("space", 10.0),
+            ..Default::default()
+        });
+        let mut sum_produced = HashMap::new();
+        sum_produced.insert("widget".to_owned(), Fixed::from(10.0));
+        sum_produced.insert("gadget".to_owned(), Fixed::from(5.0));
+
+        let explanation = explain_costs_with_aggregates("widget", &products, &tag_costs, &sum_produced).unwrap().unwrap();
+        assert_eq!(explanation.units_produced, 10.0);
+        assert_eq!(explanation.tags.len(), 2);
+        let rent = &explanation.tags[0];
+        assert_eq!(rent.cost_tag_id, "rent");
+        assert!(rent.implicit);
+        assert_eq!(rent.share, rent.costs.total() * rent.ratio);
+        let widgets = &explanation.tags[1];
+        assert_eq!(widgets.cost_tag_id, "widgets");
+        assert!(!widgets.implicit);
+        assert_eq!(widgets.ratio, 1.0);
+        assert_eq!(widgets.costs.sources.len(), 2);
+        assert_eq!(widgets.share.get("steel"), 20.0);
+        assert_eq!(widgets.share.get_labor("machinist"), 30.0);
+
+        // the explanation should agree with the actual algorithm
+        let sum_costs = tag_costs.iter()
+            .map(|(tag_id, costs)| (tag_id.clone(), costs.total()))
+            .collect::<HashMap<_, _>>();
+        let calculated = crate::calculate_costs_with_aggregates(&products, &sum_costs, &sum_produced).unwrap();
+        assert_eq!(&explanation.costs, calculated.get("widget").unwrap());
+
+        let unproduced = explain_costs_with_aggregates("gizmo", &products, &tag_costs, &sum_produced).unwrap();
+        assert!(unproduced.is_none());
+    }
+}
+
@@ -1,57 +1,108 @@
 //! This library holds the algorithm that costs products and services.
 
+pub mod strategy;
+pub mod explain;
+pub mod conservation;
+
 use std::collections::HashMap;
+use chrono::{DateTime, Utc};
 use error::{BResult, BError};
 use models::{
     costs::Costs,
     cost_tag::{CostTagEntry, Costable},
+    fixed::Fixed,
     order::Order,
     amortization::Amortization,
     product::Product,
     labor::Labor,
 };
+use crate::strategy::{CostingStrategy, CostingInputs};
 
 /// Takes two sets of orders: a company's incoming orders ("sales" in the
 /// current vernacular) and outgoing orders ("purchases").
 ///
 /// The orders *must* be filtered such that both sets are a particular window
 /// in time (ex, the last 365 days) and must be ordered from oldest to newest.
-pub fn calculate_costs(orders_incoming: &Vec<Order>, orders_outgoing: &Vec<Order>, labor: &Vec<Labor>, _wamortization: &HashMap<String, Amortization>, products: &HashMap<String, Product>) -> BResult<HashMap<String, Costs>> {
+/// That same window is passed in via `window_start`/`window_end` so we know
+/// what portion of each amortized cost to include.
+///
+/// The totals are handed off to the given strategy, which does the actual
+/// per-unit costing. `baseline` holds each product's baseline costs (used by
+/// strategies that smooth over time).
+pub fn calculate_costs(strategy: &dyn CostingStrategy, orders_incoming: &Vec<Order>, orders_outgoing: &Vec<Order>, labor: &Vec<Labor>, amortization: &HashMap<String, Amortization>, products: &HashMap<String, Product>, baseline: &HashMap<String, Costs>, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>) -> BResult<HashMap<String, Costs>> {
+    let (sum_costs, sum_produced) = sum_inputs(orders_incoming, orders_outgoing, labor, amortization, window_start, window_end)?;
+    let inputs = CostingInputs {
+        products,
+        sum_costs: &sum_costs,
+        sum_produced: &sum_produced,
+        baseline,
+    };
+    strategy.calculate(&inputs)
+}
+
+/// Boil a company's raw records down into the totals that get fed into a
+/// costing strategy. Returns a map of cost_tag_id -> summed costs and a map of
+/// product_id -> number produced.
+///
+/// Errors if any of the records hold costs we can't add up (negative costs, or
+/// totals that overflow).
+pub fn sum_inputs(orders_incoming: &Vec<Order>, orders_outgoing: &Vec<Order>, labor: &Vec<Labor>, amortization: &HashMap<String, Amortization>, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>) -> BResult<(HashMap<String, Costs>, HashMap<String, Fixed>)> {
     // holds a mapping for cost_tag -> sum costs for all of our cost tags
     let mut sum_costs: HashMap<String, Costs> = HashMap::new();
     // maps product_id -> number produced over order period
-    let mut sum_produced: HashMap<String, f64> = HashMap::new();
+    let mut sum_produced: HashMap<String, Fixed> = HashMap::new();
 
     // add our labor costs into the totals
     for entry in labor {
-        entry.tally_tagged_costs(&mut sum_costs);
+        entry.try_tally_tagged_costs(&mut sum_costs)?;
     }
 
     // add all outgoing orders into the cost totals
     for order in orders_outgoing {
-        order.tally_tagged_costs(&mut sum_costs);
+        order.try_tally_tagged_costs(&mut sum_costs)?;
     }
 
-    // sum how many of each product we have produced
+    // add the slice of our amortized costs that falls within the window
+    tally_amortized_costs(amortization, window_start, window_end, &mut sum_costs)?;
+
+    // sum how many of each product we have produced (see
+    // Order::costed_quantity() for which units count)
     for order in orders_incoming {
         for prod in &order.products {
             let current = sum_produced.entry(prod.product_id.clone()).or_insert(Default::default());
-            *current += prod.quantity;
+            *current = current.checked_add(order.costed_quantity(prod))
+                .ok_or_else(|| BError::CostArithmetic(format!("sum_inputs -- overflow summing units produced for {:?}", prod.product_id)))?;
         }
     }
+    Ok((sum_costs, sum_produced))
+}
 
-    calculate_costs_with_aggregates(products, &sum_costs, &sum_produced)
+/// Add the amortized costs that fall within the given window into a set of
+/// tagged costs. Each amortization spreads its costs evenly over its own
+/// start -> end period, so only the overlap with our window is counted.
+pub fn tally_amortized_costs(amortization: &HashMap<String, Amortization>, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>, sum_costs: &mut HashMap<String, Costs>) -> BResult<()> {
+    for entry in amortization.values() {
+        if entry.is_deleted() {
+            continue;
+        }
+        entry.tally_tagged_costs_within(window_start, window_end, sum_costs)?;
+    }
+    Ok(())
 }
 
-pub fn calculate_costs_with_aggregates(products: &HashMap<String, Product>, sum_costs: &HashMap<String, Costs>, sum_produced: &HashMap<String, f64>) -> BResult<HashMap<String, Costs>> {
+/// Figure out which cost tags each product carries for the purposes of costing,
+/// and the total weight of each tag across all products.
+///
+/// Returns the (possibly modified) products along with a map of
+/// cost_tag_id -> total weight.
+pub(crate) fn assign_cost_tags(products: &HashMap<String, Product>, sum_costs: &HashMap<String, Costs>, sum_produced: &HashMap<String, Fixed>) -> (HashMap<String, Product>, HashMap<String, u64>) {
     let mut tag_tracker: HashMap<String, bool> = HashMap::new();
-    let mut final_costs: HashMap<String, Costs> = HashMap::new();
     let mut product_tag_totals: HashMap<String, u64> = HashMap::new();
     let mut products = products.clone();
 
     // track which cost tags are present in the products
     for (prod_id, product) in products.iter() {
-        if sum_produced.get(prod_id).unwrap_or(&0.0) == &0.0 {
+        if sum_produced.get(prod_id).map(|x| x.is_zero()).unwrap_or(true) {
             // products that were not ordered/produced won't get costs
             continue;
         }
@@ -78,14 +129,27 @@ pub fn calculate_costs_with_aggregates(products: &HashMap<String, Product>, sum_
             product.cost_tags.push(CostTagEntry::new(&tag_id, 1));
         }
     }
-    // for each product, tally up the sum of the cost tags (bucketed by cost
-    // tag)
-    for (_, product) in products.iter() {
+    // for each produced product, tally up the sum of the cost tags (bucketed
+    // by cost tag). products we didn't produce are left out, otherwise their
+    // weights would soak up a slice of each tag's costs that never gets
+    // assigned to anything.
+    for (prod_id, product) in products.iter() {
+        if sum_produced.get(prod_id).map(|x| x.is_zero()).unwrap_or(true) {
+            continue;
+        }
         for tag in &product.cost_tags {
             let current = product_tag_totals.entry(tag.id.clone()).or_insert(0);
-            *current = tag.weight;
+            *current += tag.weight;
         }
     }
+    (products, product_tag_totals)
+}
+
+/// The tag-weighted costing algorithm (see `strategy::TagWeighted`).
+pub fn calculate_costs_with_aggregates(products: &HashMap<String, Product>, sum_costs: &HashMap<String, Costs>, sum_produced: &HashMap<String, Fixed>) -> BResult<HashMap<String, Costs>> {
+    let mut final_costs: HashMap<String, Costs> = HashMap::new();
+    let (products, product_tag_totals) = assign_cost_tags(products, sum_costs, sum_produced);
+
     // for each product, divvy up the costs of each of its cost tags via the
     // tag ratio (as compared to other products) and then divide by the amount
     // produced.
@@ -93,8 +157,8 @@ pub fn calculate_costs_with_aggregates(products: &HashMap<String, Product>, sum_
     // this gives a per-unit cost to each product based on the flow of costs
     // through the cost tags.
     for (prod_id, product) in products.iter() {
-        let num_produced: f64 = sum_produced.get(prod_id).unwrap_or(&0.0).clone();
-        if num_produced == 0.0 {
+        let num_produced: Fixed = sum_produced.get(prod_id).map(|x| x.clone()).unwrap_or(Fixed::zero());
+        if num_produced.is_zero() {
             // products that were not produced have no cost (and will not have
             // their cost tags tallied in the totals, meaning they will not
             // "steal" costs from products that were actively produced)
@@ -102,12 +166,17 @@ pub fn calculate_costs_with_aggregates(products: &HashMap<String, Product>, sum_
         } else {
             let mut prod_cost_sum = Costs::new();
             for tag in &product.cost_tags {
-                let total: f64 = product_tag_totals.get(&tag.id).ok_or_else(|| BError::CostMissingTag)?.clone() as f64;
-                let tag_ratio: f64 = tag.weight as f64 / total;
+                if tag.weight == 0 {
+                    continue;
+                }
+                let total = Fixed::from(product_tag_totals.get(&tag.id).ok_or_else(|| BError::CostMissingTag)?.clone());
                 let tag_costs = sum_costs.get(&tag.id).map(|x| x.clone()).unwrap_or(Costs::new());
-                prod_cost_sum = prod_cost_sum + (tag_costs * tag_ratio);
+                // multiply by the tag weight before dividing by the total so
+                // we only round once
+                let share = tag_costs.checked_mul(Fixed::from(tag.weight))?.checked_div(total)?;
+                prod_cost_sum = prod_cost_sum.checked_add(&share)?;
             }
-            final_costs.insert(prod_id.clone(), prod_cost_sum / num_produced);
+            final_costs.insert(prod_id.clone(), prod_cost_sum.checked_div(num_produced)?);
         }
     }
     Ok(final_costs)
@@ -121,6 +190,8 @@ pub mod tests {
     use models::order::{Order, ProcessStatus, ProductEntry};
     use models::labor::Labor;
     use models::product::{Product, Unit, Dimensions};
+    use models::amortization::Amortization;
+    use chrono::Duration;
     use util::time;
 
     fn make_hash() -> Hash {
@@ -134,10 +205,85 @@ pub mod tests {
         let labor = test_labor();
         let amortization = HashMap::new();
         let products = test_products();
-        let costs = calculate_costs(&orders_incoming, &orders_outgoing, &labor, &amortization, &products).expect("costs failed");
+        let now = time::now();
+        let costs = calculate_costs(&strategy::TagWeighted, &orders_incoming, &orders_outgoing, &labor, &amortization, &products, &HashMap::new(), &(now - Duration::days(365)), &now).expect("costs failed");
         println!(">>> final costs: {:?}", costs);
     }
 
+    #[test]
+    fn amortizes() {
+        let now = time::now();
+        let window_start = now - Duration::days(365);
+        let mut amortization = HashMap::new();
+        let laser = Amortization::new(
+            "1b3c2ea1-7b44-4ad9-8b10-1e2e2c5e4a45",
+            "b9eb0cc2-5b37-4fd1-83fd-8597625aee95",
+            "Industrial laser",
+            &Costs::new_with_labor("laser technician", 4000.0),
+            &vec![CostTagEntry::new("operating", 1)],
+            &(now - Duration::days(730)),
+            &(now + Duration::days(730)),
+            "",
+            &now,
+            &now,
+            None,
+            1,
+            &make_hash(),
+        );
+        let deleted = Amortization::new(
+            "5e0e3b0e-52b3-43f5-b3ed-0cf3e3c3a6f0",
+            "b9eb0cc2-5b37-4fd1-83fd-8597625aee95",
+            "Laser we decided was a bad idea",
+            &Costs::new_with_labor("laser technician", 9000.0),
+            &vec![CostTagEntry::new("operating", 1)],
+            &(now - Duration::days(730)),
+            &(now + Duration::days(730)),
+            "",
+            &now,
+            &now,
+            Some(&now),
+            2,
+            &make_hash(),
+        );
+        amortization.insert(laser.id.clone(), laser);
+        amortization.insert(deleted.id.clone(), deleted);
+        let mut sum_costs = HashMap::new();
+        tally_amortized_costs(&amortization, &window_start, &now, &mut sum_costs).unwrap();
+        assert_eq!(sum_costs.len(), 1);
+        assert_eq!(sum_costs.get("operating").unwrap().get_labor("laser technician"), 1000.0);
+    }
+
+    #[test]
+    fn errors_on_bad_costs() {
+        let now = time::now();
+        // negative costs can sneak in via protobuf, since Costs::track() is
+        // the only thing guarding against them
+        let mut costs = Costs::new();
+        costs.labor.insert("laser technician".to_owned(), Fixed::from(-4000.0));
+        let laser = Amortization::new(
+            "1b3c2ea1-7b44-4ad9-8b10-1e2e2c5e4a45",
+            "b9eb0cc2-5b37-4fd1-83fd-8597625aee95",
+            "Industrial laser",
+            &costs,
+            &vec![CostTagEntry::new("operating", 1)],
+            &(now - Duration::days(730)),
+            &(now + Duration::days(730)),
+            "",
+            &now,
+            &now,
+            None,
+            1,
+            &make_hash(),
+        );
+        let mut amortization = HashMap::new();
+        amortization.insert(laser.id.clone(), laser);
+        let res = sum_inputs(&vec![], &vec![], &vec![], &amortization, &(now - Duration::days(365)), &now);
+        match res {
+            Err(BError::CostArithmetic(_)) => {}
+            _ => panic!("expected a cost arithmetic error, got {:?}", res),
+        }
+    }
+
     fn test_orders_incoming() -> Vec<Order> {
         let fakehash = make_hash();
         vec![
@@ -0,0 +1,214 @@
+//! Costing strategies. A strategy takes a company's aggregated costs (bucketed
+//! by cost tag) and how much of each product it produced, and turns those into
+//! per-unit costs for each product.
+//!
+//! Which strategy a network uses is part of its costing config, which lets us
+//! run alternative algorithms against the same data and compare them.
+
+use std::collections::HashMap;
+use error::{BResult, BError};
+use models::{
+    costs::Costs,
+    costing::Strategy,
+    fixed::Fixed,
+    product::Product,
+};
+
+/// Everything a strategy gets to work with
+pub struct CostingInputs<'a> {
+    /// The products we're costing, keyed by product id
+    pub products: &'a HashMap<String, Product>,
+    /// cost_tag_id -> the sum of costs for that tag
+    pub sum_costs: &'a HashMap<String, Costs>,
+    /// product_id -> the number of units produced
+    pub sum_produced: &'a HashMap<String, Fixed>,
+    /// product_id -> the costs the product started the current baseline
+    /// period with (if any). These stay put for a whole costing window, so
+    /// recalculating with the same data always lands on the same costs.
+    pub baseline: &'a HashMap<String, Costs>,
+}
+
+pub trait CostingStrategy {
+    /// Calculate the per-unit costs for each product. Every product in
+    /// `inputs.products` should get an entry, even if it's empty.
+    fn calculate(&self, inputs: &CostingInputs) -> BResult<HashMap<String, Costs>>;
+}
+
+/// Grab the strategy implementation for a configured strategy type
+pub fn from_config(strategy: &Strategy) -> Box<dyn CostingStrategy> {
+    match strategy {
+        Strategy::TagWeighted => Box::new(TagWeighted),
+        Strategy::EqualSplit => Box::new(EqualSplit),
+        Strategy::MovingAverage => Box::new(MovingAverage::default()),
+    }
+}
+
+fn num_produced(inputs: &CostingInputs, product_id: &str) -> Fixed {
+    inputs.sum_produced.get(product_id).map(|x| x.clone()).unwrap_or(Fixed::zero())
+}
+
+/// Our original algorithm. Each tag's costs are divvied up between the
+/// products carrying that tag (by tag weight), then divided by the number of
+/// units produced.
+pub struct TagWeighted;
+
+impl CostingStrategy for TagWeighted {
+    fn calculate(&self, inputs: &CostingInputs) -> BResult<HashMap<String, Costs>> {
+        crate::calculate_costs_with_aggregates(inputs.products, inputs.sum_costs, inputs.sum_produced)
+    }
+}
+
+/// Ignores cost tags entirely. All of the company's costs are added together
+/// and split evenly over every unit it produced, regardless of which product
+/// the unit belongs to.
+pub struct EqualSplit;
+
+impl CostingStrategy for EqualSplit {
+    fn calculate(&self, inputs: &CostingInputs) -> BResult<HashMap<String, Costs>> {
+        let mut total_costs = Costs::new();
+        for costs in inputs.sum_costs.values() {
+            total_costs = total_costs.checked_add(costs)?;
+        }
+        let mut total_units = Fixed::zero();
+        for prod_id in inputs.products.keys() {
+            total_units = total_units.checked_add(num_produced(inputs, prod_id))
+                .ok_or_else(|| BError::CostArithmetic(String::from("EqualSplit -- overflow summing units produced")))?;
+        }
+        let mut final_costs = HashMap::new();
+        for prod_id in inputs.products.keys() {
+            let costs = if num_produced(inputs, prod_id).is_zero() {
+                Costs::new()
+            } else {
+                total_costs.checked_div(total_units)?
+            };
+            final_costs.insert(prod_id.clone(), costs);
+        }
+        Ok(final_costs)
+    }
+}
+
+/// Runs the tag-weighted algorithm, but instead of replacing a product's costs
+/// outright, moves them `weight` of the way from the product's baseline costs
+/// towards the new ones. This smooths out spikes (a single big order, say) at
+/// the cost of reacting more slowly to real changes.
+///
+/// The baseline is fixed for a window at a time (see `CostingInputs`), not the
+/// result of the last calculation. Otherwise every recalculation would drag
+/// the costs further along even when nothing changed.
+pub struct MovingAverage {
+    pub weight: Fixed,
+}
+
+impl MovingAverage {
+    pub fn new(weight: Fixed) -> Self {
+        Self { weight }
+    }
+}
+
+impl Default for MovingAverage {
+    fn default() -> Self {
+        Self::new(Fixed::from(0.25))
+    }
+}
+
+impl CostingStrategy for MovingAverage {
+    fn calculate(&self, inputs: &CostingInputs) -> BResult<HashMap<String, Costs>> {
+        let current = TagWeighted.calculate(inputs)?;
+        let keep = Fixed::from(1i64) - self.weight;
+        let mut final_costs = HashMap::new();
+        for (prod_id, costs) in current {
+            // products we didn't produce get no costs, same as the other
+            // strategies
+            let baseline = inputs.baseline.get(&prod_id).filter(|x| !x.is_zero());
+            let averaged = match baseline {
+                Some(baseline) if !costs.is_zero() => {
+                    baseline.checked_mul(keep)?.checked_add(&costs.checked_mul(self.weight)?)?
+                }
+                _ => costs,
+            };
+            final_costs.insert(prod_id, averaged);
+        }
+        Ok(final_costs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exonum::crypto::Hash;
+    use models::{
+        cost_tag::CostTagEntry,
+        product::{Unit, Dimensions},
+    };
+    use util::time;
+
+    fn make_product(id: &str, tag_id: &str) -> Product {
+        let now = time::now();
+        Product::new(id, "b9eb0cc2-5b37-4fd1-83fd-8597625aee95", "Widget", &Unit::Millimeter, 0.0, &Dimensions::new(0.0, 0.0, 0.0), &vec![CostTagEntry::new(tag_id, 1)], true, "", &now, &now, None, 1, &Hash::new([1; 32]))
+    }
+
+    fn make_inputs() -> (HashMap<String, Product>, HashMap<String, Costs>, HashMap<String, Fixed>) {
+        let mut products = HashMap::new();
+        products.insert("widget".to_owned(), make_product("widget", "widgets"));
+        products.insert("gadget".to_owned(), make_product("gadget", "gadgets"));
+        products.insert("gizmo".to_owned(), make_product("gizmo", "gizmos"));
+        let mut sum_costs = HashMap::new();
+        sum_costs.insert("widgets".to_owned(), Costs::new_with_labor("machinist", 30.0));
+        sum_costs.insert("gadgets".to_owned(), Costs::new_with_labor("machinist", 10.0));
+        let mut sum_produced = HashMap::new();
+        sum_produced.insert("widget".to_owned(), Fixed::from(10.0));
+        sum_produced.insert("gadget".to_owned(), Fixed::from(10.0));
+        (products, sum_costs, sum_produced)
+    }
+
+    #[test]
+    fn tag_weighted() {
+        let (products, sum_costs, sum_produced) = make_inputs();
+        let baseline = HashMap::new();
+        let inputs = CostingInputs { products: &products, sum_costs: &sum_costs, sum_produced: &sum_produced, baseline: &baseline };
+        let costs = from_config(&Strategy::TagWeighted).calculate(&inputs).unwrap();
+        assert_eq!(costs.get("widget").unwrap().get_labor("machinist"), 3.0);
+        assert_eq!(costs.get("gadget").unwrap().get_labor("machinist"), 1.0);
+        assert!(costs.get("gizmo").unwrap().is_zero());
+    }
+
+    #[test]
+    fn equal_split() {
+        let (products, sum_costs, sum_produced) = make_inputs();
+        let baseline = HashMap::new();
+        let inputs = CostingInputs { products: &products, sum_costs: &sum_costs, sum_produced: &sum_produced, baseline: &baseline };
+        let costs = from_config(&Strategy::EqualSplit).calculate(&inputs).unwrap();
+        assert_eq!(costs.get("widget").unwrap().get_labor("machinist"), 2.0);
+        assert_eq!(costs.get("gadget").unwrap().get_labor("machinist"), 2.0);
+        assert!(costs.get("gizmo").unwrap().is_zero());
+    }
+
+    #[test]
+    fn moving_average() {
+        let (products, sum_costs, sum_produced) = make_inputs();
+        let mut baseline = HashMap::new();
+        baseline.insert("widget".to_owned(), Costs::new_with_labor("machinist", 7.0));
+        baseline.insert("gizmo".to_owned(), Costs::new_with_labor("machinist", 7.0));
+        let inputs = CostingInputs { products: &products, sum_costs: &sum_costs, sum_produced: &sum_produced, baseline: &baseline };
+        let costs = from_config(&Strategy::MovingAverage).calculate(&inputs).unwrap();
+        // 7 * 0.75 + 3 * 0.25
+        assert_eq!(costs.get("widget").unwrap().get_labor("machinist"), 6.0);
+        // no baseline costs, so we take the new ones as-is
+        assert_eq!(costs.get("gadget").unwrap().get_labor("machinist"), 1.0);
+        // not produced, so no costs
+        assert!(costs.get("gizmo").unwrap().is_zero());
+    }
+
+    #[test]
+    fn moving_average_is_idempotent() {
+        let (products, sum_costs, sum_produced) = make_inputs();
+        let mut baseline = HashMap::new();
+        baseline.insert("widget".to_owned(), Costs::new_with_labor("machinist", 7.0));
+        let inputs = CostingInputs { products: &products, sum_costs: &sum_costs, sum_produced: &sum_produced, baseline: &baseline };
+        let strategy = from_config(&Strategy::MovingAverage);
+        let costs1 = strategy.calculate(&inputs).unwrap();
+        let costs2 = strategy.calculate(&inputs).unwrap();
+        assert_eq!(costs1, costs2);
+        assert_eq!(costs2.get("widget").unwrap().get_labor("machinist"), 6.0);
+    }
+}
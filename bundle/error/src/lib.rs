@@ -14,11 +14,17 @@ pub enum BError {
     #[fail(display = "Invalid role")]
     InvalidRole,
 
+    #[fail(display = "Invalid fixed-point number")]
+    InvalidFixed,
+
     #[fail(display = "Missing product in costing data")]
     CostMissingProduct,
 
     #[fail(display = "Missing tag in costing data")]
     CostMissingTag,
+
+    #[fail(display = "Invalid cost arithmetic: {}", _0)]
+    CostArithmetic(String),
 }
 
 pub type BResult<T> = Result<T, BError>;
@@ -18,16 +18,25 @@ pub enum Permission {
     UserSetPubkey,
     UserDelete,
 
+    RegionCreate,
+    RegionUpdate,
+
+    OccupationCreate,
+    OccupationMerge,
+
     CompanyCreateSyndicate,
     CompanyCreatePrivate,
+    CompanyCreatePublic,
     CompanySetApproved,
     CompanyAdminUpdate,
     CompanyAdminDelete,
     CompanySetType,
+    CompanySetRegion,
     CompanyUpdateMembers,
     CompanyClockIn,
     CompanyClockOut,
     CompanySetLaborWage,
+    CompanyVoidLabor,
     CompanyAdminClock,
 
     ProductCreate,
@@ -40,12 +49,18 @@ pub enum Permission {
     CostTagUpdate,
     CostTagDelete,
 
+    AmortizationCreate,
+    AmortizationUpdate,
+    AmortizationDelete,
+
     ResourceTagCreate,
     ResourceTagDelete,
 
     OrderCreate,
     OrderUpdate,
     OrderAdminUpdate,
+
+    CostsRepair,
 }
 
 /// Define the system-wide roles users can have.
@@ -85,8 +100,10 @@ impl Role {
             Role::CompanyAdmin => {
                 vec![
                     Permission::CompanySetApproved,
+                    Permission::CompanySetRegion,
                     Permission::CompanyAdminUpdate,
                     Permission::CompanyAdminDelete,
+                    Permission::CompanyVoidLabor,
                 ]
             }
             Role::ProductAdmin => {
@@ -108,6 +125,7 @@ impl Role {
             }
             Role::Bank => {
                 vec![
+                    Permission::CompanyCreatePublic,
                     Permission::CompanySetType,
                     Permission::CompanySetApproved,
                 ]
@@ -128,6 +146,9 @@ impl Role {
                     Permission::CostTagCreate,
                     Permission::CostTagUpdate,
                     Permission::CostTagDelete,
+                    Permission::AmortizationCreate,
+                    Permission::AmortizationUpdate,
+                    Permission::AmortizationDelete,
                     Permission::OrderCreate,
                     Permission::OrderUpdate,
                 ]
@@ -156,6 +177,15 @@ impl Role {
         }
         false
     }
+
+    /// Whether or not this role can be granted within a single region (as
+    /// opposed to system-wide).
+    pub fn is_regional(&self) -> bool {
+        match *self {
+            Role::CompanyAdmin | Role::Bank | Role::TagAdmin => true,
+            _ => false,
+        }
+    }
 }
 
 impl ProtobufConvert for Role {
@@ -220,9 +250,45 @@ pub mod tests {
         assert!(comp_admin.can(&Permission::CompanyAdminUpdate));
         assert!(comp_admin.can(&Permission::CompanyAdminDelete));
         assert!(!comp_admin.can(&Permission::CompanySetType));
+        assert!(!comp_admin.can(&Permission::CompanyCreatePublic));
+
+        let bank = Role::Bank;
+        assert!(bank.can(&Permission::CompanyCreatePublic));
+        assert!(!bank.can(&Permission::CompanyCreatePrivate));
+
+        let user = Role::User;
+        assert!(user.can(&Permission::CompanyCreatePrivate));
+        assert!(user.can(&Permission::CompanyCreateSyndicate));
+        assert!(!user.can(&Permission::CompanyCreatePublic));
+        assert!(!user.can(&Permission::RegionCreate));
+        assert!(!user.can(&Permission::CompanySetRegion));
+
+        assert!(super_admin.can(&Permission::RegionCreate));
+        assert!(super_admin.can(&Permission::RegionUpdate));
+        assert!(comp_admin.can(&Permission::CompanySetRegion));
+        assert!(!comp_admin.can(&Permission::RegionCreate));
+        assert!(super_admin.can(&Permission::OccupationCreate));
+        assert!(super_admin.can(&Permission::OccupationMerge));
+        assert!(!user.can(&Permission::OccupationCreate));
+        assert!(!user.can(&Permission::OccupationMerge));
+        assert!(super_admin.can(&Permission::CompanyVoidLabor));
+        assert!(comp_admin.can(&Permission::CompanyVoidLabor));
+        assert!(!user.can(&Permission::CompanyVoidLabor));
+        assert!(!traveller.can(&Permission::CompanyVoidLabor));
 
         // TODO: ProductAdmin
         // TODO: OrderAdmin
     }
+
+    #[test]
+    fn regional_roles() {
+        assert!(Role::CompanyAdmin.is_regional());
+        assert!(Role::Bank.is_regional());
+        assert!(Role::TagAdmin.is_regional());
+        assert!(!Role::SuperAdmin.is_regional());
+        assert!(!Role::IdentityAdmin.is_regional());
+        assert!(!Role::TimeTraveler.is_regional());
+        assert!(!Role::User.is_regional());
+    }
 }
 
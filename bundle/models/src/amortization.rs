@@ -1,7 +1,12 @@
+use std::collections::HashMap;
 use exonum::crypto::Hash;
 use chrono::{DateTime, Utc};
+use error::BResult;
+use util;
 use crate::{
     costs::Costs,
+    cost_tag::{CostTagEntry, Costable},
+    fixed::Fixed,
     proto,
 };
 
@@ -12,30 +17,204 @@ pub struct Amortization {
     pub company_id: String,
     pub name: String,
     pub costs: Costs,
+    pub cost_tags: Vec<CostTagEntry>,
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
     pub meta: String,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
+    pub deleted: DateTime<Utc>,
     pub history_len: u64,
     pub history_hash: Hash,
 }
 
 impl Amortization {
-    pub fn new(id: &str, company_id: &str, name: &str, costs: &Costs, start: &DateTime<Utc>, end: &DateTime<Utc>, meta: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, history_hash: &Hash) -> Self {
+    pub fn new(id: &str, company_id: &str, name: &str, costs: &Costs, cost_tags: &Vec<CostTagEntry>, start: &DateTime<Utc>, end: &DateTime<Utc>, meta: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, deleted: Option<&DateTime<Utc>>, history_len: u64, history_hash: &Hash) -> Self {
         Self {
             id: id.to_owned(),
             company_id: company_id.to_owned(),
             name: name.to_owned(),
             costs: costs.clone(),
+            cost_tags: cost_tags.clone(),
             start: start.clone(),
             end: end.clone(),
             meta: meta.to_owned(),
             created: created.clone(),
             updated: updated.clone(),
+            deleted: deleted.unwrap_or(&util::time::default_time()).clone(),
             history_len,
             history_hash: history_hash.clone(),
         }
     }
+
+    pub fn update(&self, name: Option<&str>, costs: Option<&Costs>, cost_tags: Option<&Vec<CostTagEntry>>, start: Option<&DateTime<Utc>>, end: Option<&DateTime<Utc>>, meta: Option<&str>, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.company_id,
+            name.unwrap_or(&self.name),
+            costs.unwrap_or(&self.costs),
+            cost_tags.unwrap_or(&self.cost_tags),
+            start.unwrap_or(&self.start),
+            end.unwrap_or(&self.end),
+            meta.unwrap_or(&self.meta),
+            &self.created,
+            updated,
+            Some(&self.deleted),
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn delete(&self, deleted: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.company_id,
+            &self.name,
+            &self.costs,
+            &self.cost_tags,
+            &self.start,
+            &self.end,
+            &self.meta,
+            &self.created,
+            &self.updated,
+            Some(deleted),
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted != util::time::default_time()
+    }
+
+    /// Returns the portion of this amortization's costs that fall within the
+    /// given time window. Costs are spread evenly over the amortization's
+    /// start -> end period, so a window covering a quarter of that period gets
+    /// a quarter of the costs.
+    pub fn costs_within(&self, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>) -> BResult<Costs> {
+        let total = (self.end - self.start).num_seconds();
+        let start = if &self.start > window_start { &self.start } else { window_start };
+        let end = if &self.end < window_end { &self.end } else { window_end };
+        let overlap = (*end - *start).num_seconds();
+        if total <= 0 || overlap <= 0 {
+            return Ok(Costs::new());
+        }
+        self.costs.checked_mul(Fixed::from(overlap))?.checked_div(Fixed::from(total))
+    }
+
+    /// Add this amortization's costs (only the portion that falls within the
+    /// given window) to an existing hash of tagged costs.
+    pub fn tally_tagged_costs_within(&self, window_start: &DateTime<Utc>, window_end: &DateTime<Utc>, cost_collection: &mut HashMap<String, Costs>) -> BResult<()> {
+        let windowed = Self {
+            costs: self.costs_within(window_start, window_end)?,
+            ..self.clone()
+        };
+        windowed.try_tally_tagged_costs(cost_collection)
+    }
+}
+
+impl Costable for Amortization {
+    fn get_costs(&self) -> Costs {
+        self.costs.clone()
+    }
+
+    fn get_cost_tags(&self) -> Vec<CostTagEntry> {
+        self.cost_tags.clone()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::Duration;
+    use util;
+
+    fn make_date() -> DateTime<Utc> {
+        chrono::offset::Utc::now()
+    }
+
+    fn make_hash() -> Hash {
+        Hash::new([1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4])
+    }
+
+    fn make_amortization() -> Amortization {
+        let date = make_date();
+        Amortization::new(
+            "1b3c2ea1-7b44-4ad9-8b10-1e2e2c5e4a45",
+            "b9eb0cc2-5b37-4fd1-83fd-8597625aee95",
+            "Very large laser, for frickin sharks",
+            &Costs::new_with_labor("laser technician", 4000.0),
+            &vec![CostTagEntry::new("1111", 1)],
+            &util::time::from_timestamp(0),
+            &util::time::from_timestamp(3600 * 24 * 400),
+            "",
+            &date,
+            &date,
+            None,
+            0,
+            &make_hash(),
+        )
+    }
+
+    #[test]
+    fn updates() {
+        let amortization = make_amortization();
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let amortization2 = amortization.update(
+            Some("Slightly smaller laser"),
+            Some(&Costs::new_with_labor("laser technician", 2000.0)),
+            None,
+            None,
+            Some(&util::time::from_timestamp(3600 * 24 * 800)),
+            None,
+            &date2,
+            &hash2
+        );
+        assert_eq!(amortization2.company_id, amortization.company_id);
+        assert_eq!(amortization.name, "Very large laser, for frickin sharks");
+        assert_eq!(amortization2.name, "Slightly smaller laser");
+        assert_eq!(amortization2.costs.get_labor("laser technician"), 2000.0);
+        assert_eq!(amortization2.cost_tags, amortization.cost_tags);
+        assert_eq!(amortization2.start, amortization.start);
+        assert_eq!(amortization2.end, util::time::from_timestamp(3600 * 24 * 800));
+        assert_eq!(amortization.created, amortization2.created);
+        assert!(amortization.updated != amortization2.updated);
+        assert_eq!(amortization2.updated, date2);
+        assert_eq!(amortization2.history_len, amortization.history_len + 1);
+        assert_eq!(amortization2.history_hash, hash2);
+    }
+
+    #[test]
+    fn deletes() {
+        let amortization = make_amortization();
+        assert_eq!(amortization.deleted, util::time::default_time());
+        assert!(!amortization.is_deleted());
+        let date2 = make_date();
+        let hash2 = Hash::new([56, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let amortization2 = amortization.delete(&date2, &hash2);
+        assert_eq!(amortization2.deleted, date2);
+        assert!(amortization2.is_deleted());
+    }
+
+    #[test]
+    fn spreads_costs_over_window() {
+        let amortization = make_amortization();
+        let start = amortization.start.clone();
+        // a window covering the first quarter of the amortization period
+        let costs = amortization.costs_within(&(start - Duration::days(50)), &(start + Duration::days(100))).unwrap();
+        assert_eq!(costs.get_labor("laser technician"), 1000.0);
+        // the whole thing
+        let costs = amortization.costs_within(&(start - Duration::days(1)), &(start + Duration::days(900))).unwrap();
+        assert_eq!(costs.get_labor("laser technician"), 4000.0);
+        // outside the window entirely
+        let costs = amortization.costs_within(&(start + Duration::days(500)), &(start + Duration::days(865))).unwrap();
+        assert!(costs.is_zero());
+
+        let mut tagged = HashMap::new();
+        amortization.tally_tagged_costs_within(&(start + Duration::days(300)), &(start + Duration::days(665)), &mut tagged).unwrap();
+        assert_eq!(tagged.get("1111").unwrap().get_labor("laser technician"), 1000.0);
+    }
 }
 
@@ -18,6 +18,16 @@ proto_enum! {
     proto::company::CompanyType
 }
 
+proto_enum! {
+    enum ApprovalStatus {
+        Unknown = 0,
+        Pending = 1,
+        Approved = 2,
+        Rejected = 3,
+    };
+    proto::company::Company_ApprovalStatus
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum Permission {
     All,
@@ -33,6 +43,7 @@ pub enum Permission {
     LaborSetClock,
     LaborTagCost,
     LaborSetWage,
+    LaborReviewCorrection,
 
     ProductCreate,
     ProductUpdate,
@@ -42,14 +53,20 @@ pub enum Permission {
     OrderCreate,
     OrderUpdateProcessStatus,
     OrderUpdateCostTags,
+    OrderFinalize,
     OrderUpdateShipping,
     OrderUpdateShippingDates,
     OrderCancel,
+    OrderCreateReturn,
     OrderTagCost,
 
     CostTagCreate,
     CostTagUpdate,
     CostTagDelete,
+
+    AmortizationCreate,
+    AmortizationUpdate,
+    AmortizationDelete,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -60,6 +77,7 @@ pub enum Role {
     LaborAdmin,
     ProductAdmin,
     CostTagAdmin,
+    AmortizationAdmin,
     CostTaggerAdmin,
     CostTaggerLabor,
     CostTaggerProduct,
@@ -90,6 +108,7 @@ impl Role {
                 vec![
                     Permission::LaborSetClock,
                     Permission::LaborSetWage,
+                    Permission::LaborReviewCorrection,
                 ]
             }
             Role::ProductAdmin => {
@@ -106,6 +125,13 @@ impl Role {
                     Permission::CostTagDelete,
                 ]
             }
+            Role::AmortizationAdmin => {
+                vec![
+                    Permission::AmortizationCreate,
+                    Permission::AmortizationUpdate,
+                    Permission::AmortizationDelete,
+                ]
+            }
             Role::CostTaggerAdmin => {
                 vec![
                     Permission::LaborTagCost,
@@ -132,12 +158,17 @@ impl Role {
                 vec![
                     Permission::OrderCreate,
                     Permission::OrderUpdateCostTags,
+                    Permission::OrderFinalize,
+                    Permission::OrderUpdateShipping,
                     Permission::OrderCancel,
+                    Permission::OrderCreateReturn,
                 ]
             }
             Role::Supplier => {
                 vec![
                     Permission::OrderUpdateProcessStatus,
+                    Permission::OrderUpdateShipping,
+                    Permission::OrderUpdateShippingDates,
                     Permission::OrderCancel,
                 ]
             }
@@ -221,6 +252,28 @@ impl TxCreatePrivateFounder {
     }
 }
 
+#[derive(Clone, Debug, Default, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::company::TxCreateSyndicate_Member", serde_pb_convert)]
+pub struct TxCreateSyndicateMember {
+    pub member_id: String,
+    pub user_id: String,
+    pub occupation: String,
+    pub wage: f64,
+    pub default_cost_tags: Vec<CostTagEntry>,
+}
+
+impl TxCreateSyndicateMember {
+    pub fn new(member_id: &str, user_id: &str, occupation: &str, wage: f64, default_cost_tags: &Vec<CostTagEntry>) -> Self {
+        Self {
+            member_id: member_id.to_owned(),
+            user_id: user_id.to_owned(),
+            occupation: occupation.to_owned(),
+            wage,
+            default_cost_tags: default_cost_tags.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, ProtobufConvert)]
 #[exonum(pb = "proto::company::Company", serde_pb_convert)]
 pub struct Company {
@@ -229,6 +282,8 @@ pub struct Company {
     pub region_id: String,  // should be an Option, but protobufs are stupid
     pub email: String,
     pub name: String,
+    pub approval_status: ApprovalStatus,
+    pub approval_reason: String,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
     pub history_len: u64,
@@ -236,13 +291,15 @@ pub struct Company {
 }
 
 impl Company {
-    pub fn new(id: &str, ty: &CompanyType, region_id: Option<&str>, email: &str, name: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, &history_hash: &Hash) -> Self {
+    pub fn new(id: &str, ty: &CompanyType, region_id: Option<&str>, email: &str, name: &str, approval_status: &ApprovalStatus, approval_reason: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, &history_hash: &Hash) -> Self {
         Self {
             id: id.to_owned(),
             ty: ty.clone(),
             region_id: region_id.map(|x| x.to_owned()).unwrap_or("".to_owned()),
             email: email.to_owned(),
             name: name.to_owned(),
+            approval_status: approval_status.clone(),
+            approval_reason: approval_reason.to_owned(),
             created: created.clone(),
             updated: updated.clone(),
             history_len,
@@ -257,6 +314,8 @@ impl Company {
             Some(&self.region_id),
             email.unwrap_or(&self.email),
             name.unwrap_or(&self.name),
+            &self.approval_status,
+            &self.approval_reason,
             &self.created,
             updated,
             self.history_len + 1,
@@ -271,6 +330,24 @@ impl Company {
             Some(&self.region_id),
             &self.email,
             &self.name,
+            &self.approval_status,
+            &self.approval_reason,
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn set_approval(self, approval_status: &ApprovalStatus, approval_reason: &str, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.ty,
+            Some(&self.region_id),
+            &self.email,
+            &self.name,
+            approval_status,
+            approval_reason,
             &self.created,
             updated,
             self.history_len + 1,
@@ -278,6 +355,26 @@ impl Company {
         )
     }
 
+    pub fn set_region(self, region_id: Option<&str>, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.ty,
+            region_id,
+            &self.email,
+            &self.name,
+            &self.approval_status,
+            &self.approval_reason,
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn is_approved(&self) -> bool {
+        self.approval_status == ApprovalStatus::Approved
+    }
+
     pub fn is_active(&self) -> bool {
         true && !self.is_deleted()
     }
@@ -354,6 +451,8 @@ pub mod tests {
             None,
             "homayun@friendless.com",
             "LEMONADE STANDS UNLIMITED",
+            &ApprovalStatus::Pending,
+            "",
             &date,
             &date,
             0,
@@ -416,5 +515,41 @@ pub mod tests {
         assert!(company.history_hash != company2.history_hash);
         assert_eq!(company2.history_hash, hash2);
     }
+
+    #[test]
+    fn sets_approval() {
+        let company = make_company();
+        assert_eq!(company.approval_status, ApprovalStatus::Pending);
+        assert!(!company.is_approved());
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 47, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let company2 = company.clone().set_approval(&ApprovalStatus::Rejected, "lemonade is not a controlled substance, but your 'lemonade' is", &date2, &hash2);
+        assert_eq!(company2.approval_status, ApprovalStatus::Rejected);
+        assert_eq!(company2.approval_reason, "lemonade is not a controlled substance, but your 'lemonade' is");
+        assert!(!company2.is_approved());
+        assert_eq!(company.name, company2.name);
+        assert_eq!(company2.updated, date2);
+        assert_eq!(company.history_len, company2.history_len - 1);
+        let company3 = company2.clone().set_approval(&ApprovalStatus::Approved, "ok it's just lemonade", &date2, &hash2);
+        assert!(company3.is_approved());
+    }
+
+    #[test]
+    fn sets_region() {
+        let company = make_company();
+        assert_eq!(company.region_id, "");
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 47, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 47]);
+        let company2 = company.clone().set_region(Some("3e5c1a8d-2b7f-4c6e-9a0d-1f2e3d4c5b6a"), &date2, &hash2);
+        assert_eq!(company2.region_id, "3e5c1a8d-2b7f-4c6e-9a0d-1f2e3d4c5b6a");
+        assert_eq!(company2.name, company.name);
+        assert_eq!(company2.approval_status, company.approval_status);
+        assert_eq!(company2.updated, date2);
+        assert_eq!(company2.history_len, company.history_len + 1);
+        let company3 = company2.clone().set_region(None, &date2, &hash2);
+        assert_eq!(company3.region_id, "");
+    }
 }
 
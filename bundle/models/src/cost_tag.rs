@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use exonum::crypto::Hash;
 use chrono::{DateTime, Utc};
+use error::BResult;
 use crate::{
     costs::Costs,
+    fixed::Fixed,
     proto,
 };
 use util;
@@ -97,6 +99,12 @@ pub trait Costable {
     /// Get the costs for this object
     fn get_costs(&self) -> Costs;
 
+    /// Like `get_costs()`, but errors instead of panicking (or silently
+    /// overflowing) if the object's costs can't be added up
+    fn try_get_costs(&self) -> BResult<Costs> {
+        Ok(self.get_costs())
+    }
+
     /// Get the cost tags for this object
     fn get_cost_tags(&self) -> Vec<CostTagEntry>;
 
@@ -110,11 +118,11 @@ pub trait Costable {
         } else {
             vec![CostTagEntry::new("_uncategorized", 1)]
         };
-        let cost_tag_sum = cost_tags.iter().fold(0, |acc, x| acc + x.weight) as f64;
+        let cost_tag_sum = Fixed::from(cost_tags.iter().fold(0, |acc, x| acc + x.weight));
         for cost_tag in &cost_tags {
-            let ratio = (cost_tag.weight as f64) / cost_tag_sum;
+            // multiply before dividing so we only round once
             let current = cost_collection.entry(cost_tag.id.clone()).or_insert(Default::default());
-            *current = current.clone() + (object_costs.clone() * ratio);
+            *current = current.clone() + ((object_costs.clone() * Fixed::from(cost_tag.weight)) / cost_tag_sum);
         }
     }
 
@@ -124,6 +132,37 @@ pub trait Costable {
         self.tally_tagged_costs(&mut final_costs);
         final_costs
     }
+
+    /// Like `tally_tagged_costs()` but with checked arithmetic, so negative or
+    /// overflowing costs give an error instead of a bogus total. An object
+    /// whose cost tags all have zero weight adds nothing (same as the
+    /// unchecked version).
+    fn try_tally_tagged_costs(&self, cost_collection: &mut HashMap<String, Costs>) -> BResult<()> {
+        let object_costs = self.try_get_costs()?;
+        let object_cost_tags = self.get_cost_tags();
+        let cost_tags = if object_cost_tags.len() > 0 {
+            object_cost_tags
+        } else {
+            vec![CostTagEntry::new("_uncategorized", 1)]
+        };
+        let cost_tag_sum = Fixed::from(cost_tags.iter().fold(0, |acc, x| acc + x.weight));
+        if cost_tag_sum.is_zero() {
+            return Ok(());
+        }
+        for cost_tag in &cost_tags {
+            let tagged = object_costs.checked_mul(Fixed::from(cost_tag.weight))?.checked_div(cost_tag_sum)?;
+            let current = cost_collection.entry(cost_tag.id.clone()).or_insert(Default::default());
+            *current = current.checked_add(&tagged)?;
+        }
+        Ok(())
+    }
+
+    /// Like `get_tagged_costs()` but with checked arithmetic
+    fn try_get_tagged_costs(&self) -> BResult<HashMap<String, Costs>> {
+        let mut final_costs = HashMap::new();
+        self.try_tally_tagged_costs(&mut final_costs)?;
+        Ok(final_costs)
+    }
 }
 
 #[cfg(test)]
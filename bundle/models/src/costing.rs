@@ -0,0 +1,63 @@
+use crate::proto;
+
+/// The default costing window: one year
+pub const DEFAULT_WINDOW_SECS: i64 = 3600 * 24 * 365;
+
+/// The default number of queued companies we recalculate per block
+pub const DEFAULT_PROPAGATION_LIMIT: u64 = 20;
+
+proto_enum! {
+    enum Strategy {
+        TagWeighted = 0,
+        EqualSplit = 1,
+        MovingAverage = 2,
+    };
+    proto::costing::CostingConfig_Strategy
+}
+
+#[derive(Clone, Debug, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::costing::CostingConfig", serde_pb_convert)]
+pub struct CostingConfig {
+    pub window_secs: i64,
+    pub min_finalized: u64,
+    pub use_aggregate: bool,
+    pub strategy: Strategy,
+    pub propagation_limit: u64,
+}
+
+impl CostingConfig {
+    pub fn new(window_secs: i64, min_finalized: u64, use_aggregate: bool, strategy: &Strategy, propagation_limit: u64) -> Self {
+        Self {
+            window_secs,
+            min_finalized,
+            use_aggregate,
+            strategy: strategy.clone(),
+            propagation_limit,
+        }
+    }
+
+    /// Whether or not this config is sane enough to run the network on
+    pub fn is_valid(&self) -> bool {
+        self.window_secs > 0
+    }
+}
+
+impl Default for CostingConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW_SECS, 2, true, &Strategy::TagWeighted, DEFAULT_PROPAGATION_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates() {
+        assert!(CostingConfig::default().is_valid());
+        assert!(CostingConfig::new(3600, 0, false, &Strategy::EqualSplit, 0).is_valid());
+        assert!(!CostingConfig::new(0, 2, true, &Strategy::TagWeighted, 20).is_valid());
+        assert!(!CostingConfig::new(-3600, 2, true, &Strategy::TagWeighted, 20).is_valid());
+    }
+}
+
@@ -1,12 +1,17 @@
 use std::collections::HashMap;
-use crate::proto;
+use chrono::{DateTime, Utc};
+use error::{BResult, BError};
+use crate::{
+    fixed::Fixed,
+    proto,
+};
 use std::ops::{Add, Sub, Mul, Div};
 
 #[derive(Clone, Debug, Default, PartialEq, ProtobufConvert)]
 #[exonum(pb = "proto::costs::Costs", serde_pb_convert)]
 pub struct Costs {
-    pub products: HashMap<String, f64>,
-    pub labor: HashMap<String, f64>,
+    pub products: HashMap<String, Fixed>,
+    pub labor: HashMap<String, Fixed>,
 }
 
 impl Costs {
@@ -17,61 +22,79 @@ impl Costs {
         }
     }
 
-    pub fn new_with_labor(ty: &str, labor: f64) -> Self {
+    pub fn new_with_labor<V: Into<Fixed>>(ty: &str, labor: V) -> Self {
         let mut costs = Self::new();
         costs.track_labor(ty, labor);
         costs
     }
 
-    pub fn new_with_product(prod: &str, val: f64) -> Self {
+    pub fn new_with_product<V: Into<Fixed>>(prod: &str, val: V) -> Self {
         let mut costs = Self::new();
         costs.track(prod, val);
         costs
     }
 
-    pub fn track(&mut self, prod: &str, val: f64) {
-        if val < 0.0 {
-            panic!("Costs::track() -- given value must be >= 0.0")
+    /// Add a product cost. Panics if the value is negative, so use
+    /// `try_track()` for anything that isn't a known-good value.
+    pub fn track<V: Into<Fixed>>(&mut self, prod: &str, val: V) {
+        if let Err(e) = self.try_track(prod, val) {
+            panic!("Costs::track() -- {}", e)
         }
-        let entry = self.products.entry(prod.to_string()).or_insert(0.0);
-        *entry += val;
     }
 
-    pub fn track_labor(&mut self, ty: &str, val: f64) {
-        if val < 0.0 {
-            panic!("Costs::track_labor() -- given value must be >= 0.0")
+    /// Add a labor cost. Panics if the value is negative, so use
+    /// `try_track_labor()` for anything that isn't a known-good value.
+    pub fn track_labor<V: Into<Fixed>>(&mut self, ty: &str, val: V) {
+        if let Err(e) = self.try_track_labor(ty, val) {
+            panic!("Costs::track_labor() -- {}", e)
         }
-        let entry = self.labor.entry(ty.to_string()).or_insert(0.0);
-        *entry += val;
     }
 
-    pub fn labor(&self) -> &HashMap<String, f64> {
+    /// Add a product cost, erroring if the value is negative or the addition
+    /// overflows
+    pub fn try_track<V: Into<Fixed>>(&mut self, prod: &str, val: V) -> BResult<()> {
+        let val = checked_entry("track", prod, Some(val.into()))?;
+        let total = checked_entry("track", prod, self.get(prod).checked_add(val))?;
+        self.products.insert(prod.to_string(), total);
+        Ok(())
+    }
+
+    /// Add a labor cost, erroring if the value is negative or the addition
+    /// overflows
+    pub fn try_track_labor<V: Into<Fixed>>(&mut self, ty: &str, val: V) -> BResult<()> {
+        let val = checked_entry("track_labor", ty, Some(val.into()))?;
+        let total = checked_entry("track_labor", ty, self.get_labor(ty).checked_add(val))?;
+        self.labor.insert(ty.to_string(), total);
+        Ok(())
+    }
+
+    pub fn labor(&self) -> &HashMap<String, Fixed> {
         &self.labor
     }
 
-    pub fn products(&self) -> &HashMap<String, f64> {
+    pub fn products(&self) -> &HashMap<String, Fixed> {
         &self.products
     }
 
     #[allow(dead_code)]
-    pub fn get(&self, product: &str) -> f64 {
-        *self.products.get(product).unwrap_or(&0.0)
+    pub fn get(&self, product: &str) -> Fixed {
+        self.products.get(product).map(|x| x.clone()).unwrap_or(Fixed::zero())
     }
 
     #[allow(dead_code)]
-    pub fn get_labor(&self, ty: &str) -> f64 {
-        *self.labor.get(ty).unwrap_or(&0.0)
+    pub fn get_labor(&self, ty: &str) -> Fixed {
+        self.labor.get(ty).map(|x| x.clone()).unwrap_or(Fixed::zero())
     }
 
     /// Test if we have an empty cost set
     pub fn is_zero(&self) -> bool {
         for (_, val) in self.labor.iter() {
-            if val > &0.0 {
+            if val > &Fixed::zero() {
                 return false;
             }
         }
         for (_, val) in self.products.iter() {
-            if val > &0.0 {
+            if val > &Fixed::zero() {
                 return false;
             }
         }
@@ -84,32 +107,132 @@ impl Costs {
     pub fn take(&mut self, costs: &Costs) -> Costs {
         let mut new_costs = Costs::new();
         for (k, lval) in self.labor.iter_mut() {
-            let mut rval = costs.labor().get(k).unwrap_or(&0.0) + 0.0;
-            let val = if lval > &mut rval { rval } else { lval.clone() };
+            let rval = costs.get_labor(k);
+            let val = if *lval > rval { rval } else { lval.clone() };
             *lval -= val;
-            new_costs.track_labor(k, val.clone());
+            new_costs.track_labor(k, val);
         }
         for (k, lval) in self.products.iter_mut() {
-            let mut rval = costs.products().get(k).unwrap_or(&0.0) + 0.0;
-            let val = if lval > &mut rval { rval } else { lval.clone() };
+            let rval = costs.get(k);
+            let val = if *lval > rval { rval } else { lval.clone() };
             *lval -= val;
-            new_costs.track(k, val.clone());
+            new_costs.track(k, val);
         }
         new_costs
     }
+
+    /// Like `+`, but errors on overflow or if any cost comes out negative
+    pub fn checked_add(&self, other: &Costs) -> BResult<Costs> {
+        let mut costs = self.clone();
+        for (k, val) in other.labor() {
+            let entry = costs.labor.entry(k.to_owned()).or_insert(Fixed::zero());
+            *entry = checked_entry("add", k, entry.checked_add(*val))?;
+        }
+        for (k, val) in other.products() {
+            let entry = costs.products.entry(k.to_owned()).or_insert(Fixed::zero());
+            *entry = checked_entry("add", k, entry.checked_add(*val))?;
+        }
+        Ok(costs)
+    }
+
+    /// Like `-`, but errors on overflow or if any cost comes out negative
+    /// (where `-` would silently give a negative cost)
+    pub fn checked_sub(&self, other: &Costs) -> BResult<Costs> {
+        let mut costs = self.clone();
+        for (k, val) in other.labor() {
+            let entry = costs.labor.entry(k.to_owned()).or_insert(Fixed::zero());
+            *entry = checked_entry("sub", k, entry.checked_sub(*val))?;
+        }
+        for (k, val) in other.products() {
+            let entry = costs.products.entry(k.to_owned()).or_insert(Fixed::zero());
+            *entry = checked_entry("sub", k, entry.checked_sub(*val))?;
+        }
+        Ok(costs)
+    }
+
+    /// Like `* Fixed`, but errors on overflow or if any cost comes out
+    /// negative
+    pub fn checked_mul(&self, rhs: Fixed) -> BResult<Costs> {
+        let mut costs = self.clone();
+        for (k, val) in costs.labor.iter_mut() {
+            *val = checked_entry("mul", k, val.checked_mul(rhs))?;
+        }
+        for (k, val) in costs.products.iter_mut() {
+            *val = checked_entry("mul", k, val.checked_mul(rhs))?;
+        }
+        Ok(costs)
+    }
+
+    /// Like `/ Fixed`, but errors when dividing by zero (instead of giving
+    /// zero or panicking) or if any cost comes out negative
+    pub fn checked_div(&self, rhs: Fixed) -> BResult<Costs> {
+        if rhs.is_zero() {
+            return Err(BError::CostArithmetic(String::from("div -- divide by zero")));
+        }
+        let mut costs = self.clone();
+        for (k, val) in costs.labor.iter_mut() {
+            *val = checked_entry("div", k, val.checked_div(rhs))?;
+        }
+        for (k, val) in costs.products.iter_mut() {
+            *val = checked_entry("div", k, val.checked_div(rhs))?;
+        }
+        Ok(costs)
+    }
+
+    /// Like `/ Costs`, but errors if any of our costs would be divided by zero
+    /// (including costs missing from `rhs`)
+    pub fn checked_div_costs(&self, rhs: &Costs) -> BResult<Costs> {
+        let mut costs = self.clone();
+        for (k, val) in costs.labor.iter_mut() {
+            *val = checked_entry("div", k, val.checked_div(rhs.get_labor(k)))?;
+        }
+        for k in rhs.labor().keys() {
+            costs.labor.entry(k.clone()).or_insert(Fixed::zero());
+        }
+        for (k, val) in costs.products.iter_mut() {
+            *val = checked_entry("div", k, val.checked_div(rhs.get(k)))?;
+        }
+        for k in rhs.products().keys() {
+            costs.products.entry(k.clone()).or_insert(Fixed::zero());
+        }
+        Ok(costs)
+    }
+
+    /// Run each of our labor keys through `rekey`, combining the labor costs
+    /// of any keys that end up the same. Product costs are left alone.
+    pub fn rekey_labor<F>(&self, rekey: F) -> BResult<Costs>
+        where F: Fn(&str) -> String
+    {
+        let mut costs = Costs::new();
+        costs.products = self.products.clone();
+        for (k, val) in self.labor.iter() {
+            costs.try_track_labor(&rekey(k), val.clone())?;
+        }
+        Ok(costs)
+    }
+}
+
+/// Turn the result of a checked operation on a single cost into an error if
+/// the operation failed or gave a negative cost
+fn checked_entry(op: &str, key: &str, val: Option<Fixed>) -> BResult<Fixed> {
+    match val {
+        Some(x) if x >= Fixed::zero() => Ok(x),
+        Some(x) => Err(BError::CostArithmetic(format!("{} -- negative cost for {:?} ({})", op, key, x))),
+        None => Err(BError::CostArithmetic(format!("{} -- overflow or divide by zero for {:?}", op, key))),
+    }
 }
 
 impl Add for Costs {
     type Output = Self;
 
     fn add(mut self, other: Self) -> Self {
-        for k in other.labor().keys() {
-            let entry = self.labor.entry(k.to_owned()).or_insert(0.0);
-            *entry += other.labor().get(k).unwrap();
+        for (k, val) in other.labor() {
+            let entry = self.labor.entry(k.to_owned()).or_insert(Fixed::zero());
+            *entry += *val;
         }
-        for k in other.products().keys() {
-            let entry = self.products.entry(k.to_owned()).or_insert(0.0);
-            *entry += other.products().get(k).unwrap();
+        for (k, val) in other.products() {
+            let entry = self.products.entry(k.to_owned()).or_insert(Fixed::zero());
+            *entry += *val;
         }
         self
     }
@@ -119,13 +242,13 @@ impl Sub for Costs {
     type Output = Self;
 
     fn sub(mut self, other: Self) -> Self {
-        for k in other.labor().keys() {
-            let entry = self.labor.entry(k.to_owned()).or_insert(0.0);
-            *entry -= other.labor().get(k).unwrap();
+        for (k, val) in other.labor() {
+            let entry = self.labor.entry(k.to_owned()).or_insert(Fixed::zero());
+            *entry -= *val;
         }
-        for k in other.products().keys() {
-            let entry = self.products.entry(k.to_owned()).or_insert(0.0);
-            *entry -= other.products().get(k).unwrap();
+        for (k, val) in other.products() {
+            let entry = self.products.entry(k.to_owned()).or_insert(Fixed::zero());
+            *entry -= *val;
         }
         self
     }
@@ -136,24 +259,24 @@ impl Mul for Costs {
 
     fn mul(mut self, rhs: Self) -> Self {
         for (k, val) in self.labor.iter_mut() {
-            *val *= rhs.labor().get(k).unwrap_or(&0.0);
+            *val = *val * rhs.get_labor(k);
         }
         for (k, val) in self.products.iter_mut() {
-            *val *= rhs.products().get(k).unwrap_or(&0.0);
+            *val = *val * rhs.get(k);
         }
         self
     }
 }
 
-impl Mul<f64> for Costs {
+impl Mul<Fixed> for Costs {
     type Output = Self;
 
-    fn mul(mut self, rhs: f64) -> Self {
+    fn mul(mut self, rhs: Fixed) -> Self {
         for (_, val) in self.labor.iter_mut() {
-            *val *= rhs;
+            *val = *val * rhs;
         }
         for (_, val) in self.products.iter_mut() {
-            *val *= rhs;
+            *val = *val * rhs;
         }
         self
     }
@@ -164,37 +287,37 @@ impl Div for Costs {
 
     fn div(mut self, rhs: Self) -> Self::Output {
         for (k, v) in self.labor.iter_mut() {
-            let div = rhs.labor().get(k).unwrap_or(&0.0);
+            let div = rhs.get_labor(k);
             #[cfg(feature = "panic-div0")]
             {
-                if *div == 0.0 {
+                if div.is_zero() {
                     panic!("Costs::div() -- divide by zero for {:?}", k);
                 }
             }
-            *v /= div;
+            *v = *v / div;
         }
         for (k, _) in rhs.labor().iter() {
             match self.labor.get(k) {
                 None => {
-                    self.labor.insert(k.clone(), 0.0);
+                    self.labor.insert(k.clone(), Fixed::zero());
                 }
                 _ => {}
             }
         }
         for (k, v) in self.products.iter_mut() {
-            let div = rhs.products().get(k).unwrap_or(&0.0);
+            let div = rhs.get(k);
             #[cfg(feature = "panic-div0")]
             {
-                if *div == 0.0 {
+                if div.is_zero() {
                     panic!("Costs::div() -- divide by zero for {:?}", k);
                 }
             }
-            *v /= div;
+            *v = *v / div;
         }
         for (k, _) in rhs.products().iter() {
             match self.products.get(k) {
                 None => {
-                    self.products.insert(k.clone(), 0.0);
+                    self.products.insert(k.clone(), Fixed::zero());
                 }
                 _ => {}
             }
@@ -203,21 +326,21 @@ impl Div for Costs {
     }
 }
 
-impl Div<f64> for Costs {
+impl Div<Fixed> for Costs {
     type Output = Self;
 
-    fn div(mut self, rhs: f64) -> Self::Output {
+    fn div(mut self, rhs: Fixed) -> Self::Output {
         #[cfg(feature = "panic-div0")]
         {
-            if rhs == 0.0 {
+            if rhs.is_zero() {
                 panic!("Costs::div() -- divide by zero");
             }
         }
         for (_, v) in self.labor.iter_mut() {
-            *v /= rhs
+            *v = *v / rhs
         }
         for (_, v) in self.products.iter_mut() {
-            *v /= rhs
+            *v = *v / rhs
         }
         self
     }
@@ -244,18 +367,38 @@ impl CostsTally {
         self.count += 1;
     }
 
-    /// Subtract an entry from the cost bucket
+    /// Subtract an entry from the cost bucket. Panics if the bucket is empty,
+    /// so use `try_subtract()` for anything that isn't a known-good value.
     pub fn subtract(&mut self, costs: &Costs) {
         self.costs = self.costs.clone() - costs.clone();
         self.count -= 1;
     }
 
-    pub fn add_single(&mut self, val: f64) {
+    /// Like `add()`, but errors if the costs overflow or come out negative
+    pub fn try_add(&mut self, costs: &Costs) -> BResult<()> {
+        let count = self.count.checked_add(1)
+            .ok_or_else(|| BError::CostArithmetic(String::from("tally add -- too many entries")))?;
+        self.costs = self.costs.checked_add(costs)?;
+        self.count = count;
+        Ok(())
+    }
+
+    /// Like `subtract()`, but errors (instead of panicking or going negative)
+    /// if the bucket is empty or the costs come out negative
+    pub fn try_subtract(&mut self, costs: &Costs) -> BResult<()> {
+        let count = self.count.checked_sub(1)
+            .ok_or_else(|| BError::CostArithmetic(String::from("tally subtract -- no entries to subtract from")))?;
+        self.costs = self.costs.checked_sub(costs)?;
+        self.count = count;
+        Ok(())
+    }
+
+    pub fn add_single<V: Into<Fixed>>(&mut self, val: V) {
         self.costs.track("_single", val);
         self.count += 1;
     }
 
-    pub fn subtract_single(&mut self, val: f64) {
+    pub fn subtract_single<V: Into<Fixed>>(&mut self, val: V) {
         let mut tmp_costs = Costs::new();
         tmp_costs.track("_single", val);
         self.costs = self.costs.clone() - tmp_costs;
@@ -266,7 +409,7 @@ impl CostsTally {
         self.costs.clone()
     }
 
-    pub fn total_single(&self) -> f64 {
+    pub fn total_single(&self) -> Fixed {
         self.costs.get("_single")
     }
 
@@ -310,6 +453,30 @@ impl CostsTallyMap {
         }
     }
 
+    pub fn try_add(&mut self, key: &str, costs: &Costs) -> BResult<()> {
+        let entry = self.map.entry(key.to_owned()).or_insert(CostsTally::new());
+        entry.try_add(costs)
+    }
+
+    pub fn try_subtract(&mut self, key: &str, costs: &Costs) -> BResult<()> {
+        let entry = self.map.entry(key.to_owned()).or_insert(CostsTally::new());
+        entry.try_subtract(costs)
+    }
+
+    pub fn try_add_map(&mut self, map: &HashMap<String, Costs>) -> BResult<()> {
+        for (key, val) in map.iter() {
+            self.try_add(key, val)?;
+        }
+        Ok(())
+    }
+
+    pub fn try_subtract_map(&mut self, map: &HashMap<String, Costs>) -> BResult<()> {
+        for (key, val) in map.iter() {
+            self.try_subtract(key, val)?;
+        }
+        Ok(())
+    }
+
     pub fn get(&self, key: &str) -> CostsTally {
         self.map.get(key).map(|x| x.clone()).unwrap_or(CostsTally::new())
     }
@@ -324,9 +491,31 @@ impl CostsTallyMap {
     }
 }
 
+/// Records what a product's costs were as of a given block height. The
+/// timestamp is the time the previous block was committed, which every node
+/// agrees on.
+#[derive(Clone, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::costs::CostsHistoryEntry", serde_pb_convert)]
+pub struct CostsHistoryEntry {
+    pub costs: Costs,
+    pub height: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl CostsHistoryEntry {
+    pub fn new(costs: &Costs, height: u64, timestamp: &DateTime<Utc>) -> Self {
+        Self {
+            costs: costs.clone(),
+            height,
+            timestamp: timestamp.clone(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use exonum::proto::ProtobufConvert;
 
     #[test]
     fn add() {
@@ -358,7 +547,7 @@ mod tests {
         costs1.track("widget", 3.1);
         costs1.track("iron", 8.5);
 
-        let costs = costs1 * 5.2;
+        let costs = costs1 * Fixed::from(5.2);
         assert_eq!(costs.get_labor("miner"), 6.0 * 5.2);
         assert_eq!(costs.get_labor("widgetmaker"), 3.0 * 5.2);
         assert_eq!(costs.get("widget"), 3.1 * 5.2);
@@ -401,14 +590,14 @@ mod tests {
     }
 
     #[test]
-    fn div_f64() {
+    fn div_fixed() {
         let mut costs1 = Costs::new();
 
         costs1.track_labor("widgetmaker", 6.0);
         costs1.track("widget", 3.1);
         costs1.track("oil", 5.6);
 
-        let costs = costs1 / 1.3;
+        let costs = costs1 / Fixed::from(1.3);
         assert_eq!(costs.get_labor("widgetmaker"), 6.0 / 1.3);
         assert_eq!(costs.get("widget"), 3.1 / 1.3);
         assert_eq!(costs.get("oil"), 5.6 / 1.3);
@@ -436,38 +625,81 @@ mod tests {
         costs1.track("iron", 8.5);
 
         let costs = costs1 / costs2;
-        assert_eq!(costs.get("iron"), 8.5 / 0.0);
+        assert_eq!(costs.get("iron"), 0.0);
     }
 
     #[cfg(feature = "panic-div0")]
     #[test]
     #[should_panic]
-    fn div_f64_by_0() {
+    fn div_fixed_by_0() {
         let mut costs1 = Costs::new();
 
         costs1.track_labor("dancer", 6.0);
         costs1.track("widget", 3.1);
         costs1.track("oil", 5.6);
 
-        let costs = costs1 / 0.0;
-        assert_eq!(costs.get_labor("dancer"), 6.0 / 0.0);
-        assert_eq!(costs.get("widget"), 3.1 / 0.0);
-        assert_eq!(costs.get("oil"), 5.6 / 0.0);
+        let costs = costs1 / Fixed::zero();
+        assert_eq!(costs.get_labor("dancer"), 0.0);
     }
 
     #[cfg(not(feature = "panic-div0"))]
     #[test]
-    fn div_f64_by_0() {
+    fn div_fixed_by_0() {
         let mut costs1 = Costs::new();
 
         costs1.track_labor("dancer", 6.0);
         costs1.track("widget", 3.1);
         costs1.track("oil", 5.6);
 
-        let costs = costs1 / 0.0;
-        assert_eq!(costs.get_labor("dancer"), 6.0 / 0.0);
-        assert_eq!(costs.get("widget"), 3.1 / 0.0);
-        assert_eq!(costs.get("oil"), 5.6 / 0.0);
+        // dividing by zero gives zero, which keeps results reproducible (no
+        // infinities or NaNs making their way into the chain)
+        let costs = costs1 / Fixed::zero();
+        assert_eq!(costs.get_labor("dancer"), 0.0);
+        assert_eq!(costs.get("widget"), 0.0);
+        assert_eq!(costs.get("oil"), 0.0);
+    }
+
+    #[test]
+    fn checked() {
+        let mut costs1 = Costs::new();
+        let mut costs2 = Costs::new();
+        costs1.track_labor("miner", 6.0);
+        costs1.track("iron", 8.5);
+        costs2.track_labor("miner", 2.0);
+        costs2.track("oil", 5.6);
+
+        let costs = costs1.checked_add(&costs2).unwrap();
+        assert_eq!(costs, costs1.clone() + costs2.clone());
+        let costs = costs.checked_sub(&costs2).unwrap();
+        assert_eq!(costs.get_labor("miner"), 6.0);
+        assert_eq!(costs.get("oil"), 0.0);
+        assert_eq!(costs1.checked_mul(Fixed::from(2.0)).unwrap().get("iron"), 17.0);
+        assert_eq!(costs1.checked_div(Fixed::from(2.0)).unwrap().get_labor("miner"), 3.0);
+        assert!(costs1.checked_div_costs(&Costs::new_with_labor("miner", 3.0)).is_err());
+        assert_eq!(costs1.checked_div_costs(&costs1).unwrap().get("iron"), 1.0);
+
+        // the unchecked versions would give negatives, zeroes, or panic here
+        assert!(costs2.checked_sub(&costs1).is_err());
+        assert!(costs1.checked_mul(Fixed::from(-1.0)).is_err());
+        assert!(costs1.checked_div(Fixed::zero()).is_err());
+        assert!(costs1.checked_add(&Costs::new_with_labor("miner", Fixed::from_raw(i128::max_value()))).is_err());
+        assert!(costs1.try_track("iron", -1.0).is_err());
+        assert!(costs1.try_track_labor("miner", -1.0).is_err());
+        assert_eq!(costs1.get("iron"), 8.5);
+        costs1.try_track("iron", 1.5).unwrap();
+        assert_eq!(costs1.get("iron"), 10.0);
+    }
+
+    #[test]
+    fn serializes_as_decimal_strings() {
+        let mut costs = Costs::new();
+        costs.track_labor("miner", 0.1);
+        costs.track_labor("miner", 0.2);
+        costs.track("widget", 1.0 / 3.0);
+        let pb = costs.to_pb();
+        assert_eq!(pb.get_labor().get("miner").unwrap(), "0.3");
+        assert_eq!(pb.get_products().get("widget").unwrap(), "0.333333333");
+        assert_eq!(Costs::from_pb(pb).unwrap(), costs);
     }
 
     #[test]
@@ -506,6 +738,16 @@ mod tests {
         assert_eq!(bucket.total().get("widget"), 42.0);
         assert_eq!(bucket.len(), 1);
 
+        // the checked versions won't let a bucket go negative or empty out
+        // past zero
+        assert!(bucket.clone().try_subtract(&Costs::new_with_product("widget", 43.0)).is_err());
+        let mut empty_bucket = CostsTally::new();
+        assert!(empty_bucket.try_subtract(&Costs::new()).is_err());
+        assert_eq!(empty_bucket.len(), 0);
+        empty_bucket.try_add(&Costs::new_with_product("widget", 1.0)).unwrap();
+        empty_bucket.try_subtract(&Costs::new_with_product("widget", 1.0)).unwrap();
+        assert_eq!(empty_bucket.len(), 0);
+
         let mut single_bucket = CostsTally::new();
         single_bucket.add_single(64.5);
         single_bucket.add_single(12.0);
@@ -554,5 +796,22 @@ mod tests {
         assert_eq!(op.total(), Costs::new_with_product("BULLDOZER", 20.0 + 2.0));
         assert_eq!(op.len(), 1);
     }
+
+    #[test]
+    fn rekey_labor() {
+        let mut costs = Costs::new();
+        costs.track_labor("widget builder", 6.0);
+        costs.track_labor("widget-builder", 2.5);
+        costs.track_labor("miner", 3.0);
+        costs.track("iron", 8.5);
+
+        let costs2 = costs.rekey_labor(|k| k.replace("-", " ")).unwrap();
+        assert_eq!(costs2.labor().len(), 2);
+        assert_eq!(costs2.get_labor("widget builder"), 6.0 + 2.5);
+        assert_eq!(costs2.get_labor("widget-builder"), 0.0);
+        assert_eq!(costs2.get_labor("miner"), 3.0);
+        assert_eq!(costs2.get("iron"), 8.5);
+        assert_eq!(costs.rekey_labor(|k| k.to_owned()).unwrap(), costs);
+    }
 }
 
@@ -0,0 +1,398 @@
+//! A fixed-point decimal number type. We use this for costs and quantities so
+//! that every node comes up with the exact same numbers (down to the bit) no
+//! matter what platform it runs on or how floats get formatted.
+//!
+//! Values are stored as a count of billionths (nine decimal places). The
+//! rounding rules are:
+//!
+//! - Addition and subtraction are exact.
+//! - Multiplication, division, and conversion from floats or decimal strings
+//!   with more than nine decimal places round to the nearest billionth, with
+//!   ties rounded away from zero.
+//! - Dividing by zero gives zero (or panics if the `panic-div0` feature is
+//!   enabled).
+//!
+//! The operators panic on overflow (in debug builds). Anything that runs
+//! inside of a transaction should use the `checked_*` versions, which return
+//! None on overflow or division by zero instead.
+//!
+//! On the wire (protobuf and JSON) a `Fixed` is a canonical decimal string,
+//! like "12.5" or "-0.000000001".
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub, SubAssign, Mul, Div, Neg};
+use std::str::FromStr;
+use exonum::proto::ProtobufConvert;
+use serde::{Serialize, Serializer, Deserialize, Deserializer, de};
+use error::BError;
+
+/// How many decimal places we track
+pub const DECIMALS: usize = 9;
+
+/// 10^DECIMALS
+const SCALE: i128 = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i128);
+
+impl Fixed {
+    pub fn zero() -> Self {
+        Fixed(0)
+    }
+
+    /// Create a Fixed from a raw count of billionths
+    pub fn from_raw(raw: i128) -> Self {
+        Fixed(raw)
+    }
+
+    /// Get the raw count of billionths this number holds
+    pub fn raw(&self) -> i128 {
+        self.0
+    }
+
+    /// Convert to a float. This is lossy, so only use it for display or
+    /// comparisons in tests, never for anything that gets stored.
+    pub fn to_f64(&self) -> f64 {
+        (self.0 as f64) / (SCALE as f64)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn abs(&self) -> Self {
+        Fixed(self.0.abs())
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Fixed)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Fixed)
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        self.0.checked_mul(rhs.0).map(|x| Fixed(div_round(x, SCALE)))
+    }
+
+    /// Returns None when dividing by zero, regardless of `panic-div0`
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        if rhs.0 == 0 {
+            return None;
+        }
+        self.0.checked_mul(SCALE).map(|x| Fixed(div_round(x, rhs.0)))
+    }
+}
+
+/// Divide two integers, rounding the result to the nearest integer with ties
+/// going away from zero.
+fn div_round(num: i128, den: i128) -> i128 {
+    let quot = num / den;
+    let rem = num % den;
+    if rem.abs() * 2 >= den.abs() {
+        if (num < 0) == (den < 0) { quot + 1 } else { quot - 1 }
+    } else {
+        quot
+    }
+}
+
+impl From<f64> for Fixed {
+    fn from(val: f64) -> Self {
+        // f64::round() rounds half away from zero. NaN converts to 0 and
+        // infinities saturate.
+        Fixed((val * (SCALE as f64)).round() as i128)
+    }
+}
+
+impl From<i64> for Fixed {
+    fn from(val: i64) -> Self {
+        Fixed((val as i128) * SCALE)
+    }
+}
+
+impl From<u64> for Fixed {
+    fn from(val: u64) -> Self {
+        Fixed((val as i128) * SCALE)
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let int = (self.0 / SCALE).abs();
+        let frac = (self.0 % SCALE).abs();
+        if frac == 0 {
+            write!(f, "{}{}", sign, int)
+        } else {
+            let frac_str = format!("{:0width$}", frac, width = DECIMALS);
+            write!(f, "{}{}.{}", sign, int, frac_str.trim_end_matches('0'))
+        }
+    }
+}
+
+impl FromStr for Fixed {
+    type Err = BError;
+
+    /// Parse a plain decimal string ("12", "-3.25", ".5"). An empty string
+    /// parses as zero (so unset protobuf fields work). Digits past our
+    /// precision are rounded half away from zero.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "" {
+            return Ok(Fixed::zero());
+        }
+        let (negative, digits) = if s.starts_with('-') {
+            (true, &s[1..])
+        } else if s.starts_with('+') {
+            (false, &s[1..])
+        } else {
+            (false, s)
+        };
+        let mut parts = digits.splitn(2, '.');
+        let int_part = parts.next().unwrap_or("");
+        let frac_part = parts.next().unwrap_or("");
+        let all_digits = |x: &str| x.chars().all(|c| c.is_ascii_digit());
+        if (int_part == "" && frac_part == "") || !all_digits(int_part) || !all_digits(frac_part) {
+            return Err(BError::InvalidFixed);
+        }
+        let int_val: i128 = if int_part == "" {
+            0
+        } else {
+            int_part.parse().map_err(|_| BError::InvalidFixed)?
+        };
+        let mut frac_val: i128 = 0;
+        for c in frac_part.chars().take(DECIMALS) {
+            frac_val = (frac_val * 10) + (c as i128 - '0' as i128);
+        }
+        for _ in frac_part.len()..DECIMALS {
+            frac_val *= 10;
+        }
+        let round_up = frac_part.chars().nth(DECIMALS).map(|c| c >= '5').unwrap_or(false);
+        let mut raw = int_val.checked_mul(SCALE)
+            .and_then(|x| x.checked_add(frac_val))
+            .ok_or(BError::InvalidFixed)?;
+        if round_up {
+            raw += 1;
+        }
+        Ok(Fixed(if negative { -raw } else { raw }))
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Fixed(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Fixed(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Fixed(-self.0)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Fixed(div_round(self.0 * rhs.0, SCALE))
+    }
+}
+
+impl Div for Fixed {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        if rhs.0 == 0 {
+            #[cfg(feature = "panic-div0")]
+            {
+                panic!("Fixed::div() -- divide by zero");
+            }
+            #[cfg(not(feature = "panic-div0"))]
+            {
+                return Fixed::zero();
+            }
+        }
+        Fixed(div_round(self.0 * SCALE, rhs.0))
+    }
+}
+
+impl Sum for Fixed {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Fixed::zero(), |acc, x| acc + x)
+    }
+}
+
+/// Compare against a float by first converting the float (with rounding) into
+/// a Fixed. Mostly useful for tests.
+impl PartialEq<f64> for Fixed {
+    fn eq(&self, other: &f64) -> bool {
+        *self == Fixed::from(*other)
+    }
+}
+
+impl ProtobufConvert for Fixed {
+    type ProtoStruct = String;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        self.to_string()
+    }
+
+    fn from_pb(pb: Self::ProtoStruct) -> Result<Self, failure::Error> {
+        pb.parse::<Fixed>()
+            .map_err(|e| From::from(e))
+    }
+}
+
+impl Serialize for Fixed {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+struct FixedVisitor;
+
+impl<'de> de::Visitor<'de> for FixedVisitor {
+    type Value = Fixed;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal string or a number")
+    }
+
+    fn visit_str<E>(self, val: &str) -> Result<Fixed, E>
+        where E: de::Error
+    {
+        val.parse::<Fixed>().map_err(|_| E::custom(BError::InvalidFixed))
+    }
+
+    fn visit_i64<E>(self, val: i64) -> Result<Fixed, E>
+        where E: de::Error
+    {
+        Ok(Fixed::from(val))
+    }
+
+    fn visit_u64<E>(self, val: u64) -> Result<Fixed, E>
+        where E: de::Error
+    {
+        Ok(Fixed::from(val))
+    }
+
+    fn visit_f64<E>(self, val: f64) -> Result<Fixed, E>
+        where E: de::Error
+    {
+        Ok(Fixed::from(val))
+    }
+}
+
+impl<'de> Deserialize<'de> for Fixed {
+    fn deserialize<D>(deserializer: D) -> Result<Fixed, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_any(FixedVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fx(val: &str) -> Fixed {
+        val.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_formats() {
+        assert_eq!(fx("12").to_string(), "12");
+        assert_eq!(fx("12.500").to_string(), "12.5");
+        assert_eq!(fx("-0.25").to_string(), "-0.25");
+        assert_eq!(fx(".5").to_string(), "0.5");
+        assert_eq!(fx("+3.").to_string(), "3");
+        assert_eq!(fx("").to_string(), "0");
+        assert_eq!(fx("0.000000001").raw(), 1);
+        assert!("1.2.3".parse::<Fixed>().is_err());
+        assert!("12e4".parse::<Fixed>().is_err());
+        assert!("-".parse::<Fixed>().is_err());
+        assert!("NaN".parse::<Fixed>().is_err());
+    }
+
+    #[test]
+    fn rounds_half_away_from_zero() {
+        assert_eq!(fx("0.0000000005").raw(), 1);
+        assert_eq!(fx("0.00000000049999").raw(), 0);
+        assert_eq!(fx("-0.0000000005").raw(), -1);
+        assert_eq!(Fixed::from(0.1), fx("0.1"));
+        assert_eq!(Fixed::from(-1.0000000004), fx("-1"));
+        assert_eq!(fx("0.000000005") * fx("0.1"), Fixed::from_raw(1));
+        assert_eq!(fx("-0.000000005") * fx("0.1"), Fixed::from_raw(-1));
+        assert_eq!(fx("2") / fx("3"), fx("0.666666667"));
+        assert_eq!(fx("-2") / fx("3"), fx("-0.666666667"));
+        assert_eq!(fx("1") / fx("3"), fx("0.333333333"));
+    }
+
+    #[test]
+    fn math() {
+        assert_eq!(fx("0.1") + fx("0.2"), fx("0.3"));
+        assert_eq!(fx("6") - fx("8.5"), fx("-2.5"));
+        assert_eq!(fx("3.1") * fx("5.2"), fx("16.12"));
+        assert_eq!(fx("16.12") / fx("5.2"), fx("3.1"));
+        assert_eq!(vec![fx("1.5"), fx("2.25")].into_iter().sum::<Fixed>(), fx("3.75"));
+        assert_eq!(Fixed::from(7i64), fx("7"));
+        assert_eq!(fx("0.1"), 0.1);
+        assert!(fx("0.1") < fx("0.2"));
+    }
+
+    #[cfg(not(feature = "panic-div0"))]
+    #[test]
+    fn div_by_0() {
+        assert_eq!(fx("8.5") / Fixed::zero(), Fixed::zero());
+    }
+
+    #[test]
+    fn checked() {
+        assert_eq!(fx("1.5").checked_add(fx("2.25")), Some(fx("3.75")));
+        assert_eq!(fx("1.5").checked_sub(fx("2.25")), Some(fx("-0.75")));
+        assert_eq!(fx("1.5").checked_mul(fx("2.25")), Some(fx("3.375")));
+        assert_eq!(fx("1").checked_div(fx("3")), Some(fx("0.333333333")));
+        assert_eq!(fx("8.5").checked_div(Fixed::zero()), None);
+        assert_eq!(Fixed::from_raw(i128::max_value()).checked_add(fx("0.000000001")), None);
+        assert_eq!(Fixed::from_raw(i128::max_value()).checked_mul(fx("2")), None);
+    }
+
+    #[test]
+    fn serializes() {
+        let val = fx("42.125");
+        assert_eq!(Fixed::from_pb(val.to_pb()).unwrap(), val);
+        assert_eq!(serde_json::to_string(&val).unwrap(), "\"42.125\"");
+        assert_eq!(serde_json::from_str::<Fixed>("\"42.125\"").unwrap(), val);
+        assert_eq!(serde_json::from_str::<Fixed>("42.125").unwrap(), val);
+        assert_eq!(serde_json::from_str::<Fixed>("42").unwrap(), fx("42"));
+    }
+}
+
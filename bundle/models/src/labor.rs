@@ -1,9 +1,11 @@
 use exonum::crypto::Hash;
 use chrono::{DateTime, Utc};
+use error::{BResult, BError};
 use crate::{
     proto,
     cost_tag::{CostTagEntry, Costable},
     costs::Costs,
+    fixed::Fixed,
 };
 
 #[derive(Clone, Debug, ProtobufConvert)]
@@ -19,12 +21,14 @@ pub struct Labor {
     pub end: DateTime<Utc>,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
+    pub voided: DateTime<Utc>,
+    pub void_reason: String,
     pub history_len: u64,
     pub history_hash: Hash,
 }
 
 impl Labor {
-    pub fn new(id: &str, company_id: &str, user_id: &str, occupation: &str, wage: f64, cost_tags: &Vec<CostTagEntry>, start: Option<&DateTime<Utc>>, end: Option<&DateTime<Utc>>, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, history_hash: &Hash) -> Self {
+    pub fn new(id: &str, company_id: &str, user_id: &str, occupation: &str, wage: f64, cost_tags: &Vec<CostTagEntry>, start: Option<&DateTime<Utc>>, end: Option<&DateTime<Utc>>, created: &DateTime<Utc>, updated: &DateTime<Utc>, voided: Option<&DateTime<Utc>>, void_reason: &str, history_len: u64, history_hash: &Hash) -> Self {
         Self {
             id: id.to_owned(),
             company_id: company_id.to_owned(),
@@ -36,6 +40,8 @@ impl Labor {
             end: end.unwrap_or(&util::time::default_time()).clone(),
             created: created.clone(),
             updated: updated.clone(),
+            voided: voided.unwrap_or(&util::time::default_time()).clone(),
+            void_reason: void_reason.to_owned(),
             history_len,
             history_hash: history_hash.clone(),
         }
@@ -53,6 +59,8 @@ impl Labor {
             Some(end.unwrap_or(&self.end)),
             &self.created,
             updated,
+            Some(&self.voided),
+            &self.void_reason,
             self.history_len + 1,
             history_hash
         )
@@ -70,11 +78,38 @@ impl Labor {
             Some(&self.end),
             &self.created,
             updated,
+            Some(&self.voided),
+            &self.void_reason,
             self.history_len + 1,
             history_hash
         )
     }
 
+    pub fn void(&self, reason: &str, voided: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.company_id,
+            &self.user_id,
+            &self.occupation,
+            self.wage,
+            &self.cost_tags,
+            Some(&self.start),
+            Some(&self.end),
+            &self.created,
+            voided,
+            Some(voided),
+            reason,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    /// voided labor stays around for the record, but no longer counts
+    /// towards anything
+    pub fn is_voided(&self) -> bool {
+        self.voided != util::time::default_time()
+    }
+
     /// lets us know if we have both a start and end data
     pub fn is_finalized(&self) -> bool {
         let empty = util::time::default_time();
@@ -87,9 +122,94 @@ impl Labor {
         duration.num_milliseconds() as f64 / (60.0 * 60.0 * 1000.0)
     }
 
-    /// Gets the adjusted hours (by wage) for this labor record
-    pub fn wage_hours(&self) -> f64 {
-        self.hours() * self.wage
+    /// Gets the adjusted hours (by wage) for this labor record. We work off
+    /// the raw milliseconds (multiplying before dividing) so the result only
+    /// gets rounded once.
+    pub fn wage_hours(&self) -> Fixed {
+        let millis = (self.end - self.start).num_milliseconds();
+        (Fixed::from(millis) * Fixed::from(self.wage)) / Fixed::from(60i64 * 60 * 1000)
+    }
+
+    /// Like `wage_hours()`, but gives None if the calculation overflows
+    pub fn checked_wage_hours(&self) -> Option<Fixed> {
+        let millis = (self.end - self.start).num_milliseconds();
+        Fixed::from(millis).checked_mul(Fixed::from(self.wage))?
+            .checked_div(Fixed::from(60i64 * 60 * 1000))
+    }
+}
+
+proto_enum! {
+    enum CorrectionStatus {
+        Pending = 0,
+        Approved = 1,
+        Rejected = 2,
+    };
+    proto::labor::LaborCorrection_Status
+}
+
+/// A proposed change to a labor record's start/end times. Workers propose
+/// these when their hours were recorded wrong, and the labor record is left
+/// alone until someone who can administer the company's labor approves it.
+#[derive(Clone, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::labor::LaborCorrection", serde_pb_convert)]
+pub struct LaborCorrection {
+    pub id: String,
+    pub labor_id: String,
+    pub company_id: String,
+    pub user_id: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub reason: String,
+    pub status: CorrectionStatus,
+    pub reviewer_id: String,
+    pub review_note: String,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub history_len: u64,
+    pub history_hash: Hash,
+}
+
+impl LaborCorrection {
+    pub fn new(id: &str, labor_id: &str, company_id: &str, user_id: &str, start: &DateTime<Utc>, end: &DateTime<Utc>, reason: &str, status: &CorrectionStatus, reviewer_id: &str, review_note: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, history_hash: &Hash) -> Self {
+        Self {
+            id: id.to_owned(),
+            labor_id: labor_id.to_owned(),
+            company_id: company_id.to_owned(),
+            user_id: user_id.to_owned(),
+            start: start.clone(),
+            end: end.clone(),
+            reason: reason.to_owned(),
+            status: status.clone(),
+            reviewer_id: reviewer_id.to_owned(),
+            review_note: review_note.to_owned(),
+            created: created.clone(),
+            updated: updated.clone(),
+            history_len,
+            history_hash: history_hash.clone(),
+        }
+    }
+
+    pub fn review(&self, status: &CorrectionStatus, reviewer_id: &str, review_note: &str, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.labor_id,
+            &self.company_id,
+            &self.user_id,
+            &self.start,
+            &self.end,
+            &self.reason,
+            status,
+            reviewer_id,
+            review_note,
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.status == CorrectionStatus::Pending
     }
 }
 
@@ -98,6 +218,14 @@ impl Costable for Labor {
         Costs::new_with_labor(&self.occupation, self.wage_hours())
     }
 
+    fn try_get_costs(&self) -> BResult<Costs> {
+        let hours = self.checked_wage_hours()
+            .ok_or_else(|| BError::CostArithmetic(format!("labor {} -- overflow calculating wage hours", self.id)))?;
+        let mut costs = Costs::new();
+        costs.try_track_labor(&self.occupation, hours)?;
+        Ok(costs)
+    }
+
     fn get_cost_tags(&self) -> Vec<CostTagEntry> {
         self.cost_tags.clone()
     }
@@ -126,6 +254,8 @@ pub mod tests {
             None,
             &date,
             &date,
+            None,
+            "",
             0,
             &make_hash()
         )
@@ -174,6 +304,44 @@ pub mod tests {
         assert_eq!(labor3.history_hash, hash3);
     }
 
+    #[test]
+    fn reviews_corrections() {
+        let date = util::time::now();
+        let start: DateTime<Utc> = "2018-01-01T08:00:00Z".parse().unwrap();
+        let end: DateTime<Utc> = "2018-01-01T16:00:00Z".parse().unwrap();
+        let correction = LaborCorrection::new(
+            "3b5e8f4a-2d6c-4d1e-9a0b-6f7c8d9e0a1b",
+            "9fd8cdc6-04a8-4a35-9cd8-9dc6073a2d10",
+            "df874abc-5583-4740-9f4e-3236530bcc1e",
+            "7de177ba-d589-4f7b-94e0-96d2b0752460",
+            &start,
+            &end,
+            "forgot to clock out",
+            &CorrectionStatus::Pending,
+            "",
+            "",
+            &date,
+            &date,
+            1,
+            &make_hash()
+        );
+        assert!(correction.is_pending());
+        util::sleep(100);
+        let date2 = util::time::now();
+        let hash2 = Hash::new([1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 233, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let correction2 = correction.review(&CorrectionStatus::Approved, "0d6a3c8e-1b2f-4e5d-8c7a-9b0e1f2a3c4d", "checked the cameras", &date2, &hash2);
+        assert!(!correction2.is_pending());
+        assert_eq!(correction2.status, CorrectionStatus::Approved);
+        assert_eq!(correction2.start, correction.start);
+        assert_eq!(correction2.end, correction.end);
+        assert_eq!(correction2.reviewer_id, "0d6a3c8e-1b2f-4e5d-8c7a-9b0e1f2a3c4d");
+        assert_eq!(correction2.review_note, "checked the cameras");
+        assert_eq!(correction2.created, correction.created);
+        assert_eq!(correction2.updated, date2);
+        assert_eq!(correction2.history_len, 2);
+        assert_eq!(correction2.history_hash, hash2);
+    }
+
     #[test]
     fn set_wage() {
         let labor = make_labor();
@@ -196,6 +364,17 @@ pub mod tests {
         assert_eq!(labor2.wage_hours(), 11.736816666666666 * 1000.0);
     }
 
+    #[test]
+    fn negative_hours_error() {
+        let labor = make_labor();
+        let start: DateTime<Utc> = "2018-01-02T03:17:11.573Z".parse().unwrap();
+        let end: DateTime<Utc> = "2018-01-01T15:32:59.033Z".parse().unwrap();
+        let hash2 = Hash::new([1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 233, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let labor2 = labor.update(None, Some(&start), Some(&end), &end, &hash2);
+        assert!(labor2.try_get_costs().is_err());
+        assert!(labor2.try_get_tagged_costs().is_err());
+    }
+
     #[test]
     fn empty() {
         let labor = make_labor();
@@ -206,5 +385,26 @@ pub mod tests {
         let labor2 = labor.update(None, Some(&start), Some(&end), &end, &hash2);
         assert!(labor2.is_finalized());
     }
+
+    #[test]
+    fn void() {
+        let labor = make_labor();
+        assert!(!labor.is_voided());
+        assert_eq!(labor.voided, util::time::default_time());
+        util::sleep(100);
+        let date2 = util::time::now();
+        let hash2 = Hash::new([1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 233, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let labor2 = labor.void("wrong company", &date2, &hash2);
+        assert!(labor2.is_voided());
+        assert_eq!(labor2.voided, date2);
+        assert_eq!(labor2.updated, date2);
+        assert_eq!(labor2.void_reason, "wrong company");
+        assert_eq!(labor2.start, labor.start);
+        assert_eq!(labor2.history_len, labor.history_len + 1);
+        // voiding sticks through later updates
+        let labor3 = labor2.set_wage(12.0, &date2, &hash2);
+        assert!(labor3.is_voided());
+        assert_eq!(labor3.void_reason, "wrong company");
+    }
 }
 
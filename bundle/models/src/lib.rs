@@ -5,12 +5,17 @@
 pub mod proto;
 pub mod access;
 pub mod user;
+pub mod region;
+pub mod occupation;
+pub mod fixed;
 pub mod costs;
+pub mod costing;
 pub mod cost_tag;
 pub mod company;
 pub mod company_member;
 pub mod labor;
 pub mod product;
+pub mod product_listing;
 pub mod resource_tag;
 pub mod order;
 pub mod amortization;
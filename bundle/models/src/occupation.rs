@@ -0,0 +1,115 @@
+use exonum::crypto::Hash;
+use chrono::{DateTime, Utc};
+use crate::proto;
+
+/// An entry in the global occupation registry. Company members (and by
+/// extension their labor) reference occupations by ID so that labor costs
+/// for the same kind of work end up under the same key, regardless of how
+/// each company happens to spell it.
+#[derive(Clone, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::occupation::Occupation", serde_pb_convert)]
+pub struct Occupation {
+    pub id: String,
+    pub label: String,
+    pub merged_into: String,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub history_len: u64,
+    pub history_hash: Hash,
+}
+
+impl Occupation {
+    pub fn new(id: &str, label: &str, merged_into: Option<&str>, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, history_hash: &Hash) -> Self {
+        Self {
+            id: id.to_owned(),
+            label: label.to_owned(),
+            merged_into: merged_into.unwrap_or("").to_owned(),
+            created: created.clone(),
+            updated: updated.clone(),
+            history_len,
+            history_hash: history_hash.clone(),
+        }
+    }
+
+    pub fn merge(self, into_id: &str, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.label,
+            Some(into_id),
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    /// merged occupations stick around so old references can still be
+    /// resolved, but nothing new should point at them
+    pub fn is_merged(&self) -> bool {
+        self.merged_into != ""
+    }
+
+    /// Boil a label down to something we can compare, so "Widget builder" and
+    /// "widget-builder" are treated as the same occupation.
+    pub fn normalize_label(label: &str) -> String {
+        label.split(|c: char| !c.is_alphanumeric())
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use util;
+
+    fn make_date() -> DateTime<Utc> {
+        chrono::offset::Utc::now()
+    }
+
+    fn make_hash() -> Hash {
+        Hash::new([1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4])
+    }
+
+    fn make_occupation() -> Occupation {
+        let date = make_date();
+        Occupation::new(
+            "1b4e6c2a-6f0d-4a57-8d0f-2b9d8e3c7a10",
+            "Widget builder",
+            None,
+            &date,
+            &date,
+            0,
+            &make_hash()
+        )
+    }
+
+    #[test]
+    fn merges() {
+        let occupation = make_occupation();
+        assert!(!occupation.is_merged());
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let occupation2 = occupation.clone().merge("5a0c1f3e-9b7d-4e2a-a6c1-0d8f3b2e4c55", &date2, &hash2);
+        assert!(occupation2.is_merged());
+        assert_eq!(occupation2.merged_into, "5a0c1f3e-9b7d-4e2a-a6c1-0d8f3b2e4c55");
+        assert_eq!(occupation2.id, occupation.id);
+        assert_eq!(occupation2.label, occupation.label);
+        assert_eq!(occupation2.created, occupation.created);
+        assert_eq!(occupation2.updated, date2);
+        assert_eq!(occupation2.history_len, occupation.history_len + 1);
+        assert_eq!(occupation2.history_hash, hash2);
+    }
+
+    #[test]
+    fn normalizes_labels() {
+        assert_eq!(Occupation::normalize_label("Widget builder"), "widget builder");
+        assert_eq!(Occupation::normalize_label("widget-builder"), "widget builder");
+        assert_eq!(Occupation::normalize_label("  WIDGET__Builder!! "), "widget builder");
+        assert_eq!(Occupation::normalize_label("--"), "");
+    }
+}
+
@@ -1,9 +1,13 @@
 use std::default::Default;
+use std::collections::HashMap;
 use exonum::crypto::Hash;
 use chrono::{DateTime, Utc};
+use util;
+use error::{BResult, BError};
 use crate::{
     costs::Costs,
     cost_tag::{CostTagEntry, Costable},
+    fixed::Fixed,
     proto,
 };
 
@@ -21,30 +25,153 @@ proto_enum! {
     proto::order::Order_ProcessStatus
 }
 
+/// The two sides of an order. The buyer is the company making the order
+/// (`company_id_from`) and the supplier is the company fulfilling it
+/// (`company_id_to`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrderParty {
+    Buyer,
+    Supplier,
+}
+
+impl ProcessStatus {
+    /// Given the status we want to move to, return which party is allowed to
+    /// make that move, or None if the transition is not allowed at all.
+    ///
+    /// The supplier moves an order along New -> Accepted -> Processing ->
+    /// (Proxying ->) Completed and the buyer confirms receipt by moving it to
+    /// Finalized. Orders can only be canceled before they are completed.
+    pub fn transition_party(&self, next: &ProcessStatus) -> Option<OrderParty> {
+        match (*self, *next) {
+            (ProcessStatus::New, ProcessStatus::Accepted) |
+            (ProcessStatus::Accepted, ProcessStatus::Processing) |
+            (ProcessStatus::Processing, ProcessStatus::Proxying) |
+            (ProcessStatus::Processing, ProcessStatus::Completed) |
+            (ProcessStatus::Proxying, ProcessStatus::Completed) => Some(OrderParty::Supplier),
+            (ProcessStatus::Completed, ProcessStatus::Finalized) => Some(OrderParty::Buyer),
+            (_, ProcessStatus::Canceled) if self.is_cancelable() => Some(OrderParty::Supplier),
+            _ => None,
+        }
+    }
+
+    /// Whether or not an order in this status can still be canceled
+    pub fn is_cancelable(&self) -> bool {
+        match *self {
+            ProcessStatus::New |
+            ProcessStatus::Accepted |
+            ProcessStatus::Processing |
+            ProcessStatus::Proxying => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Clone, Debug, ProtobufConvert)]
 #[exonum(pb = "proto::order::Order_ProductEntry", serde_pb_convert)]
 pub struct ProductEntry {
     pub product_id: String,
-    pub quantity: f64,
+    pub quantity: Fixed,
     pub costs: Costs,
     pub resource: bool,
+    pub fulfilled: Fixed,
+    pub canceled: Fixed,
+    pub returned: Fixed,
 }
 
 impl ProductEntry {
-    pub fn new(product_id: &str, quantity: f64, costs: &Costs, resource: bool) -> Self {
+    pub fn new<V: Into<Fixed>>(product_id: &str, quantity: V, costs: &Costs, resource: bool) -> Self {
         Self {
             product_id: product_id.to_owned(),
-            quantity,
+            quantity: quantity.into(),
             costs: costs.clone(),
             resource,
+            fulfilled: Fixed::zero(),
+            canceled: Fixed::zero(),
+            returned: Fixed::zero(),
         }
     }
 
+    /// The quantity of this line that was fulfilled and has not been returned
+    pub fn net_fulfilled(&self) -> Fixed {
+        self.fulfilled - self.returned
+    }
+
+    /// The quantity of this line that has been neither fulfilled nor canceled.
+    /// Returns None if the line's quantities are too big to work with.
+    pub fn remaining(&self) -> Option<Fixed> {
+        self.fulfilled.checked_add(self.canceled)
+            .and_then(|x| self.quantity.checked_sub(x))
+    }
+
     pub fn is_resource(&self) -> bool {
         self.resource
     }
 }
 
+#[derive(Clone, Debug, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::order::Order_Shipping", serde_pb_convert)]
+pub struct Shipping {
+    pub address: String,
+    pub carrier: String,
+    pub tracking: String,
+    pub meta: String,
+    pub date_ship_expected: DateTime<Utc>,
+    pub date_shipped: DateTime<Utc>,
+    pub date_delivery_expected: DateTime<Utc>,
+    pub date_delivered: DateTime<Utc>,
+}
+
+impl Shipping {
+    pub fn new(address: &str, carrier: &str, tracking: &str, meta: &str, date_ship_expected: Option<&DateTime<Utc>>, date_shipped: Option<&DateTime<Utc>>, date_delivery_expected: Option<&DateTime<Utc>>, date_delivered: Option<&DateTime<Utc>>) -> Self {
+        let empty_date = util::time::default_time();
+        Self {
+            address: address.to_owned(),
+            carrier: carrier.to_owned(),
+            tracking: tracking.to_owned(),
+            meta: meta.to_owned(),
+            date_ship_expected: date_ship_expected.unwrap_or(&empty_date).clone(),
+            date_shipped: date_shipped.unwrap_or(&empty_date).clone(),
+            date_delivery_expected: date_delivery_expected.unwrap_or(&empty_date).clone(),
+            date_delivered: date_delivered.unwrap_or(&empty_date).clone(),
+        }
+    }
+
+    /// Update the address/carrier/tracking info, leaving the dates alone
+    pub fn update_info(&self, address: Option<&str>, carrier: Option<&str>, tracking: Option<&str>, meta: Option<&str>) -> Self {
+        Self::new(
+            address.unwrap_or(&self.address),
+            carrier.unwrap_or(&self.carrier),
+            tracking.unwrap_or(&self.tracking),
+            meta.unwrap_or(&self.meta),
+            Some(&self.date_ship_expected),
+            Some(&self.date_shipped),
+            Some(&self.date_delivery_expected),
+            Some(&self.date_delivered),
+        )
+    }
+
+    /// Update the expected/actual shipping and delivery dates, leaving the
+    /// address/carrier/tracking info alone
+    pub fn update_dates(&self, date_ship_expected: Option<&DateTime<Utc>>, date_shipped: Option<&DateTime<Utc>>, date_delivery_expected: Option<&DateTime<Utc>>, date_delivered: Option<&DateTime<Utc>>) -> Self {
+        Self::new(
+            &self.address,
+            &self.carrier,
+            &self.tracking,
+            &self.meta,
+            Some(date_ship_expected.unwrap_or(&self.date_ship_expected)),
+            Some(date_shipped.unwrap_or(&self.date_shipped)),
+            Some(date_delivery_expected.unwrap_or(&self.date_delivery_expected)),
+            Some(date_delivered.unwrap_or(&self.date_delivered)),
+        )
+    }
+}
+
+impl Default for Shipping {
+    fn default() -> Self {
+        Self::new("", "", "", "", None, None, None, None)
+    }
+}
+
 #[derive(Clone, Debug, ProtobufConvert)]
 #[exonum(pb = "proto::order::Order", serde_pb_convert)]
 pub struct Order {
@@ -54,6 +181,9 @@ pub struct Order {
     pub cost_tags: Vec<CostTagEntry>,
     pub products: Vec<ProductEntry>,
     pub process_status: ProcessStatus,
+    pub canceled_by: String,
+    pub cancel_reason: String,
+    pub shipping: Shipping,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
     pub history_len: u64,
@@ -61,7 +191,7 @@ pub struct Order {
 }
 
 impl Order {
-    pub fn new(id: &str, company_id_from: &str, company_id_to: &str, cost_tags: &Vec<CostTagEntry>, products: &Vec<ProductEntry>, process_status: &ProcessStatus, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, &history_hash: &Hash) -> Self {
+    pub fn new(id: &str, company_id_from: &str, company_id_to: &str, cost_tags: &Vec<CostTagEntry>, products: &Vec<ProductEntry>, process_status: &ProcessStatus, canceled_by: &str, cancel_reason: &str, shipping: &Shipping, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, &history_hash: &Hash) -> Self {
         Self {
             id: id.to_owned(),
             company_id_from: company_id_from.to_owned(),
@@ -69,6 +199,9 @@ impl Order {
             cost_tags: cost_tags.clone(),
             products: products.clone(),
             process_status: process_status.clone(),
+            canceled_by: canceled_by.to_owned(),
+            cancel_reason: cancel_reason.to_owned(),
+            shipping: shipping.clone(),
             created: created.clone(),
             updated: updated.clone(),
             history_len: history_len,
@@ -76,14 +209,42 @@ impl Order {
         }
     }
 
+    /// How many units of a line count towards costing. Until an order is
+    /// finalized we don't know what will actually ship, so anything that
+    /// hasn't been canceled counts. Once it's finalized, only what was
+    /// fulfilled (and not returned) does.
+    pub fn costed_quantity(&self, entry: &ProductEntry) -> Fixed {
+        match self.process_status {
+            ProcessStatus::Finalized => entry.net_fulfilled(),
+            ProcessStatus::Canceled => Fixed::zero(),
+            _ => entry.quantity - entry.canceled,
+        }
+    }
+
+    /// Update the order's process status. Completing an order fulfills
+    /// whatever remains on each line that hasn't already been fulfilled or
+    /// canceled.
     pub fn update_status(&self, process_status: &ProcessStatus, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        let mut products = self.products.clone();
+        if process_status == &ProcessStatus::Completed {
+            for entry in &mut products {
+                let fulfilled = entry.remaining()
+                    .and_then(|x| entry.fulfilled.checked_add(x));
+                if let Some(fulfilled) = fulfilled {
+                    entry.fulfilled = fulfilled;
+                }
+            }
+        }
         Self::new(
             &self.id,
             &self.company_id_from,
             &self.company_id_to,
             &self.cost_tags,
-            &self.products,
+            &products,
             process_status,
+            &self.canceled_by,
+            &self.cancel_reason,
+            &self.shipping,
             &self.created,
             updated,
             self.history_len + 1,
@@ -91,6 +252,109 @@ impl Order {
         )
     }
 
+    pub fn cancel(&self, canceled_by: &str, cancel_reason: &str, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.company_id_from,
+            &self.company_id_to,
+            &self.cost_tags,
+            &self.products,
+            &ProcessStatus::Canceled,
+            canceled_by,
+            cancel_reason,
+            &self.shipping,
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn update_shipping(&self, shipping: &Shipping, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.company_id_from,
+            &self.company_id_to,
+            &self.cost_tags,
+            &self.products,
+            &self.process_status,
+            &self.canceled_by,
+            &self.cancel_reason,
+            shipping,
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    /// Fulfill and/or cancel some quantity of one of the order's lines. The
+    /// caller is responsible for making sure the line exists and that it has
+    /// enough remaining to cover the given quantities.
+    pub fn update_fulfillment(&self, line: usize, fulfill: Fixed, cancel: Fixed, updated: &DateTime<Utc>, history_hash: &Hash) -> BResult<Self> {
+        let mut products = self.products.clone();
+        if let Some(entry) = products.get_mut(line) {
+            entry.fulfilled = entry.fulfilled.checked_add(fulfill)
+                .ok_or_else(|| BError::CostArithmetic(String::from("Order::update_fulfillment() -- overflow fulfilling line")))?;
+            entry.canceled = entry.canceled.checked_add(cancel)
+                .ok_or_else(|| BError::CostArithmetic(String::from("Order::update_fulfillment() -- overflow canceling line")))?;
+        }
+        Ok(Self::new(
+            &self.id,
+            &self.company_id_from,
+            &self.company_id_to,
+            &self.cost_tags,
+            &products,
+            &self.process_status,
+            &self.canceled_by,
+            &self.cancel_reason,
+            &self.shipping,
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        ))
+    }
+
+    /// Mark some quantity of this order's lines as returned
+    pub fn update_returned(&self, returns: &Vec<ReturnEntry>, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        let mut products = self.products.clone();
+        for ret in returns {
+            if let Some(entry) = products.get_mut(ret.line as usize) {
+                entry.returned += ret.quantity;
+            }
+        }
+        Self::new(
+            &self.id,
+            &self.company_id_from,
+            &self.company_id_to,
+            &self.cost_tags,
+            &products,
+            &self.process_status,
+            &self.canceled_by,
+            &self.cancel_reason,
+            &self.shipping,
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    /// Swap in new per-unit costs (product_id -> costs) for any lines ordering
+    /// the given products. This tracks changes to the supplier's costs rather
+    /// than changes anyone made to the order, so the order's history is left
+    /// alone.
+    pub fn reprice(&self, product_costs: &HashMap<String, Costs>) -> Self {
+        let mut order = self.clone();
+        for entry in &mut order.products {
+            if let Some(costs) = product_costs.get(&entry.product_id) {
+                entry.costs = costs.clone();
+            }
+        }
+        order
+    }
+
     pub fn update_cost_tags(&self, cost_tags: &Vec<CostTagEntry>, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
         Self::new(
             &self.id,
@@ -99,6 +363,9 @@ impl Order {
             cost_tags,
             &self.products,
             &self.process_status,
+            &self.canceled_by,
+            &self.cancel_reason,
+            &self.shipping,
             &self.created,
             updated,
             self.history_len + 1,
@@ -107,19 +374,85 @@ impl Order {
     }
 }
 
+#[derive(Clone, Debug, Default, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::order::OrderReturn_ReturnEntry", serde_pb_convert)]
+pub struct ReturnEntry {
+    pub line: u32,
+    pub product_id: String,
+    pub quantity: Fixed,
+}
+
+impl ReturnEntry {
+    pub fn new<V: Into<Fixed>>(line: u32, product_id: &str, quantity: V) -> Self {
+        Self {
+            line,
+            product_id: product_id.to_owned(),
+            quantity: quantity.into(),
+        }
+    }
+}
+
+/// A return of some portion of a finalized order back to the supplier. The
+/// returned quantities are also tracked on the original order's lines, which
+/// is what takes them out of the costing.
+#[derive(Clone, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::order::OrderReturn", serde_pb_convert)]
+pub struct OrderReturn {
+    pub id: String,
+    pub order_id: String,
+    pub company_id_from: String,
+    pub company_id_to: String,
+    pub products: Vec<ReturnEntry>,
+    pub reason: String,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub history_len: u64,
+    pub history_hash: Hash,
+}
+
+impl OrderReturn {
+    pub fn new(id: &str, order_id: &str, company_id_from: &str, company_id_to: &str, products: &Vec<ReturnEntry>, reason: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, history_hash: &Hash) -> Self {
+        Self {
+            id: id.to_owned(),
+            order_id: order_id.to_owned(),
+            company_id_from: company_id_from.to_owned(),
+            company_id_to: company_id_to.to_owned(),
+            products: products.clone(),
+            reason: reason.to_owned(),
+            created: created.clone(),
+            updated: updated.clone(),
+            history_len,
+            history_hash: history_hash.clone(),
+        }
+    }
+}
+
 impl Costable for Order {
     fn get_costs(&self) -> Costs {
         let mut order_costs: Costs = Costs::new();
         for entry in &self.products {
-            let mut prod_costs = entry.costs.clone() * entry.quantity;
+            let quantity = self.costed_quantity(entry);
+            let mut prod_costs = entry.costs.clone() * quantity;
             if entry.is_resource() {
-                prod_costs = prod_costs + Costs::new_with_product(&entry.product_id, entry.quantity);
+                prod_costs = prod_costs + Costs::new_with_product(&entry.product_id, quantity);
             }
             order_costs = order_costs + prod_costs;
         }
         order_costs
     }
 
+    fn try_get_costs(&self) -> BResult<Costs> {
+        let mut order_costs: Costs = Costs::new();
+        for entry in &self.products {
+            let quantity = self.costed_quantity(entry);
+            order_costs = order_costs.checked_add(&entry.costs.checked_mul(quantity)?)?;
+            if entry.is_resource() {
+                order_costs.try_track(&entry.product_id, quantity)?;
+            }
+        }
+        Ok(order_costs)
+    }
+
     fn get_cost_tags(&self) -> Vec<CostTagEntry> {
         self.cost_tags.clone()
     }
@@ -158,6 +491,9 @@ pub mod tests {
             &vec![CostTagEntry::new("6969", 123)],
             &products,
             &ProcessStatus::New,
+            "",
+            "",
+            &Shipping::default(),
             &now,
             &now,
             0,
@@ -184,6 +520,126 @@ pub mod tests {
         assert_eq!(order2.updated, date2);
     }
 
+    #[test]
+    fn fulfills() {
+        let order = make_order();
+        assert_eq!(order.products[0].remaining().unwrap(), 183.0);
+        // nothing has shipped yet, but pending orders are costed by what we
+        // expect to ship
+        assert_eq!(order.get_costs().get("1234"), 6969.0 * 183.0);
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 31, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let order2 = order.update_fulfillment(0, Fixed::from(100.0), Fixed::from(3.0), &date2, &hash2).unwrap();
+        assert_eq!(order2.products[0].fulfilled, 100.0);
+        assert_eq!(order2.products[0].canceled, 3.0);
+        assert_eq!(order2.products[0].remaining().unwrap(), 80.0);
+        assert_eq!(order2.products[1].fulfilled, 0.0);
+        assert_eq!(order2.get_costs().get("1234"), 6969.0 * 180.0);
+        assert_eq!(order2.get_costs().get("5678"), 1212.0);
+        assert_eq!(order2.history_len, order.history_len + 1);
+
+        // completing the order fulfills whatever is left over
+        let order3 = order2.update_status(&ProcessStatus::Completed, &date2, &hash2);
+        assert_eq!(order3.products[0].fulfilled, 180.0);
+        assert_eq!(order3.products[0].canceled, 3.0);
+        assert_eq!(order3.products[0].remaining().unwrap(), 0.0);
+        assert_eq!(order3.products[1].fulfilled, 1.0);
+        assert_eq!(order3.get_costs().get("1234"), 6969.0 * 180.0);
+        assert_eq!(order3.get_costs().get("5678"), 1212.0);
+    }
+
+    #[test]
+    fn returns() {
+        let order = make_order();
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 32, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let order2 = order
+            .update_status(&ProcessStatus::Completed, &date2, &hash2)
+            .update_status(&ProcessStatus::Finalized, &date2, &hash2);
+        assert_eq!(order2.get_costs().get("1234"), 6969.0 * 183.0);
+        let returns = vec![
+            ReturnEntry::new(0, "ea682431-d0d0-48c5-9166-be5b76a35d62", 80.0),
+            ReturnEntry::new(0, "ea682431-d0d0-48c5-9166-be5b76a35d62", 3.0),
+            ReturnEntry::new(1, "0aabf72f-0cbf-4363-a39d-502be618060d", 1.0),
+        ];
+        let order3 = order2.update_returned(&returns, &date2, &hash2);
+        assert_eq!(order3.products[0].fulfilled, 183.0);
+        assert_eq!(order3.products[0].returned, 83.0);
+        assert_eq!(order3.products[0].net_fulfilled(), 100.0);
+        assert_eq!(order3.products[1].net_fulfilled(), 0.0);
+        assert_eq!(order3.get_costs().get("1234"), 6969.0 * 100.0);
+        assert_eq!(order3.get_costs().get("5678"), 0.0);
+        assert_eq!(order3.process_status, ProcessStatus::Finalized);
+    }
+
+    #[test]
+    fn cancels() {
+        let order = make_order();
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 29, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let order2 = order.cancel("b8f3a1c4-6d2e-4f1a-9c3b-7e5d2a1f0c88", "turns out we had plenty of widgets in the back", &date2, &hash2);
+        assert_eq!(order.canceled_by, "");
+        assert_eq!(order.cancel_reason, "");
+        assert_eq!(order2.process_status, ProcessStatus::Canceled);
+        assert_eq!(order2.canceled_by, "b8f3a1c4-6d2e-4f1a-9c3b-7e5d2a1f0c88");
+        assert_eq!(order2.cancel_reason, "turns out we had plenty of widgets in the back");
+        assert_eq!(order.created, order2.created);
+        assert_eq!(order2.updated, date2);
+        assert_eq!(order2.history_len, order.history_len + 1);
+    }
+
+    #[test]
+    fn updates_shipping() {
+        let order = make_order();
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 30, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let shipping = order.shipping.update_info(Some("123 Main St. (the house with the big gulag out front)"), Some("Freight Pigeon"), Some("COO-COO-1234"), None);
+        let order2 = order.update_shipping(&shipping, &date2, &hash2);
+        assert_eq!(order.shipping, Shipping::default());
+        assert_eq!(order2.shipping.address, "123 Main St. (the house with the big gulag out front)");
+        assert_eq!(order2.shipping.carrier, "Freight Pigeon");
+        assert_eq!(order2.shipping.tracking, "COO-COO-1234");
+        assert_eq!(order2.shipping.meta, "");
+        assert_eq!(order2.shipping.date_shipped, util::time::default_time());
+        assert_eq!(order2.updated, date2);
+        assert_eq!(order2.history_len, order.history_len + 1);
+
+        let date3 = make_date();
+        let shipping = order2.shipping.update_dates(None, Some(&date3), Some(&date3), None);
+        let order3 = order2.update_shipping(&shipping, &date3, &hash2);
+        assert_eq!(order3.shipping.carrier, "Freight Pigeon");
+        assert_eq!(order3.shipping.date_ship_expected, util::time::default_time());
+        assert_eq!(order3.shipping.date_shipped, date3);
+        assert_eq!(order3.shipping.date_delivery_expected, date3);
+        assert_eq!(order3.shipping.date_delivered, util::time::default_time());
+    }
+
+    #[test]
+    fn status_transitions() {
+        assert_eq!(ProcessStatus::New.transition_party(&ProcessStatus::Accepted), Some(OrderParty::Supplier));
+        assert_eq!(ProcessStatus::Accepted.transition_party(&ProcessStatus::Processing), Some(OrderParty::Supplier));
+        assert_eq!(ProcessStatus::Processing.transition_party(&ProcessStatus::Proxying), Some(OrderParty::Supplier));
+        assert_eq!(ProcessStatus::Processing.transition_party(&ProcessStatus::Completed), Some(OrderParty::Supplier));
+        assert_eq!(ProcessStatus::Proxying.transition_party(&ProcessStatus::Completed), Some(OrderParty::Supplier));
+        assert_eq!(ProcessStatus::Completed.transition_party(&ProcessStatus::Finalized), Some(OrderParty::Buyer));
+        assert_eq!(ProcessStatus::Processing.transition_party(&ProcessStatus::Canceled), Some(OrderParty::Supplier));
+
+        // no skipping ahead
+        assert_eq!(ProcessStatus::New.transition_party(&ProcessStatus::Finalized), None);
+        assert_eq!(ProcessStatus::Accepted.transition_party(&ProcessStatus::Completed), None);
+        // no going backwards
+        assert_eq!(ProcessStatus::Finalized.transition_party(&ProcessStatus::New), None);
+        assert_eq!(ProcessStatus::Completed.transition_party(&ProcessStatus::Processing), None);
+        // no canceling once completed
+        assert_eq!(ProcessStatus::Completed.transition_party(&ProcessStatus::Canceled), None);
+        assert_eq!(ProcessStatus::Finalized.transition_party(&ProcessStatus::Canceled), None);
+        assert_eq!(ProcessStatus::Canceled.transition_party(&ProcessStatus::Canceled), None);
+        assert_eq!(ProcessStatus::Canceled.transition_party(&ProcessStatus::New), None);
+    }
+
     #[test]
     fn changes_category() {
         let order = make_order();
@@ -203,5 +659,21 @@ pub mod tests {
         assert!(order.updated != order2.updated);
         assert_eq!(order2.updated, date2);
     }
-}
 
+    #[test]
+    fn reprices() {
+        let order = make_order();
+        let mut costs = Costs::new();
+        costs.track("1234", 4200.0);
+        let mut product_costs = HashMap::new();
+        product_costs.insert("ea682431-d0d0-48c5-9166-be5b76a35d62".to_owned(), costs.clone());
+        let order2 = order.reprice(&product_costs);
+        assert_eq!(order2.products[0].costs, costs);
+        assert_eq!(order2.products[0].quantity, order.products[0].quantity);
+        // lines for other products are left alone
+        assert_eq!(order2.products[1].costs, order.products[1].costs);
+        assert_eq!(order2.updated, order.updated);
+        assert_eq!(order2.history_len, order.history_len);
+        assert_eq!(order2.history_hash, order.history_hash);
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use exonum::crypto::Hash;
+use chrono::{DateTime, Utc};
+use util;
+use crate::proto;
+
+#[derive(Clone, Debug, Default, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::product_listing::ProductListing_ProductEntry", serde_pb_convert)]
+pub struct ProductEntry {
+    pub product_id: String,
+    pub options: HashMap<String, String>,
+}
+
+impl ProductEntry {
+    pub fn new(product_id: &str, options: &HashMap<String, String>) -> Self {
+        Self {
+            product_id: product_id.to_owned(),
+            options: options.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::product_listing::ProductListing", serde_pb_convert)]
+pub struct ProductListing {
+    pub id: String,
+    pub company_id: String,
+    pub name: String,
+    pub options: HashMap<String, String>,
+    pub products: HashMap<String, ProductEntry>,
+    pub meta: String,
+    pub active: bool,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub deleted: DateTime<Utc>,
+    pub history_len: u64,
+    pub history_hash: Hash,
+}
+
+impl ProductListing {
+    pub fn new(id: &str, company_id: &str, name: &str, options: &HashMap<String, String>, products: &HashMap<String, ProductEntry>, meta: &str, active: bool, created: &DateTime<Utc>, updated: &DateTime<Utc>, deleted: Option<&DateTime<Utc>>, history_len: u64, history_hash: &Hash) -> Self {
+        Self {
+            id: id.to_owned(),
+            company_id: company_id.to_owned(),
+            name: name.to_owned(),
+            options: options.clone(),
+            products: products.clone(),
+            meta: meta.to_owned(),
+            active,
+            created: created.clone(),
+            updated: updated.clone(),
+            deleted: deleted.unwrap_or(&util::time::default_time()).clone(),
+            history_len,
+            history_hash: history_hash.clone(),
+        }
+    }
+
+    pub fn update(&self, name: Option<&str>, options: Option<&HashMap<String, String>>, products: Option<&HashMap<String, ProductEntry>>, meta: Option<&str>, active: Option<bool>, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.company_id,
+            name.unwrap_or(&self.name),
+            options.unwrap_or(&self.options),
+            products.unwrap_or(&self.products),
+            meta.unwrap_or(&self.meta),
+            active.unwrap_or(self.active),
+            &self.created,
+            updated,
+            Some(&self.deleted),
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn delete(&self, deleted: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.company_id,
+            &self.name,
+            &self.options,
+            &self.products,
+            &self.meta,
+            self.active,
+            &self.created,
+            &self.updated,
+            Some(deleted),
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active && !self.is_deleted()
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted != util::time::default_time()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use util;
+
+    fn make_date() -> DateTime<Utc> {
+        chrono::offset::Utc::now()
+    }
+
+    fn make_hash() -> Hash {
+        Hash::new([1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4])
+    }
+
+    fn make_listing() -> ProductListing {
+        let date = make_date();
+        let mut options = HashMap::new();
+        options.insert(String::from("size"), String::from("Shirt size"));
+        let mut products = HashMap::new();
+        let mut entry_opts = HashMap::new();
+        entry_opts.insert(String::from("size"), String::from("XXXLarge"));
+        products.insert(String::from("4266954b-c5c0-43e4-a740-9e36c726451d"), ProductEntry::new("4266954b-c5c0-43e4-a740-9e36c726451d", &entry_opts));
+        ProductListing::new(
+            "7b0a8b4c-8a7c-4fa4-a2a1-2b8a1c6d7e22",
+            "b9eb0cc2-5b37-4fd1-83fd-8597625aee95",
+            "RED TSHIRTS (IN EVERY SIZE THAT MATTERS)",
+            &options,
+            &products,
+            "",
+            true,
+            &date,
+            &date,
+            None,
+            0,
+            &make_hash(),
+        )
+    }
+
+    #[test]
+    fn updates() {
+        let listing = make_listing();
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let mut options = listing.options.clone();
+        options.insert(String::from("color"), String::from("Shirt color"));
+        let listing2 = listing.update(
+            Some("Tshirts"),
+            Some(&options),
+            None,
+            None,
+            Some(false),
+            &date2,
+            &hash2
+        );
+        assert_eq!(listing2.company_id, listing.company_id);
+        assert_eq!(listing.name, "RED TSHIRTS (IN EVERY SIZE THAT MATTERS)");
+        assert_eq!(listing2.name, "Tshirts");
+        assert_eq!(listing.options.len(), 1);
+        assert_eq!(listing2.options.len(), 2);
+        assert_eq!(listing2.products, listing.products);
+        assert!(listing.is_active());
+        assert!(!listing2.is_active());
+        assert_eq!(listing.created, listing2.created);
+        assert!(listing.updated != listing2.updated);
+        assert_eq!(listing2.updated, date2);
+        assert_eq!(listing2.history_len, listing.history_len + 1);
+        assert_eq!(listing2.history_hash, hash2);
+    }
+
+    #[test]
+    fn deletes() {
+        let listing = make_listing();
+        assert_eq!(listing.deleted, util::time::default_time());
+        assert!(!listing.is_deleted());
+        let date2 = make_date();
+        let hash2 = Hash::new([56, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let listing2 = listing.delete(&date2, &hash2);
+        assert_eq!(listing2.deleted, date2);
+        assert!(listing2.is_deleted());
+        assert!(!listing2.is_active());
+    }
+}
+
@@ -0,0 +1,87 @@
+use exonum::crypto::Hash;
+use chrono::{DateTime, Utc};
+use crate::proto;
+
+#[derive(Clone, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::region::Region", serde_pb_convert)]
+pub struct Region {
+    pub id: String,
+    pub name: String,
+    pub meta: String,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub history_len: u64,
+    pub history_hash: Hash,
+}
+
+impl Region {
+    pub fn new(id: &str, name: &str, meta: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, history_hash: &Hash) -> Self {
+        Self {
+            id: id.to_owned(),
+            name: name.to_owned(),
+            meta: meta.to_owned(),
+            created: created.clone(),
+            updated: updated.clone(),
+            history_len,
+            history_hash: history_hash.clone(),
+        }
+    }
+
+    pub fn update(self, name: Option<&str>, meta: Option<&str>, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            name.unwrap_or(&self.name),
+            meta.unwrap_or(&self.meta),
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use util;
+
+    fn make_date() -> DateTime<Utc> {
+        chrono::offset::Utc::now()
+    }
+
+    fn make_hash() -> Hash {
+        Hash::new([1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4])
+    }
+
+    fn make_region() -> Region {
+        let date = make_date();
+        Region::new(
+            "e2e0c1d4-0f4b-4a8b-9b8e-3f6c6a1c2d11",
+            "Pacific Northwest",
+            r#"{"weather":"damp"}"#,
+            &date,
+            &date,
+            0,
+            &make_hash()
+        )
+    }
+
+    #[test]
+    fn updates() {
+        let region = make_region();
+        util::sleep(100);
+        let date2 = make_date();
+        let hash2 = Hash::new([1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4, 1, 27, 6, 4]);
+        let region2 = region.clone().update(Some("Cascadia"), None, &date2, &hash2);
+        assert_eq!(region.id, region2.id);
+        assert_eq!(region.name, "Pacific Northwest");
+        assert_eq!(region2.name, "Cascadia");
+        assert_eq!(region.meta, region2.meta);
+        assert_eq!(region.created, region2.created);
+        assert!(region.updated != region2.updated);
+        assert_eq!(region2.updated, date2);
+        assert_eq!(region2.history_len, region.history_len + 1);
+        assert_eq!(region2.history_hash, hash2);
+    }
+}
+
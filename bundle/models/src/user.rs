@@ -3,12 +3,29 @@ use crate::proto;
 use chrono::{DateTime, Utc};
 use crate::access::Role;
 
+#[derive(Clone, Debug, Default, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::user::User_RegionRole", serde_pb_convert)]
+pub struct RegionRole {
+    pub region_id: String,
+    pub roles: Vec<Role>,
+}
+
+impl RegionRole {
+    pub fn new(region_id: &str, roles: &Vec<Role>) -> Self {
+        Self {
+            region_id: region_id.to_owned(),
+            roles: roles.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, ProtobufConvert)]
 #[exonum(pb = "proto::user::User", serde_pb_convert)]
 pub struct User {
     pub id: String,
     pub pubkey: PublicKey,
     pub roles: Vec<Role>,
+    pub region_roles: Vec<RegionRole>,
     pub email: String,
     pub name: String,
     pub meta: String,
@@ -19,11 +36,12 @@ pub struct User {
 }
 
 impl User {
-    pub fn new(id: &str, &pubkey: &PublicKey, roles: &Vec<Role>, email: &str, name: &str, meta: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, &history_hash: &Hash) -> Self {
+    pub fn new(id: &str, &pubkey: &PublicKey, roles: &Vec<Role>, region_roles: &Vec<RegionRole>, email: &str, name: &str, meta: &str, created: &DateTime<Utc>, updated: &DateTime<Utc>, history_len: u64, &history_hash: &Hash) -> Self {
         Self {
             id: id.to_owned(),
             pubkey,
             roles: roles.clone(),
+            region_roles: region_roles.clone(),
             email: email.to_owned(),
             name: name.to_owned(),
             meta: meta.to_owned(),
@@ -39,6 +57,7 @@ impl User {
             &self.id,
             &self.pubkey,
             &self.roles,
+            &self.region_roles,
             email.unwrap_or(&self.email),
             name.unwrap_or(&self.name),
             meta.unwrap_or(&self.meta),
@@ -54,6 +73,7 @@ impl User {
             &self.id,
             pubkey,
             &self.roles,
+            &self.region_roles,
             &self.email,
             &self.name,
             &self.meta,
@@ -69,6 +89,23 @@ impl User {
             &self.id,
             &self.pubkey,
             roles,
+            &self.region_roles,
+            &self.email,
+            &self.name,
+            &self.meta,
+            &self.created,
+            updated,
+            self.history_len + 1,
+            history_hash
+        )
+    }
+
+    pub fn set_region_roles(self, region_roles: &Vec<RegionRole>, updated: &DateTime<Utc>, history_hash: &Hash) -> Self {
+        Self::new(
+            &self.id,
+            &self.pubkey,
+            &self.roles,
+            region_roles,
             &self.email,
             &self.name,
             &self.meta,
@@ -78,6 +115,14 @@ impl User {
             history_hash
         )
     }
+
+    /// Grab the roles this user has been given within the given region.
+    pub fn roles_in_region(&self, region_id: &str) -> Vec<Role> {
+        self.region_roles.iter()
+            .filter(|x| x.region_id == region_id)
+            .flat_map(|x| x.roles.clone())
+            .collect::<Vec<_>>()
+    }
 }
 
 #[cfg(test)]
@@ -100,6 +145,7 @@ pub mod tests {
             "0ca3a0d4-63f2-4e5d-8250-f4528506c0d9",
             &PublicKey::new([1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4]),
             &vec![Role::SuperAdmin],
+            &vec![],
             "socialism.is.when@govt.does.stuff",
             "Carl Mark",
             r#"{"hates":"freedom"}"#,
@@ -176,5 +222,25 @@ pub mod tests {
         assert!(user.history_hash != user2.history_hash);
         assert_eq!(user2.history_hash, hash);
     }
-}
 
+    #[test]
+    fn sets_region_roles() {
+        let user = make_user();
+        util::sleep(100);
+        let date2 = make_date();
+        let hash = Hash::new([69, 27, 6, 4, 69, 27, 6, 4, 69, 27, 6, 4, 69, 27, 6, 4, 69, 27, 6, 4, 69, 27, 6, 4, 69, 27, 6, 4, 69, 27, 6, 69]);
+        let region_roles = vec![
+            RegionRole::new("2f1a7f5e-55d4-4bd3-9a7e-0c4e1d7a9b01", &vec![Role::CompanyAdmin]),
+            RegionRole::new("8c0f4b1e-9d2e-4a55-8f3b-6e2a7c1d0e42", &vec![Role::Bank, Role::TagAdmin]),
+        ];
+        let user2 = user.clone().set_region_roles(&region_roles, &date2, &hash);
+        assert_eq!(user.region_roles.len(), 0);
+        assert_eq!(user2.region_roles, region_roles);
+        assert_eq!(user2.roles, user.roles);
+        assert_eq!(user2.roles_in_region("2f1a7f5e-55d4-4bd3-9a7e-0c4e1d7a9b01"), vec![Role::CompanyAdmin]);
+        assert_eq!(user2.roles_in_region("8c0f4b1e-9d2e-4a55-8f3b-6e2a7c1d0e42"), vec![Role::Bank, Role::TagAdmin]);
+        assert_eq!(user2.roles_in_region("ba5e1e55-0000-4000-8000-000000000000"), vec![]);
+        assert_eq!(user.history_len, user2.history_len - 1);
+        assert_eq!(user2.history_hash, hash);
+    }
+}
@@ -136,6 +136,42 @@ describe('companies', function() {
 		expect(res.description).toMatch(/insufficient priv/i);
 	});
 
+	it('can be approved', async () => {
+		var company = await Companies.get({id: company_id});
+		expect(company.approval_status).toBe('PENDING');
+
+		// jerry cannot approve his own company
+		var res = await trans.send_as('jerry', tx.company.TxSetApproved, {
+			id: company_id,
+			approval_status: 'APPROVED',
+			reason: 'I have reviewed my company and found it to be excellent',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(false);
+		expect(res.description).toMatch(/insufficient priv/i);
+
+		var res = await trans.send_as('root', tx.company.TxSetApproved, {
+			id: company_id,
+			approval_status: 'REJECTED',
+			reason: 'nobody needs widgets',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(true);
+		var company = await Companies.get({id: company_id});
+		expect(company.approval_status).toBe('REJECTED');
+		expect(company.approval_reason).toBe('nobody needs widgets');
+
+		var res = await trans.send_as('root', tx.company.TxSetApproved, {
+			id: company_id,
+			approval_status: 'APPROVED',
+			reason: 'on second thought, widgets are great',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(true);
+		var company = await Companies.get({id: company_id});
+		expect(company.approval_status).toBe('APPROVED');
+	});
+
 	it('can have their type updated', async () => {
 		var company = await Companies.get({id: company_id});
 		expect(company.ty).toBe('PRIVATE');
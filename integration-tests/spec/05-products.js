@@ -90,6 +90,25 @@ describe('products', function() {
 			created: new Date().toISOString(),
 		});
 		expect(res.success).toBe(true);
+
+		// no products until the company is approved
+		var res = await trans.send_as('sandra', tx.product.TxCreate, {
+			id: product_id,
+			company_id: company_id,
+			name: 'widget',
+			unit: 'MILLIMETER',
+			created: new Date().toISOString(),
+		});
+		expect(res.success).toBe(false);
+		expect(res.description).toMatch(/not been approved/i);
+
+		var res = await trans.send_as('root', tx.company.TxSetApproved, {
+			id: company_id,
+			approval_status: 'APPROVED',
+			reason: 'widgets are a fine business',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(true);
 	});
 
 	it('creates new products', async () => {
@@ -125,6 +125,16 @@ describe('orders', function() {
 		expect(res.success).toBe(true);
 		expect(res.description).toBeFalsy();
 
+		for (const id of [company1_id, company2_id, company_shipping_id]) {
+			var res = await trans.send_as('root', tx.company.TxSetApproved, {
+				id: id,
+				approval_status: 'APPROVED',
+				reason: 'approved for testing',
+				updated: new Date().toISOString(),
+			});
+			expect(res.success).toBe(true);
+		}
+
 		var res = await trans.send_as('sandra', tx.product.TxCreate, {
 			id: product1_id,
 			company_id: company1_id,
@@ -196,10 +206,10 @@ describe('orders', function() {
 			],
 			products: [{
 				product_id: product1_id,
-				quantity: 3,
+				quantity: '3',
 			}, {
 				product_id: product2_id,
-				quantity: 6,
+				quantity: '6',
 			}],
 			created: new Date().toISOString(),
 		}
@@ -218,9 +228,9 @@ describe('orders', function() {
 		expect(ord.company_id_from).toBe(company2_id);
 		expect(ord.company_id_to).toBe(company1_id);
 		expect(ord.products[0].product_id).toBe(product1_id);
-		expect(ord.products[0].quantity).toBe(3);
+		expect(ord.products[0].quantity).toBe('3');
 		expect(ord.products[1].product_id).toBe(product2_id);
-		expect(ord.products[1].quantity).toBe(6);
+		expect(ord.products[1].quantity).toBe('6');
 		expect(ord.process_status).toBe('NEW');
 		expect(cost_tags.length).toBe(2);
 		expect(cost_tags[0]).toEqual({id: ctag2_inv_id, weight: 3});
@@ -237,11 +247,40 @@ describe('orders', function() {
 		var ord = await Orders.get({id: order_id});
 		expect(ord.process_status).toBe('ACCEPTED');
 
+		// can't skip steps
+		var res = await trans.send_as('sandra', tx.order.TxUpdateStatus, {
+			id: order_id,
+			process_status: 'COMPLETED',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(false);
+		expect(res.description).toMatch(/invalid order status transition/i);
+
+		for(const process_status of ['PROCESSING', 'COMPLETED']) {
+			var res = await trans.send_as('sandra', tx.order.TxUpdateStatus, {
+				id: order_id,
+				process_status,
+				updated: new Date().toISOString(),
+			});
+			expect(res.success).toBe(true);
+		}
+		var ord = await Orders.get({id: order_id});
+		expect(ord.process_status).toBe('COMPLETED');
+
+		// only the buyer gets to finalize
 		var res = await trans.send_as('sandra', tx.order.TxUpdateStatus, {
 			id: order_id,
 			process_status: 'FINALIZED',
 			updated: new Date().toISOString(),
 		});
+		expect(res.success).toBe(false);
+		expect(res.description).toMatch(/only the buying company/i);
+
+		var res = await trans.send_as('jerry', tx.order.TxUpdateStatus, {
+			id: order_id,
+			process_status: 'FINALIZED',
+			updated: new Date().toISOString(),
+		});
 		expect(res.success).toBe(true);
 		var ord = await Orders.get({id: order_id});
 		expect(ord.process_status).toBe('FINALIZED');
@@ -261,6 +300,44 @@ describe('orders', function() {
 		expect(ord.cost_tags).toEqual([{id: ctag2_inv_id, weight: 37}]);
 	});
 
+	it('can update shipping', async () => {
+		var res = await trans.send_as('jerry', tx.order.TxUpdateShipping, {
+			id: order_id,
+			address: '666 Jerry Way, Jerrytown, JR 12345',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(true);
+		var res = await trans.send_as('sandra', tx.order.TxUpdateShipping, {
+			id: order_id,
+			carrier: 'jerry\'s logistix',
+			tracking: 'JLX-0000-0001',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(true);
+
+		const shipped = new Date().toISOString();
+		// only the supplier knows when things actually shipped
+		var res = await trans.send_as('jerry', tx.order.TxUpdateShippingDates, {
+			id: order_id,
+			date_shipped: shipped,
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(false);
+		expect(res.description).toMatch(/insufficient priv/i);
+		var res = await trans.send_as('sandra', tx.order.TxUpdateShippingDates, {
+			id: order_id,
+			date_shipped: shipped,
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(true);
+
+		var ord = await Orders.get({id: order_id});
+		expect(ord.shipping.address).toBe('666 Jerry Way, Jerrytown, JR 12345');
+		expect(ord.shipping.carrier).toBe('jerry\'s logistix');
+		expect(ord.shipping.tracking).toBe('JLX-0000-0001');
+		expect(new Date(ord.shipping.date_shipped).toISOString()).toBe(shipped);
+	});
+
 	it('will fail when it\'s supposed to', async () => {
 		const order = {
 			id: order_id,
@@ -270,10 +347,10 @@ describe('orders', function() {
 			cost_category: 'OPERATING',
 			products: [{
 				product_id: product1_id,
-				quantity: 3,
+				quantity: '3',
 			}, {
 				product_id: product2_id,
-				quantity: 6,
+				quantity: '6',
 			}],
 			created: new Date().toISOString(),
 		};
@@ -303,6 +380,47 @@ describe('orders', function() {
 		expect(res.description).toMatch(/product not found/i);
 	});
 
+	it('can cancel', async () => {
+		const order2_id = uuid();
+		var res = await trans.send_as('jerry', tx.order.TxCreate, {
+			id: order2_id,
+			company_id_from: company2_id,
+			company_id_to: company1_id,
+			cost_tags: [{id: ctag2_op_id, weight: 1}],
+			products: [{product_id: product1_id, quantity: '2'}],
+			created: new Date().toISOString(),
+		});
+		expect(res.success).toBe(true);
+
+		// sandra doesn't get to cancel on jerry's behalf
+		var res = await trans.send_as('sandra', tx.order.TxCancel, {
+			id: order2_id,
+			reason: 'i changed my mind for you',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(false);
+		expect(res.description).toMatch(/insufficient priv/i);
+
+		var res = await trans.send_as('jerry', tx.order.TxCancel, {
+			id: order2_id,
+			reason: 'found cheaper widgets down the street',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(true);
+		var ord = await Orders.get({id: order2_id});
+		expect(ord.process_status).toBe('CANCELED');
+		expect(ord.canceled_by).toBe(jerry_user_id);
+		expect(ord.cancel_reason).toBe('found cheaper widgets down the street');
+
+		var res = await trans.send_as('jerry', tx.order.TxCancel, {
+			id: order2_id,
+			reason: 'just making sure',
+			updated: new Date().toISOString(),
+		});
+		expect(res.success).toBe(false);
+		expect(res.description).toMatch(/canceled order/i);
+	});
+
 	it('destroys', async () => {
 		var res = await trans.send_as('sandra', tx.product.TxDelete, {
 			id: product1_id,
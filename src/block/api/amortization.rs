@@ -0,0 +1,132 @@
+use exonum::{
+    api::{self, ServiceApiBuilder, ServiceApiState},
+    blockchain,
+    crypto::{self, Hash},
+    helpers::Height,
+    explorer::BlockchainExplorer,
+};
+use exonum_merkledb::MapProof;
+use models;
+use crate::block::{
+    ApiError,
+    ObjectProof,
+    ObjectHistory,
+    ListResult,
+    ProofResult,
+    schema::Schema,
+    SERVICE_ID,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmortizationsQuery {
+    pub after: Option<String>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmortizationsByCompanyQuery {
+    pub company_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmortizationQuery {
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AmortizationApi;
+
+impl AmortizationApi {
+    pub fn get_amortizations(state: &ServiceApiState, query: AmortizationsQuery) -> api::Result<ListResult<models::amortization::Amortization>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let per_page = query.per_page.unwrap_or(10);
+        let (from, skip) = if let Some(after) = query.after.as_ref() {
+            (crypto::hash(after.as_bytes()), 1)
+        } else {
+            (Hash::default(), 0)
+        };
+        let amortizations = schema.amortizations().iter_from(&from)
+            .skip(skip)
+            .take(per_page)
+            .map(|x| x.1)
+            .collect::<Vec<_>>();
+        Ok(ListResult {
+            items: amortizations,
+        })
+    }
+
+    pub fn get_amortizations_by_company(state: &ServiceApiState, query: AmortizationsByCompanyQuery) -> api::Result<ListResult<models::amortization::Amortization>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let company = if query.company_id.is_some() {
+            schema.get_company(query.company_id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let company_id = match company.as_ref() {
+            Some(u) => u.id.clone(),
+            None => String::from(""),
+        };
+        let amortizations = schema.get_amortizations_by_company_id(&company_id);
+        Ok(ListResult {
+            items: amortizations,
+        })
+    }
+
+    pub fn get_amortization(state: &ServiceApiState, query: AmortizationQuery) -> api::Result<ProofResult<models::amortization::Amortization>> {
+        let snapshot = state.snapshot();
+        let system_schema = blockchain::Schema::new(&snapshot);
+        let schema = Schema::new(&snapshot);
+        let amortization = if query.id.is_some() {
+            schema.get_amortization(query.id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let amortization_id = match amortization.as_ref() {
+            Some(u) => u.id.clone(),
+            None => String::from(""),
+        };
+        let max_height = system_schema.block_hashes_by_height().len() - 1;
+        let block_proof = system_schema.block_and_precommits(Height(max_height));
+        let table_proof: MapProof<Hash, Hash> = system_schema.get_proof_to_service_table(SERVICE_ID, 0);
+        let amortization_proof: MapProof<Hash, models::amortization::Amortization> = schema.amortizations().get_proof(crypto::hash(amortization_id.as_bytes()));
+        let object_proof = ObjectProof {
+            table: table_proof,
+            object: amortization_proof,
+        };
+        let explorer = BlockchainExplorer::new(state.blockchain());
+        let amortization_history = amortization.as_ref().map(|_| {
+            let history = schema.amortizations_history(&amortization_id);
+            let proof = history.get_range_proof(0..history.len());
+
+            let transactions = history
+                .iter()
+                .map(|record| explorer.transaction_without_proof(&record).unwrap())
+                .collect::<Vec<_>>();
+
+            ObjectHistory {
+                proof,
+                transactions,
+            }
+        });
+        Ok(ProofResult {
+            block_proof,
+            item_proof: object_proof,
+            item_history: amortization_history,
+            item: amortization,
+        })
+    }
+
+    pub fn wire(builder: &mut ServiceApiBuilder) {
+        builder.public_scope()
+            .endpoint("v1/amortization", Self::get_amortizations)
+            .endpoint("v1/amortization/by-company", Self::get_amortizations_by_company)
+            .endpoint("v1/amortization/info", Self::get_amortization);
+    }
+}
+
@@ -8,6 +8,7 @@ use exonum::{
 };
 use exonum_merkledb::MapProof;
 use models;
+use ::costs::conservation::ConservationReport;
 use crate::block::{
     ApiError,
     ObjectProof,
@@ -16,7 +17,7 @@ use crate::block::{
     ProofResult,
     schema::Schema,
     SERVICE_ID,
-    transactions::costs,
+    transactions::costs::{self, AggregateDiscrepancy},
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,6 +26,11 @@ pub struct CompaniesQuery {
     pub per_page: Option<usize>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompaniesByRegionQuery {
+    pub region_id: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CompanyQuery {
     pub id: Option<String>,
@@ -38,6 +44,15 @@ pub struct CompanyCosts {
     pub agg_len: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompanyCostsCheck {
+    /// Whether the company's costs are conserved (give or take rounding)
+    pub balanced: bool,
+    /// How much of a difference we allow for rounding
+    pub tolerance: models::fixed::Fixed,
+    pub report: ConservationReport,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct CompanyApi;
 
@@ -62,6 +77,26 @@ impl CompanyApi {
         })
     }
 
+    pub fn get_companies_by_region(state: &ServiceApiState, query: CompaniesByRegionQuery) -> api::Result<ListResult<models::company::Company>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let region = if query.region_id.is_some() {
+            schema.get_region(query.region_id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let region_id = match region.as_ref() {
+            Some(r) => r.id.clone(),
+            None => String::from(""),
+        };
+        let companies = schema.get_companies_by_region_id(&region_id);
+        Ok(ListResult {
+            items: companies,
+        })
+    }
+
     pub fn get_company(state: &ServiceApiState, query: CompanyQuery) -> api::Result<ProofResult<models::company::Company>> {
         let snapshot = state.snapshot();
         let system_schema = blockchain::Schema::new(&snapshot);
@@ -143,11 +178,72 @@ impl CompanyApi {
         Ok(costs)
     }
 
+    pub fn get_company_costs_check(state: &ServiceApiState, query: CompanyQuery) -> api::Result<CompanyCostsCheck> {
+        let snapshot = state.snapshot();
+        let mut schema = Schema::new(&snapshot);
+        let company_id = match query.id {
+            Some(id) => id,
+            None => {
+                let err: failure::Error = From::from(ApiError::BadQuery);
+                Err(err)?
+            }
+        };
+        if schema.get_company(&company_id).is_none() {
+            let err: failure::Error = From::from(ApiError::NotFound);
+            Err(err)?
+        }
+        let (report, tolerance) = match costs::check_company_costs(&mut schema, &company_id) {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Company::get_company_costs_check() -- {:?}", e);
+                let err: failure::Error = From::from(ApiError::InternalError);
+                Err(err)?
+            }
+        };
+        Ok(CompanyCostsCheck {
+            balanced: report.is_balanced(tolerance),
+            tolerance,
+            report,
+        })
+    }
+
+    /// Recalculate a company's cost aggregates from its raw orders/labor and
+    /// list any places where the stored aggregates have drifted
+    pub fn get_company_costs_verify(state: &ServiceApiState, query: CompanyQuery) -> api::Result<ListResult<AggregateDiscrepancy>> {
+        let snapshot = state.snapshot();
+        let mut schema = Schema::new(&snapshot);
+        let company_id = match query.id {
+            Some(id) => id,
+            None => {
+                let err: failure::Error = From::from(ApiError::BadQuery);
+                Err(err)?
+            }
+        };
+        if schema.get_company(&company_id).is_none() {
+            let err: failure::Error = From::from(ApiError::NotFound);
+            Err(err)?
+        }
+        let items = match costs::verify_aggregates(&mut schema, &company_id) {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Company::get_company_costs_verify() -- {:?}", e);
+                let err: failure::Error = From::from(ApiError::InternalError);
+                Err(err)?
+            }
+        };
+        Ok(ListResult {
+            items,
+        })
+    }
+
     pub fn wire(builder: &mut ServiceApiBuilder) {
         builder.public_scope()
             .endpoint("v1/companies", Self::get_companies)
+            .endpoint("v1/companies/by-region", Self::get_companies_by_region)
             .endpoint("v1/companies/info", Self::get_company)
-            .endpoint("v1/companies/costs", Self::get_company_costs);
+            .endpoint("v1/companies/costs", Self::get_company_costs)
+            .endpoint("v1/companies/costs/check", Self::get_company_costs_check)
+            .endpoint("v1/companies/costs/verify", Self::get_company_costs_verify);
     }
 }
 
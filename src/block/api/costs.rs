@@ -0,0 +1,219 @@
+use std::collections::BTreeSet;
+use chrono::Duration;
+use exonum::{
+    api::{self, ServiceApiBuilder, ServiceApiState},
+    crypto::Hash,
+};
+use exonum_merkledb::Fork;
+use models::{
+    costs::Costs,
+    cost_tag::CostTagEntry,
+    fixed::Fixed,
+    order::{ProductEntry, ProcessStatus},
+};
+use util;
+use crate::block::{
+    ApiError,
+    ListResult,
+    schema::Schema,
+    transactions::{costs, cost_tag},
+};
+
+/// Caps on how much work a single (unauthenticated) simulation can ask for
+const SIMULATE_MAX_ORDERS: usize = 32;
+const SIMULATE_MAX_ORDER_PRODUCTS: usize = 64;
+const SIMULATE_MAX_LABOR: usize = 64;
+const SIMULATE_MAX_PRODUCT_COST_TAGS: usize = 32;
+const SIMULATE_MAX_COMPANIES: usize = 16;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulatedOrderProduct {
+    pub product_id: String,
+    pub quantity: Fixed,
+}
+
+/// A hypothetical order. It's treated as finalized and fully fulfilled.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulatedOrder {
+    pub company_id_from: String,
+    pub company_id_to: String,
+    #[serde(default)]
+    pub cost_tags: Vec<CostTagEntry>,
+    pub products: Vec<SimulatedOrderProduct>,
+}
+
+/// A hypothetical (finished) labor record, ending now
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulatedLabor {
+    pub company_id: String,
+    pub occupation: String,
+    pub wage: f64,
+    pub hours: f64,
+    #[serde(default)]
+    pub cost_tags: Vec<CostTagEntry>,
+}
+
+/// A hypothetical change to a product's cost tags
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulatedProductCostTags {
+    pub product_id: String,
+    pub cost_tags: Vec<CostTagEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulateRequest {
+    #[serde(default)]
+    pub orders: Vec<SimulatedOrder>,
+    #[serde(default)]
+    pub labor: Vec<SimulatedLabor>,
+    #[serde(default)]
+    pub product_cost_tags: Vec<SimulatedProductCostTags>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulatedProductCosts {
+    pub product_id: String,
+    pub company_id: String,
+    pub before: Costs,
+    pub after: Costs,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CostsApi;
+
+impl CostsApi {
+    /// Apply the simulated changes to the given fork, returning the ids of the
+    /// companies whose costs they touch.
+    fn apply_simulation(schema: &mut Schema<&Fork>, req: &SimulateRequest) -> Result<BTreeSet<String>, ApiError> {
+        let now = util::time::now();
+        let hash = Hash::default();
+        let mut company_ids = BTreeSet::new();
+
+        if req.orders.len() > SIMULATE_MAX_ORDERS ||
+            req.orders.iter().any(|x| x.products.len() > SIMULATE_MAX_ORDER_PRODUCTS) ||
+            req.labor.len() > SIMULATE_MAX_LABOR ||
+            req.product_cost_tags.len() > SIMULATE_MAX_PRODUCT_COST_TAGS
+        {
+            Err(ApiError::BadQuery)?;
+        }
+
+        for (i, order) in req.orders.iter().enumerate() {
+            for company_id in &[&order.company_id_from, &order.company_id_to] {
+                if schema.get_company(company_id).is_none() {
+                    Err(ApiError::NotFound)?;
+                }
+            }
+            let mut products = Vec::new();
+            for entry in &order.products {
+                let (costs, tag) = match schema.get_product_with_costs_tagged(&entry.product_id) {
+                    (Some(_), costs, tag) => (costs.unwrap_or(Costs::new()), tag),
+                    _ => Err(ApiError::NotFound)?,
+                };
+                let mut product = ProductEntry::new(&entry.product_id, entry.quantity, &costs, tag.is_some());
+                product.fulfilled = entry.quantity;
+                products.push(product);
+            }
+            let id = format!("simulated-order-{}", i);
+            let cost_tags = cost_tag::validate_cost_tags(schema, &order.company_id_from, &order.cost_tags);
+            schema.orders_create(&id, &order.company_id_from, &order.company_id_to, &cost_tags, &products, &now, &hash).map_err(|_| ApiError::BadQuery)?;
+            let created = schema.get_order(&id).ok_or(ApiError::InternalError)?;
+            schema.orders_update_status(created, &ProcessStatus::Finalized, &now, &hash).map_err(|_| ApiError::BadQuery)?;
+            company_ids.insert(order.company_id_from.clone());
+            company_ids.insert(order.company_id_to.clone());
+        }
+
+        for (i, labor) in req.labor.iter().enumerate() {
+            if schema.get_company(&labor.company_id).is_none() {
+                Err(ApiError::NotFound)?;
+            }
+            let millis = labor.hours * 3600.0 * 1000.0;
+            if !millis.is_finite() || millis <= 0.0 || millis >= i64::max_value() as f64 {
+                Err(ApiError::BadQuery)?;
+            }
+            let id = format!("simulated-labor-{}", i);
+            let start = now.checked_sub_signed(Duration::milliseconds(millis as i64))
+                .ok_or(ApiError::BadQuery)?;
+            let cost_tags = cost_tag::validate_cost_tags(schema, &labor.company_id, &labor.cost_tags);
+            schema.labor_create(&id, &labor.company_id, "", &labor.occupation, labor.wage, &cost_tags, &start, &hash).map_err(|_| ApiError::BadQuery)?;
+            let created = schema.get_labor(&id).ok_or(ApiError::InternalError)?;
+            schema.labor_update(created, None, None, Some(&now), &now, &hash).map_err(|_| ApiError::BadQuery)?;
+            company_ids.insert(labor.company_id.clone());
+        }
+
+        for change in &req.product_cost_tags {
+            let product = schema.get_product(&change.product_id).ok_or(ApiError::NotFound)?;
+            let company_id = product.company_id.clone();
+            let cost_tags = cost_tag::validate_cost_tags(schema, &company_id, &change.cost_tags);
+            schema.products_update(product, None, None, None, None, Some(&cost_tags), None, None, &now, &hash);
+            company_ids.insert(company_id);
+        }
+        if company_ids.len() > SIMULATE_MAX_COMPANIES {
+            Err(ApiError::BadQuery)?;
+        }
+        Ok(company_ids)
+    }
+
+    /// Run a set of hypothetical orders/labor/cost tag changes against a
+    /// throwaway fork of the current state and report how product costs would
+    /// change. Nothing is written to the chain.
+    pub fn simulate(state: &ServiceApiState, req: SimulateRequest) -> api::Result<ListResult<SimulatedProductCosts>> {
+        let fork = state.blockchain().fork();
+        let mut schema = Schema::new(&fork);
+        let company_ids = match Self::apply_simulation(&mut schema, &req) {
+            Ok(x) => x,
+            Err(e) => {
+                let err: failure::Error = From::from(e);
+                Err(err)?
+            }
+        };
+
+        // none of the changes above recalculate costs, so the products still
+        // have their original costs at this point
+        let mut before = Vec::new();
+        for company_id in &company_ids {
+            for product_id in schema.products_idx_company_id(company_id).iter() {
+                let costs = schema.get_product_costs(&product_id).unwrap_or(Costs::new());
+                before.push((product_id, company_id.clone(), costs));
+            }
+        }
+        for company_id in &company_ids {
+            if let Err(e) = costs::calculate_product_costs(&mut schema, company_id) {
+                error!("Costs::simulate() -- {:?}", e);
+                let err: failure::Error = From::from(ApiError::InternalError);
+                Err(err)?
+            }
+        }
+        let items = before.into_iter()
+            .map(|(product_id, company_id, before)| {
+                let after = schema.get_product_costs(&product_id).unwrap_or(Costs::new());
+                SimulatedProductCosts {
+                    product_id,
+                    company_id,
+                    before,
+                    after,
+                }
+            })
+            .filter(|x| x.before != x.after)
+            .collect::<Vec<_>>();
+        Ok(ListResult {
+            items,
+        })
+    }
+
+    /// List the companies waiting to have their costs recalculated (because
+    /// a supplier's costs changed), in the order they'll be processed.
+    pub fn get_dirty(state: &ServiceApiState, _query: ()) -> api::Result<ListResult<String>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+        Ok(ListResult {
+            items: schema.get_costs_dirty(),
+        })
+    }
+
+    pub fn wire(builder: &mut ServiceApiBuilder) {
+        builder.public_scope()
+            .endpoint("v1/costs/dirty", Self::get_dirty)
+            .endpoint_mut("v1/costs/simulate", Self::simulate);
+    }
+}
+
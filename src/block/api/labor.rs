@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+use chrono::{DateTime, Utc};
 use exonum::{
     api::{self, ServiceApiBuilder, ServiceApiState},
     blockchain,
@@ -30,6 +32,35 @@ pub struct LaborCurrentQuery {
     pub filter: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LaborClockedInQuery {
+    pub company_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LaborCorrectionsQuery {
+    pub company_id: Option<String>,
+    pub labor_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LaborByUserQuery {
+    pub user_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Totals for a user's (finalized, non-voided) labor within one company
+/// under one occupation
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LaborSummary {
+    pub company_id: String,
+    pub occupation: String,
+    pub count: u64,
+    pub hours: f64,
+    pub wage_hours: models::fixed::Fixed,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LaborQuery {
     pub id: Option<String>,
@@ -86,6 +117,106 @@ impl LaborApi {
         Ok(labor)
     }
 
+    /// List the shifts that are currently open (clocked in, but not yet
+    /// clocked out) within a company
+    pub fn get_labor_clocked_in(state: &ServiceApiState, query: LaborClockedInQuery) -> api::Result<ListResult<models::labor::Labor>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let company_id = match query.company_id {
+            Some(id) => id,
+            None => {
+                let err: failure::Error = From::from(ApiError::BadQuery);
+                Err(err)?
+            }
+        };
+        if schema.get_company(&company_id).is_none() {
+            let err: failure::Error = From::from(ApiError::NotFound);
+            Err(err)?
+        }
+        Ok(ListResult {
+            items: schema.get_labor_open(&company_id),
+        })
+    }
+
+    /// List labor corrections, either the corrections waiting on review
+    /// within a company or all corrections made against one labor record
+    pub fn get_labor_corrections(state: &ServiceApiState, query: LaborCorrectionsQuery) -> api::Result<ListResult<models::labor::LaborCorrection>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let items = match (query.company_id, query.labor_id) {
+            (Some(company_id), None) => {
+                if schema.get_company(&company_id).is_none() {
+                    let err: failure::Error = From::from(ApiError::NotFound);
+                    Err(err)?
+                }
+                schema.get_labor_corrections_pending(&company_id)
+            }
+            (None, Some(labor_id)) => {
+                if schema.get_labor(&labor_id).is_none() {
+                    let err: failure::Error = From::from(ApiError::NotFound);
+                    Err(err)?
+                }
+                schema.get_labor_corrections_by_labor_id(&labor_id)
+            }
+            _ => {
+                let err: failure::Error = From::from(ApiError::BadQuery);
+                Err(err)?
+            }
+        };
+        Ok(ListResult { items })
+    }
+
+    /// Summarize a user's labor across all the companies they've worked for,
+    /// grouped by company and occupation
+    pub fn get_labor_by_user(state: &ServiceApiState, query: LaborByUserQuery) -> api::Result<ListResult<LaborSummary>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let user_id = match query.user_id.as_ref() {
+            Some(id) => id,
+            None => {
+                let err: failure::Error = From::from(ApiError::BadQuery);
+                Err(err)?
+            }
+        };
+        let parse_date = |date: Option<&String>| -> Result<Option<DateTime<Utc>>, failure::Error> {
+            match date {
+                Some(x) => x.parse::<DateTime<Utc>>()
+                    .map(|x| Some(x))
+                    .map_err(|_| From::from(ApiError::BadQuery)),
+                None => Ok(None),
+            }
+        };
+        let from = parse_date(query.from.as_ref())?;
+        let to = parse_date(query.to.as_ref())?;
+        if schema.get_user(user_id).is_none() {
+            let err: failure::Error = From::from(ApiError::NotFound);
+            Err(err)?
+        }
+        let mut summaries: BTreeMap<(String, String), LaborSummary> = BTreeMap::new();
+        for labor in schema.get_labor_by_user_id(user_id, from.as_ref(), to.as_ref()) {
+            if !labor.is_finalized() || labor.is_voided() {
+                continue;
+            }
+            let summary = summaries.entry((labor.company_id.clone(), labor.occupation.clone()))
+                .or_insert_with(|| LaborSummary {
+                    company_id: labor.company_id.clone(),
+                    occupation: labor.occupation.clone(),
+                    count: 0,
+                    hours: 0.0,
+                    wage_hours: models::fixed::Fixed::zero(),
+                });
+            summary.count += 1;
+            summary.hours += labor.hours();
+            summary.wage_hours += labor.wage_hours();
+        }
+        Ok(ListResult {
+            items: summaries.into_iter().map(|(_, v)| v).collect::<Vec<_>>(),
+        })
+    }
+
     pub fn get_labor(state: &ServiceApiState, query: LaborQuery) -> api::Result<ProofResult<models::labor::Labor>> {
         let snapshot = state.snapshot();
         let system_schema = blockchain::Schema::new(&snapshot);
@@ -135,6 +266,9 @@ impl LaborApi {
         builder.public_scope()
             .endpoint("v1/labor", Self::get_labor_list)
             .endpoint("v1/labor/company-current", Self::get_labor_current)
+            .endpoint("v1/labor/clocked-in", Self::get_labor_clocked_in)
+            .endpoint("v1/labor/corrections", Self::get_labor_corrections)
+            .endpoint("v1/labor/by-user", Self::get_labor_by_user)
             .endpoint("v1/labor/info", Self::get_labor);
     }
 }
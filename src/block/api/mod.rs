@@ -1,9 +1,14 @@
 pub mod user;
+pub mod region;
+pub mod occupation;
 pub mod company;
 pub mod company_member;
 pub mod labor;
 pub mod product;
+pub mod product_listing;
 pub mod resource_tag;
 pub mod order;
 pub mod cost_tag;
+pub mod amortization;
+pub mod costs;
 
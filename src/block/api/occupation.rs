@@ -0,0 +1,106 @@
+use exonum::{
+    api::{self, ServiceApiBuilder, ServiceApiState},
+    blockchain,
+    crypto::{self, Hash},
+    helpers::Height,
+    explorer::BlockchainExplorer,
+};
+use exonum_merkledb::MapProof;
+use models;
+use crate::block::{
+    ApiError,
+    ObjectProof,
+    ObjectHistory,
+    ListResult,
+    ProofResult,
+    schema::Schema,
+    SERVICE_ID,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OccupationsQuery {
+    pub after: Option<String>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OccupationQuery {
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OccupationApi;
+
+impl OccupationApi {
+    pub fn get_occupations(state: &ServiceApiState, query: OccupationsQuery) -> api::Result<ListResult<models::occupation::Occupation>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let per_page = query.per_page.unwrap_or(10);
+        let (from, skip) = if let Some(after) = query.after.as_ref() {
+            (crypto::hash(after.as_bytes()), 1)
+        } else {
+            (Hash::default(), 0)
+        };
+        let occupations = schema.occupations().iter_from(&from)
+            .skip(skip)
+            .take(per_page)
+            .map(|x| x.1)
+            .collect::<Vec<_>>();
+        Ok(ListResult {
+            items: occupations,
+        })
+    }
+
+    pub fn get_occupation(state: &ServiceApiState, query: OccupationQuery) -> api::Result<ProofResult<models::occupation::Occupation>> {
+        let snapshot = state.snapshot();
+        let system_schema = blockchain::Schema::new(&snapshot);
+        let schema = Schema::new(&snapshot);
+        let occupation = if query.id.is_some() {
+            schema.get_occupation(query.id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let occupation_id = match occupation.as_ref() {
+            Some(u) => u.id.clone(),
+            None => String::from(""),
+        };
+        let max_height = system_schema.block_hashes_by_height().len() - 1;
+        let block_proof = system_schema.block_and_precommits(Height(max_height));
+        let table_proof: MapProof<Hash, Hash> = system_schema.get_proof_to_service_table(SERVICE_ID, 0);
+        let occupation_proof: MapProof<Hash, models::occupation::Occupation> = schema.occupations().get_proof(crypto::hash(occupation_id.as_bytes()));
+        let object_proof = ObjectProof {
+            table: table_proof,
+            object: occupation_proof,
+        };
+        let explorer = BlockchainExplorer::new(state.blockchain());
+        let occupation_history = occupation.as_ref().map(|_| {
+            let history = schema.occupations_history(&occupation_id);
+            let proof = history.get_range_proof(0..history.len());
+
+            let transactions = history
+                .iter()
+                .map(|record| explorer.transaction_without_proof(&record).unwrap())
+                .collect::<Vec<_>>();
+
+            ObjectHistory {
+                proof,
+                transactions,
+            }
+        });
+        Ok(ProofResult {
+            block_proof,
+            item_proof: object_proof,
+            item_history: occupation_history,
+            item: occupation,
+        })
+    }
+
+    pub fn wire(builder: &mut ServiceApiBuilder) {
+        builder.public_scope()
+            .endpoint("v1/occupations", Self::get_occupations)
+            .endpoint("v1/occupations/info", Self::get_occupation);
+    }
+}
+
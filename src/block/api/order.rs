@@ -34,6 +34,16 @@ pub struct OrderQuery {
     pub id: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderReturnsQuery {
+    pub order_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderReturnQuery {
+    pub id: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrdersCurrentResult {
     pub incoming: Vec<models::order::Order>,
@@ -143,11 +153,78 @@ impl OrderApi {
         })
     }
 
+    pub fn get_order_returns(state: &ServiceApiState, query: OrderReturnsQuery) -> api::Result<ListResult<models::order::OrderReturn>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let order = if query.order_id.is_some() {
+            schema.get_order(query.order_id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let order_id = match order.as_ref() {
+            Some(u) => u.id.clone(),
+            None => String::from(""),
+        };
+        let returns = schema.get_order_returns_by_order_id(&order_id);
+        Ok(ListResult {
+            items: returns,
+        })
+    }
+
+    pub fn get_order_return(state: &ServiceApiState, query: OrderReturnQuery) -> api::Result<ProofResult<models::order::OrderReturn>> {
+        let snapshot = state.snapshot();
+        let system_schema = blockchain::Schema::new(&snapshot);
+        let schema = Schema::new(&snapshot);
+        let order_return = if query.id.is_some() {
+            schema.get_order_return(query.id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let return_id = match order_return.as_ref() {
+            Some(u) => u.id.clone(),
+            None => String::from(""),
+        };
+        let max_height = system_schema.block_hashes_by_height().len() - 1;
+        let block_proof = system_schema.block_and_precommits(Height(max_height));
+        let table_proof: MapProof<Hash, Hash> = system_schema.get_proof_to_service_table(SERVICE_ID, 0);
+        let return_proof: MapProof<Hash, models::order::OrderReturn> = schema.order_returns().get_proof(crypto::hash(return_id.as_bytes()));
+        let object_proof = ObjectProof {
+            table: table_proof,
+            object: return_proof,
+        };
+        let explorer = BlockchainExplorer::new(state.blockchain());
+        let return_history = order_return.as_ref().map(|_| {
+            let history = schema.order_returns_history(&return_id);
+            let proof = history.get_range_proof(0..history.len());
+
+            let transactions = history
+                .iter()
+                .map(|record| explorer.transaction_without_proof(&record).unwrap())
+                .collect::<Vec<_>>();
+
+            ObjectHistory {
+                proof,
+                transactions,
+            }
+        });
+        Ok(ProofResult {
+            block_proof,
+            item_proof: object_proof,
+            item_history: return_history,
+            item: order_return,
+        })
+    }
+
     pub fn wire(builder: &mut ServiceApiBuilder) {
         builder.public_scope()
             .endpoint("v1/orders", Self::get_orders)
             .endpoint("v1/orders/company-current", Self::get_orders_current)
-            .endpoint("v1/orders/info", Self::get_order);
+            .endpoint("v1/orders/info", Self::get_order)
+            .endpoint("v1/orders/returns", Self::get_order_returns)
+            .endpoint("v1/orders/returns/info", Self::get_order_return);
     }
 }
 
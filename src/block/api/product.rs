@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use exonum::{
     api::{self, ServiceApiBuilder, ServiceApiState},
     blockchain,
@@ -7,6 +8,7 @@ use exonum::{
 };
 use exonum_merkledb::MapProof;
 use models;
+use costs::explain::CostExplanation;
 use crate::block::{
     ApiError,
     ObjectProof,
@@ -15,6 +17,7 @@ use crate::block::{
     ProofResult,
     schema::Schema,
     SERVICE_ID,
+    transactions,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +36,13 @@ pub struct ProductQuery {
     pub id: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductCostsHistoryQuery {
+    pub id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProductExtended {
     pub product: models::product::Product,
@@ -160,12 +170,71 @@ impl ProductApi {
         Ok(ProductExtended::new(product, costs, tag))
     }
 
+    pub fn get_product_costs_history(state: &ServiceApiState, query: ProductCostsHistoryQuery) -> api::Result<ListResult<models::costs::CostsHistoryEntry>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+        let product_id = match query.id.as_ref() {
+            Some(id) => id,
+            None => {
+                let err: failure::Error = From::from(ApiError::BadQuery);
+                Err(err)?
+            }
+        };
+        let parse_date = |date: Option<&String>| -> Result<Option<DateTime<Utc>>, failure::Error> {
+            match date {
+                Some(x) => x.parse::<DateTime<Utc>>()
+                    .map(|x| Some(x))
+                    .map_err(|_| From::from(ApiError::BadQuery)),
+                None => Ok(None),
+            }
+        };
+        let from = parse_date(query.from.as_ref())?;
+        let to = parse_date(query.to.as_ref())?;
+        if schema.get_product(product_id).is_none() {
+            let err: failure::Error = From::from(ApiError::NotFound);
+            Err(err)?
+        }
+        let history = schema.get_product_costs_history(product_id, from.as_ref(), to.as_ref());
+        Ok(ListResult {
+            items: history,
+        })
+    }
+
+    pub fn get_product_costs_explain(state: &ServiceApiState, query: ProductQuery) -> api::Result<CostExplanation> {
+        let snapshot = state.snapshot();
+        let mut schema = Schema::new(&snapshot);
+        let product_id = match query.id {
+            Some(id) => id,
+            None => {
+                let err: failure::Error = From::from(ApiError::BadQuery);
+                Err(err)?
+            }
+        };
+        let explanation = match transactions::costs::explain_product_costs(&mut schema, &product_id) {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Product::get_product_costs_explain() -- {:?}", e);
+                let err: failure::Error = From::from(ApiError::InternalError);
+                Err(err)?
+            }
+        };
+        match explanation {
+            Some(x) => Ok(x),
+            None => {
+                let err: failure::Error = From::from(ApiError::NotFound);
+                Err(err)?
+            }
+        }
+    }
+
     pub fn wire(builder: &mut ServiceApiBuilder) {
         builder.public_scope()
             .endpoint("v1/products", Self::get_products)
             .endpoint("v1/products/by-company", Self::get_products_by_company)
             .endpoint("v1/products/info", Self::get_product)
-            .endpoint("v1/products/extended", Self::get_product_extended);
+            .endpoint("v1/products/extended", Self::get_product_extended)
+            .endpoint("v1/products/costs/history", Self::get_product_costs_history)
+            .endpoint("v1/products/costs/explain", Self::get_product_costs_explain);
     }
 }
 
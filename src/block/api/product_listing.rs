@@ -0,0 +1,166 @@
+use exonum::{
+    api::{self, ServiceApiBuilder, ServiceApiState},
+    blockchain,
+    crypto::{self, Hash},
+    helpers::Height,
+    explorer::BlockchainExplorer,
+};
+use exonum_merkledb::MapProof;
+use models;
+use crate::block::{
+    ApiError,
+    ObjectProof,
+    ObjectHistory,
+    ListResult,
+    ProofResult,
+    schema::Schema,
+    SERVICE_ID,
+    api::product::ProductExtended,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductListingsQuery {
+    pub after: Option<String>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductListingsByCompanyQuery {
+    pub company_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductListingQuery {
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductListingExtended {
+    pub listing: models::product_listing::ProductListing,
+    pub products: Vec<ProductExtended>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProductListingApi;
+
+impl ProductListingApi {
+    pub fn get_product_listings(state: &ServiceApiState, query: ProductListingsQuery) -> api::Result<ListResult<models::product_listing::ProductListing>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let per_page = query.per_page.unwrap_or(10);
+        let (from, skip) = if let Some(after) = query.after.as_ref() {
+            (crypto::hash(after.as_bytes()), 1)
+        } else {
+            (Hash::default(), 0)
+        };
+        let listings = schema.product_listings().iter_from(&from)
+            .skip(skip)
+            .take(per_page)
+            .map(|x| x.1)
+            .collect::<Vec<_>>();
+        Ok(ListResult {
+            items: listings,
+        })
+    }
+
+    pub fn get_product_listings_by_company(state: &ServiceApiState, query: ProductListingsByCompanyQuery) -> api::Result<ListResult<models::product_listing::ProductListing>> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let company = if query.company_id.is_some() {
+            schema.get_company(query.company_id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let company_id = match company.as_ref() {
+            Some(u) => u.id.clone(),
+            None => String::from(""),
+        };
+        let listings = schema.get_product_listings_by_company_id(&company_id);
+        Ok(ListResult {
+            items: listings,
+        })
+    }
+
+    pub fn get_product_listing(state: &ServiceApiState, query: ProductListingQuery) -> api::Result<ProofResult<models::product_listing::ProductListing>> {
+        let snapshot = state.snapshot();
+        let system_schema = blockchain::Schema::new(&snapshot);
+        let schema = Schema::new(&snapshot);
+        let listing = if query.id.is_some() {
+            schema.get_product_listing(query.id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let listing_id = match listing.as_ref() {
+            Some(u) => u.id.clone(),
+            None => String::from(""),
+        };
+        let max_height = system_schema.block_hashes_by_height().len() - 1;
+        let block_proof = system_schema.block_and_precommits(Height(max_height));
+        let table_proof: MapProof<Hash, Hash> = system_schema.get_proof_to_service_table(SERVICE_ID, 0);
+        let listing_proof: MapProof<Hash, models::product_listing::ProductListing> = schema.product_listings().get_proof(crypto::hash(listing_id.as_bytes()));
+        let object_proof = ObjectProof {
+            table: table_proof,
+            object: listing_proof,
+        };
+        let explorer = BlockchainExplorer::new(state.blockchain());
+        let listing_history = listing.as_ref().map(|_| {
+            let history = schema.product_listings_history(&listing_id);
+            let proof = history.get_range_proof(0..history.len());
+
+            let transactions = history
+                .iter()
+                .map(|record| explorer.transaction_without_proof(&record).unwrap())
+                .collect::<Vec<_>>();
+
+            ObjectHistory {
+                proof,
+                transactions,
+            }
+        });
+        Ok(ProofResult {
+            block_proof,
+            item_proof: object_proof,
+            item_history: listing_history,
+            item: listing,
+        })
+    }
+
+    pub fn get_product_listing_extended(state: &ServiceApiState, query: ProductListingQuery) -> api::Result<ProductListingExtended> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+        let listing = if query.id.is_some() {
+            schema.get_product_listing(query.id.as_ref().unwrap())
+        } else {
+            let err: failure::Error = From::from(ApiError::BadQuery);
+            Err(err)?
+        };
+        let listing = if let Some(listing) = listing {
+            listing
+        } else {
+            let err: failure::Error = From::from(ApiError::NotFound);
+            Err(err)?
+        };
+        let products = listing.products.values()
+            .map(|entry| schema.get_product_with_costs_tagged(&entry.product_id))
+            .filter(|(product, ..)| product.is_some())
+            .map(|(product, costs, tag)| ProductExtended::new(product.unwrap(), costs, tag))
+            .collect::<Vec<_>>();
+        Ok(ProductListingExtended {
+            listing,
+            products,
+        })
+    }
+
+    pub fn wire(builder: &mut ServiceApiBuilder) {
+        builder.public_scope()
+            .endpoint("v1/product-listings", Self::get_product_listings)
+            .endpoint("v1/product-listings/by-company", Self::get_product_listings_by_company)
+            .endpoint("v1/product-listings/info", Self::get_product_listing)
+            .endpoint("v1/product-listings/extended", Self::get_product_listing_extended);
+    }
+}
+
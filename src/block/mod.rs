@@ -10,10 +10,13 @@ use exonum::{
     messages::RawTransaction,
 };
 use exonum_merkledb::{
+    Fork,
     MapProof,
     ListProof,
     Snapshot,
 };
+use models::costing::CostingConfig;
+use serde_json::{self, Value};
 pub use crate::block::schema::Schema;
 use crate::block::transactions::TransactionGroup;
 
@@ -58,6 +61,14 @@ pub struct ListResult<T> {
     pub items: Vec<T>,
 }
 
+/// Our service's section of the network configuration. This is managed by the
+/// exonum-configuration service, so changing it requires the validators to
+/// propose and vote on a new config.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    pub costing: CostingConfig,
+}
+
 #[derive(Default, Debug)]
 pub struct Service;
 
@@ -79,15 +90,32 @@ impl blockchain::Service for Service {
         TransactionGroup::tx_from_raw(raw).map(Into::into)
     }
 
+    fn initialize(&self, _fork: &Fork) -> Value {
+        serde_json::to_value(ServiceConfig::default()).unwrap_or(Value::Null)
+    }
+
+    fn before_commit(&self, fork: &Fork) {
+        let mut schema = Schema::new(fork);
+        if let Err(e) = transactions::costs::apply_costing_config(&mut schema) {
+            error!("block::before_commit() -- problem applying costing config, staying on the current one: {}", e);
+        }
+        transactions::costs::process_dirty_companies(&mut schema);
+    }
+
     fn wire_api(&self, builder: &mut ServiceApiBuilder) {
         api::user::UserApi::wire(builder);
+        api::region::RegionApi::wire(builder);
+        api::occupation::OccupationApi::wire(builder);
         api::company::CompanyApi::wire(builder);
         api::company_member::CompanyMemberApi::wire(builder);
         api::labor::LaborApi::wire(builder);
         api::product::ProductApi::wire(builder);
+        api::product_listing::ProductListingApi::wire(builder);
         api::resource_tag::ResourceTagApi::wire(builder);
         api::order::OrderApi::wire(builder);
         api::cost_tag::CostTagApi::wire(builder);
+        api::amortization::AmortizationApi::wire(builder);
+        api::costs::CostsApi::wire(builder);
     }
 }
 
@@ -1,10 +1,13 @@
+use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 use exonum::{
+    blockchain,
     crypto::{self, Hash, PublicKey},
 };
 use exonum_merkledb::{
     IndexAccess,
     ObjectHash,
+    Entry,
     ListIndex,
     MapIndex,
     ProofListIndex,
@@ -12,18 +15,26 @@ use exonum_merkledb::{
     KeySetIndex,
 };
 use util;
+use error::BResult;
 use models::{
     access::Role,
-    user::User,
-    company::{Company, CompanyType, Role as CompanyRole},
+    user::{User, RegionRole},
+    region::Region,
+    occupation::Occupation,
+    company::{Company, CompanyType, ApprovalStatus, Role as CompanyRole},
     company_member::CompanyMember,
-    labor::Labor,
+    labor::{Labor, LaborCorrection, CorrectionStatus},
     product::{Product, Unit, Dimensions},
+    product_listing::{ProductListing, ProductEntry as ProductListingEntry},
     resource_tag::ResourceTag,
-    order::{Order, ProcessStatus, ProductEntry},
-    costs::{Costs, CostsTallyMap},
+    order::{Order, ProcessStatus, ProductEntry, Shipping, OrderReturn, ReturnEntry},
+    costs::{Costs, CostsTallyMap, CostsHistoryEntry},
+    costing::CostingConfig,
+    fixed::Fixed,
     cost_tag::{CostTag, CostTagEntry, Costable},
+    amortization::Amortization,
 };
+use crate::block::{SERVICE_NAME, ServiceConfig};
 
 #[derive(Debug)]
 pub struct Schema<T> {
@@ -72,13 +83,13 @@ fn is_rotate_record_obsolete<T>(idx: &mut MapIndex<T, String, String>, timestamp
     }
 }
 
-fn index_and_rotate_mapindex<T, F>(idx: &mut MapIndex<T, String, String>, timestamp: i64, item_id: &str, cutoff: &DateTime<Utc>, mut op_cb: F)
+fn index_and_rotate_mapindex<T, F>(idx: &mut MapIndex<T, String, String>, timestamp: i64, item_id: &str, cutoff: &DateTime<Utc>, mut op_cb: F) -> BResult<()>
     where T: IndexAccess,
-          F: FnMut(String, bool),
+          F: FnMut(String, bool) -> BResult<()>,
 {
     let key = format!("{}:{}", timestamp, item_id);
 
-    op_cb(item_id.to_owned(), false);
+    op_cb(item_id.to_owned(), false)?;
     idx.put(&key, item_id.to_owned());
     let mut remove_keys = Vec::new();
     for k in idx.keys() {
@@ -91,9 +102,68 @@ fn index_and_rotate_mapindex<T, F>(idx: &mut MapIndex<T, String, String>, timest
     }
     for k in &remove_keys {
         let item_id = key_to_id(k);
-        op_cb(item_id, true);
+        op_cb(item_id, true)?;
         idx.remove(k);
     }
+    Ok(())
+}
+
+/// Given a company's records (in any order), pick out the ones that would
+/// still be in its rotational index after replaying them all: anything within
+/// `window_secs` of the newest one. Each comes back with its index key.
+fn rotate_keep<R, F>(records: Vec<R>, window_secs: i64, get_key: F) -> Vec<(String, R)>
+    where F: Fn(&R) -> (i64, String),
+{
+    let newest = match records.iter().map(|x| get_key(x).0).max() {
+        Some(x) => x,
+        None => return Vec::new(),
+    };
+    records.into_iter()
+        .map(|x| (get_key(&x), x))
+        .filter(|((timestamp, _), _)| *timestamp >= newest - window_secs)
+        .map(|((timestamp, id), x)| (format!("{}:{}", timestamp, id), x))
+        .collect::<Vec<_>>()
+}
+
+/// Tally up cost aggregates from a company's finalized outgoing orders
+/// (costs), finalized incoming orders (product outputs), and finalized labor.
+fn costs_aggregate_from_records(orders_outgoing: &Vec<Order>, orders_incoming: &Vec<Order>, labor: &Vec<Labor>) -> BResult<HashMap<String, CostsTallyMap>> {
+    let mut bucket_map_costs = CostsTallyMap::new();
+    for order in orders_outgoing {
+        if order.process_status != ProcessStatus::Finalized {
+            continue;
+        }
+        bucket_map_costs.try_add_map(&order.try_get_tagged_costs()?)?;
+    }
+    let mut bucket_map_outputs = CostsTallyMap::new();
+    for order in orders_incoming {
+        if order.process_status != ProcessStatus::Finalized {
+            continue;
+        }
+        let mut outputs = Costs::new();
+        for entry in &order.products {
+            outputs.try_track(&entry.product_id, entry.net_fulfilled())?;
+        }
+        bucket_map_outputs.try_add("outputs", &outputs)?;
+    }
+    let mut bucket_map_labor = CostsTallyMap::new();
+    for labor in labor {
+        bucket_map_labor.try_add_map(&labor.try_get_tagged_costs()?)?;
+    }
+    let mut aggregates = HashMap::new();
+    aggregates.insert(String::from("costs.v1"), bucket_map_costs);
+    aggregates.insert(String::from("product_outputs.v1"), bucket_map_outputs);
+    aggregates.insert(String::from("labor.v1"), bucket_map_labor);
+    Ok(aggregates)
+}
+
+/// What a company's rolling indexes and cost aggregates should hold under a
+/// given costing window. See `Schema::costs_rebuild_rolling_calculate()`.
+pub struct RollingRebuild {
+    orders_from: Vec<(String, String)>,
+    orders_to: Vec<(String, String)>,
+    labor: Vec<(String, String)>,
+    aggregates: HashMap<String, CostsTallyMap>,
 }
 
 impl<T> Schema<T>
@@ -106,12 +176,18 @@ impl<T> Schema<T>
     pub fn state_hash(&self) -> Vec<Hash> {
         vec![
             self.users().object_hash(),
+            self.regions().object_hash(),
             self.companies().object_hash(),
             self.companies_members().object_hash(),
             self.labor().object_hash(),
             self.products().object_hash(),
+            self.product_listings().object_hash(),
             self.resource_tags().object_hash(),
             self.orders().object_hash(),
+            self.order_returns().object_hash(),
+            self.amortizations().object_hash(),
+            self.labor_corrections().object_hash(),
+            self.occupations().object_hash(),
         ]
     }
 
@@ -159,7 +235,7 @@ impl<T> Schema<T>
             let mut history = self.users_history(id);
             history.push(*transaction);
             let history_hash = history.object_hash();
-            User::new(id, pubkey, roles, email, name, meta, created, created, history.len(), &history_hash)
+            User::new(id, pubkey, roles, &vec![], email, name, meta, created, created, history.len(), &history_hash)
         };
         self.users().put(&crypto::hash(id.as_bytes()), user);
         self.users_idx_pubkey().put(pubkey, id.to_owned());
@@ -205,6 +281,16 @@ impl<T> Schema<T>
         self.users().put(&crypto::hash(id.as_bytes()), user);
     }
 
+    pub fn users_set_region_roles(&mut self, user: User, id: &str, region_roles: &Vec<RegionRole>, updated: &DateTime<Utc>, transaction: &Hash) {
+        let user = {
+            let mut history = self.users_history(id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            user.set_region_roles(region_roles, updated, &history_hash)
+        };
+        self.users().put(&crypto::hash(id.as_bytes()), user);
+    }
+
     pub fn users_delete(&mut self, user: User, id: &str) {
         self.users().remove(&crypto::hash(id.as_bytes()));
         self.users_idx_pubkey().remove(&user.pubkey);
@@ -212,6 +298,115 @@ impl<T> Schema<T>
         self.users_history(id).clear();
     }
 
+    // -------------------------------------------------------------------------
+    // Regions
+    // -------------------------------------------------------------------------
+    pub fn regions(&self) -> ProofMapIndex<T, Hash, Region> {
+        ProofMapIndex::new("basis.regions.table", self.access.clone())
+    }
+
+    pub fn regions_history(&self, id: &str) -> ProofListIndex<T, Hash> {
+        ProofListIndex::new_in_family("basis.regions.history", &crypto::hash(id.as_bytes()), self.access.clone())
+    }
+
+    pub fn get_region(&self, id: &str) -> Option<Region> {
+        self.regions().get(&crypto::hash(id.as_bytes()))
+    }
+
+    pub fn regions_create(&mut self, id: &str, name: &str, meta: &str, created: &DateTime<Utc>, transaction: &Hash) {
+        let region = {
+            let mut history = self.regions_history(id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            Region::new(id, name, meta, created, created, history.len(), &history_hash)
+        };
+        self.regions().put(&crypto::hash(id.as_bytes()), region);
+    }
+
+    pub fn regions_update(&mut self, region: Region, name: Option<&str>, meta: Option<&str>, updated: &DateTime<Utc>, transaction: &Hash) {
+        let id = region.id.clone();
+        let region = {
+            let mut history = self.regions_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            region.update(name, meta, updated, &history_hash)
+        };
+        self.regions().put(&crypto::hash(id.as_bytes()), region);
+    }
+
+    // -------------------------------------------------------------------------
+    // Occupations
+    // -------------------------------------------------------------------------
+    pub fn occupations(&self) -> ProofMapIndex<T, Hash, Occupation> {
+        ProofMapIndex::new("basis.occupations.table", self.access.clone())
+    }
+
+    pub fn occupations_history(&self, id: &str) -> ProofListIndex<T, Hash> {
+        ProofListIndex::new_in_family("basis.occupations.history", &crypto::hash(id.as_bytes()), self.access.clone())
+    }
+
+    /// normalized label -> occupation_id
+    pub fn occupations_idx_label(&self) -> MapIndex<T, String, String> {
+        MapIndex::new("basis.occupations.idx_label", self.access.clone())
+    }
+
+    pub fn get_occupation(&self, id: &str) -> Option<Occupation> {
+        self.occupations().get(&crypto::hash(id.as_bytes()))
+    }
+
+    pub fn get_occupation_by_label(&self, label: &str) -> Option<Occupation> {
+        self.occupations_idx_label()
+            .get(&Occupation::normalize_label(label))
+            .and_then(|id| self.get_occupation(&id))
+    }
+
+    /// Find the occupation that an occupation ID (or label) refers to,
+    /// following any merges. Returns None if the occupation isn't in the
+    /// registry.
+    pub fn get_occupation_resolved(&self, id_or_label: &str) -> Option<Occupation> {
+        let mut occupation = self.get_occupation(id_or_label)
+            .or_else(|| self.get_occupation_by_label(id_or_label))?;
+        // merges can only point at unmerged occupations, so this can't cycle
+        while occupation.is_merged() {
+            occupation = self.get_occupation(&occupation.merged_into)?;
+        }
+        Some(occupation)
+    }
+
+    /// Turn an occupation (ID or label) into the ID of the registry entry it
+    /// refers to. Occupations that aren't in the registry are passed through
+    /// as-is.
+    pub fn occupation_normalize(&self, occupation: &str) -> String {
+        match self.get_occupation_resolved(occupation) {
+            Some(x) => x.id,
+            None => occupation.to_owned(),
+        }
+    }
+
+    pub fn occupations_create(&mut self, id: &str, label: &str, created: &DateTime<Utc>, transaction: &Hash) {
+        let occupation = {
+            let mut history = self.occupations_history(id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            Occupation::new(id, label, None, created, created, history.len(), &history_hash)
+        };
+        self.occupations().put(&crypto::hash(id.as_bytes()), occupation);
+        self.occupations_idx_label().put(&Occupation::normalize_label(label), id.to_owned());
+    }
+
+    /// Merge one occupation into another. Anything referencing the merged
+    /// occupation resolves to `into_id` from here on out.
+    pub fn occupations_merge(&mut self, occupation: Occupation, into_id: &str, updated: &DateTime<Utc>, transaction: &Hash) {
+        let id = occupation.id.clone();
+        let occupation = {
+            let mut history = self.occupations_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            occupation.merge(into_id, updated, &history_hash)
+        };
+        self.occupations().put(&crypto::hash(id.as_bytes()), occupation);
+    }
+
     // -------------------------------------------------------------------------
     // Companies
     // -------------------------------------------------------------------------
@@ -223,18 +418,34 @@ impl<T> Schema<T>
         ProofListIndex::new_in_family("basis.companies.history", &crypto::hash(id.as_bytes()), self.access.clone())
     }
 
+    pub fn companies_idx_region_id(&self, region_id: &str) -> KeySetIndex<T, String> {
+        KeySetIndex::new_in_family("basis.companies.idx_region_id", &crypto::hash(region_id.as_bytes()), self.access.clone())
+    }
+
     pub fn get_company(&self, id: &str) -> Option<Company> {
         self.companies().get(&crypto::hash(id.as_bytes()))
     }
 
-    pub fn companies_create(&mut self, id: &str, ty: &CompanyType, region_id: Option<&str>, email: &str, name: &str, created: &DateTime<Utc>, transaction: &Hash) {
+    pub fn get_companies_by_region_id(&self, region_id: &str) -> Vec<Company> {
+        self.companies_idx_region_id(region_id)
+            .iter()
+            .map(|id| self.get_company(&id))
+            .filter(|x| x.is_some())
+            .map(|x| x.unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    pub fn companies_create(&mut self, id: &str, ty: &CompanyType, region_id: Option<&str>, email: &str, name: &str, approval_status: &ApprovalStatus, created: &DateTime<Utc>, transaction: &Hash) {
         let company = {
             let mut history = self.companies_history(id);
             history.push(*transaction);
             let history_hash = history.object_hash();
-            Company::new(id, ty, region_id, email, name, created, created, history.len(), &history_hash)
+            Company::new(id, ty, region_id, email, name, approval_status, "", created, created, history.len(), &history_hash)
         };
         self.companies().put(&crypto::hash(id.as_bytes()), company.clone());
+        if let Some(region_id) = region_id {
+            self.companies_idx_region_id(region_id).insert(id.to_owned());
+        }
     }
 
     pub fn companies_update(&mut self, company: Company, email: Option<&str>, name: Option<&str>, updated: &DateTime<Utc>, transaction: &Hash) {
@@ -257,7 +468,39 @@ impl<T> Schema<T>
         self.companies().put(&crypto::hash(company.id.as_bytes()), company);
     }
 
+    pub fn companies_set_approval(&mut self, company: Company, approval_status: &ApprovalStatus, approval_reason: &str, updated: &DateTime<Utc>, transaction: &Hash) {
+        let company = {
+            let mut history = self.companies_history(&company.id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            company.set_approval(approval_status, approval_reason, updated, &history_hash)
+        };
+        self.companies().put(&crypto::hash(company.id.as_bytes()), company);
+    }
+
+    pub fn companies_set_region(&mut self, company: Company, region_id: Option<&str>, updated: &DateTime<Utc>, transaction: &Hash) {
+        let region_id_old = company.region_id.clone();
+        let company = {
+            let mut history = self.companies_history(&company.id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            company.set_region(region_id, updated, &history_hash)
+        };
+        if region_id_old != "" {
+            self.companies_idx_region_id(&region_id_old).remove(&company.id);
+        }
+        if let Some(region_id) = region_id {
+            self.companies_idx_region_id(region_id).insert(company.id.clone());
+        }
+        self.companies().put(&crypto::hash(company.id.as_bytes()), company);
+    }
+
     pub fn companies_delete(&mut self, id: &str) {
+        if let Some(company) = self.get_company(id) {
+            if company.region_id != "" {
+                self.companies_idx_region_id(&company.region_id).remove(&id.to_owned());
+            }
+        }
         self.companies().remove(&crypto::hash(id.as_bytes()));
         self.companies_members_delete_by_company(id);
         self.companies_history(id).clear();
@@ -288,22 +531,24 @@ impl<T> Schema<T>
     }
 
     pub fn companies_members_create(&mut self, id: &str, company_id: &str, user_id: &str, roles: &Vec<CompanyRole>, occupation: &str, wage: f64, default_cost_tags: &Vec<CostTagEntry>, created: &DateTime<Utc>, transaction: &Hash) {
+        let occupation = self.occupation_normalize(occupation);
         let member = {
             let mut history = self.companies_members_history(id);
             history.push(*transaction);
             let history_hash = history.object_hash();
-            CompanyMember::new(id, company_id, user_id, roles, occupation, wage, default_cost_tags, created, created, history.len(), &history_hash)
+            CompanyMember::new(id, company_id, user_id, roles, &occupation, wage, default_cost_tags, created, created, history.len(), &history_hash)
         };
         self.companies_members().put(&crypto::hash(id.as_bytes()), member);
         self.companies_members_idx_company_id(company_id).put(&user_id.to_owned(), id.to_owned());
     }
 
     pub fn companies_members_update(&mut self, member: CompanyMember, roles: Option<&Vec<CompanyRole>>, occupation: Option<&str>, wage: Option<f64>, default_cost_tags: Option<&Vec<CostTagEntry>>, updated: &DateTime<Utc>, transaction: &Hash) {
+        let occupation = occupation.map(|x| self.occupation_normalize(x));
         let member = {
             let mut history = self.companies_members_history(&member.id);
             history.push(*transaction);
             let history_hash = history.object_hash();
-            member.update(roles, occupation, wage, default_cost_tags, updated, &history_hash)
+            member.update(roles, occupation.as_ref().map(|x| x.as_str()), wage, default_cost_tags, updated, &history_hash)
         };
         self.companies_members().put(&crypto::hash(member.id.as_bytes()), member);
     }
@@ -346,6 +591,16 @@ impl<T> Schema<T>
         MapIndex::new_in_family("basis.labor.idx_company_id_rolling", &crypto::hash(company_id.as_bytes()), self.access.clone())
     }
 
+    pub fn labor_idx_user_id(&self, user_id: &str) -> ListIndex<T, String> {
+        ListIndex::new_in_family("basis.labor.idx_user_id", &crypto::hash(user_id.as_bytes()), self.access.clone())
+    }
+
+    /// user_id -> labor_id for each open (clocked in, not yet clocked out)
+    /// shift within a company
+    pub fn labor_idx_company_id_open(&self, company_id: &str) -> MapIndex<T, String, String> {
+        MapIndex::new_in_family("basis.labor.idx_company_id_open", &crypto::hash(company_id.as_bytes()), self.access.clone())
+    }
+
     pub fn get_labor(&self, id: &str) -> Option<Labor> {
         self.labor().get(&crypto::hash(id.as_bytes()))
     }
@@ -356,23 +611,77 @@ impl<T> Schema<T>
             .map(|x| self.get_labor(&x))
             .filter(|x| x.is_some())
             .map(|x| x.unwrap())
-            .filter(|x| x.is_finalized())
+            .filter(|x| x.is_finalized() && !x.is_voided())
+            .collect::<Vec<_>>()
+    }
+
+    /// Get a user's labor records across all companies, optionally limited
+    /// to records that start within the given (inclusive) range.
+    pub fn get_labor_by_user_id(&self, user_id: &str, from: Option<&DateTime<Utc>>, to: Option<&DateTime<Utc>>) -> Vec<Labor> {
+        self.labor_idx_user_id(user_id)
+            .iter()
+            .filter_map(|x| self.get_labor(&x))
+            .filter(|x| from.map(|from| &x.start >= from).unwrap_or(true))
+            .filter(|x| to.map(|to| &x.start <= to).unwrap_or(true))
             .collect::<Vec<_>>()
     }
 
-    pub fn labor_create(&mut self, id: &str, company_id: &str, user_id: &str, occupation: &str, wage: f64, cost_tags: &Vec<CostTagEntry>, created: &DateTime<Utc>, transaction: &Hash) {
+    /// Get the shifts currently open (clocked in) within a company
+    pub fn get_labor_open(&self, company_id: &str) -> Vec<Labor> {
+        self.labor_idx_company_id_open(company_id)
+            .values()
+            .map(|x| self.get_labor(&x))
+            .filter(|x| x.is_some())
+            .map(|x| x.unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    /// Get the open shift (if any) for a user within a company
+    pub fn get_labor_open_by_user_id(&self, company_id: &str, user_id: &str) -> Option<Labor> {
+        self.labor_idx_company_id_open(company_id)
+            .get(user_id)
+            .and_then(|x| self.get_labor(&x))
+    }
+
+    /// Find a labor record for the given user (within the given company) whose
+    /// start..end range overlaps the given one. Records with no end (open
+    /// shifts) run forever, as does the given range if `end` is None.
+    /// `exclude_id` lets us skip the record we're checking against.
+    pub fn labor_find_overlap(&self, company_id: &str, user_id: &str, start: &DateTime<Utc>, end: Option<&DateTime<Utc>>, exclude_id: Option<&str>) -> Option<Labor> {
+        let empty = util::time::default_time();
+        self.labor_idx_user_id(user_id)
+            .iter()
+            .filter(|x| Some(x.as_str()) != exclude_id)
+            .filter_map(|x| self.get_labor(&x))
+            .filter(|x| x.company_id == company_id && x.start != empty && !x.is_voided())
+            .find(|x| {
+                let starts_before_end = match end {
+                    Some(end) => &x.start < end,
+                    None => true,
+                };
+                let ends_after_start = x.end == empty || &x.end > start;
+                starts_before_end && ends_after_start
+            })
+    }
+
+    pub fn labor_create(&mut self, id: &str, company_id: &str, user_id: &str, occupation: &str, wage: f64, cost_tags: &Vec<CostTagEntry>, created: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
+        // the member's occupation may have been merged since they were
+        // assigned it
+        let occupation = self.occupation_normalize(occupation);
         let labor = {
             let mut history = self.labor_history(id);
             history.push(*transaction);
             let history_hash = history.object_hash();
-            Labor::new(id, company_id, user_id, occupation, wage, cost_tags, Some(created), None, created, created, history.len(), &history_hash)
+            Labor::new(id, company_id, user_id, &occupation, wage, cost_tags, Some(created), None, created, created, None, "", history.len(), &history_hash)
         };
         self.labor().put(&crypto::hash(id.as_bytes()), labor.clone());
         self.labor_idx_company_id(company_id).push(id.to_owned());
-        self.labor_update_rolling_index(&labor, None);
+        self.labor_idx_user_id(user_id).push(id.to_owned());
+        self.labor_update_open_index(&labor);
+        self.labor_update_rolling_index(&labor, None)
     }
 
-    pub fn labor_update(&mut self, labor: Labor, cost_tags: Option<&Vec<CostTagEntry>>, start: Option<&DateTime<Utc>>, end: Option<&DateTime<Utc>>, updated: &DateTime<Utc>, transaction: &Hash) {
+    pub fn labor_update(&mut self, labor: Labor, cost_tags: Option<&Vec<CostTagEntry>>, start: Option<&DateTime<Utc>>, end: Option<&DateTime<Utc>>, updated: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
         let id = labor.id.clone();
         let labor_original = labor.clone();
         let labor = {
@@ -382,10 +691,11 @@ impl<T> Schema<T>
             labor.update(cost_tags, start, end, updated, &history_hash)
         };
         self.labor().put(&crypto::hash(id.as_bytes()), labor.clone());
-        self.labor_update_rolling_index(&labor, Some(&labor_original));
+        self.labor_update_open_index(&labor);
+        self.labor_update_rolling_index(&labor, Some(&labor_original))
     }
 
-    pub fn labor_set_wage(&mut self, labor: Labor, wage: f64, updated: &DateTime<Utc>, transaction: &Hash) {
+    pub fn labor_set_wage(&mut self, labor: Labor, wage: f64, updated: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
         let id = labor.id.clone();
         let labor_original = labor.clone();
         let labor = {
@@ -395,16 +705,44 @@ impl<T> Schema<T>
             labor.set_wage(wage, updated, &history_hash)
         };
         self.labor().put(&crypto::hash(id.as_bytes()), labor.clone());
-        self.labor_update_rolling_index(&labor, Some(&labor_original));
+        self.labor_update_rolling_index(&labor, Some(&labor_original))
     }
 
-    fn labor_update_rolling_index(&self, labor: &Labor, original: Option<&Labor>) {
+    /// Void a labor record. The record stays in the labor table (and its
+    /// indexes) but its costs are pulled out of the company's aggregates.
+    pub fn labor_void(&mut self, labor: Labor, reason: &str, voided: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
+        let id = labor.id.clone();
+        let labor_original = labor.clone();
+        let labor = {
+            let mut history = self.labor_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            labor.void(reason, voided, &history_hash)
+        };
+        self.labor().put(&crypto::hash(id.as_bytes()), labor.clone());
+        self.labor_update_open_index(&labor);
+        self.labor_update_rolling_index(&labor, Some(&labor_original))
+    }
+
+    /// Keep the open shift index in sync with a labor record. A record with
+    /// no end is its user's open shift, and once it gets an end it's removed
+    /// (unless a newer shift has taken its place). Voided shifts are never
+    /// open.
+    fn labor_update_open_index(&self, labor: &Labor) {
+        let mut idx = self.labor_idx_company_id_open(&labor.company_id);
+        if labor.end == util::time::default_time() && !labor.is_voided() {
+            idx.put(&labor.user_id, labor.id.clone());
+        } else if idx.get(&labor.user_id).as_ref() == Some(&labor.id) {
+            idx.remove(&labor.user_id);
+        }
+    }
+
+    fn labor_update_rolling_index(&self, labor: &Labor, original: Option<&Labor>) -> BResult<()> {
         let mut idx = self.labor_idx_company_id_rolling(&labor.company_id);
         if is_rotate_record_obsolete(&mut idx, labor.created.timestamp()) {
-            return;
+            return Ok(());
         }
-        // one year cutoff, hardcoded for now
-        let cutoff = util::time::from_timestamp(labor.created.timestamp() - (3600 * 24 * 365));
+        let cutoff = util::time::from_timestamp(labor.created.timestamp() - self.get_costing_config().window_secs);
         let labor_tbl = self.labor();
         let mut cost_agg = self.costs_aggregate(&labor.company_id);
         let mut bucket_map_labor = match cost_agg.get("labor.v1") {
@@ -413,27 +751,103 @@ impl<T> Schema<T>
         };
 
         let mut op_cb_impl = |labor: Labor, is_remove: bool| {
-            if !labor.is_finalized() {
-                return;
+            // voided labor was already pulled from the tally when it was
+            // voided, so it never gets added or removed here
+            if !labor.is_finalized() || labor.is_voided() {
+                return Ok(());
             }
             info!("labor::rolling::agg::{} -- company {}", if is_remove { "remove" } else { "add" }, labor.company_id);
-            let tagged_costs = labor.get_tagged_costs();
+            let tagged_costs = labor.try_get_tagged_costs()?;
             if is_remove {
-                bucket_map_labor.subtract_map(&tagged_costs);
+                bucket_map_labor.try_subtract_map(&tagged_costs)
             } else {
-                bucket_map_labor.add_map(&tagged_costs);
+                bucket_map_labor.try_add_map(&tagged_costs)
             }
         };
-        original.map(|x| op_cb_impl(x.clone(), true));
+        if let Some(x) = original {
+            op_cb_impl(x.clone(), true)?;
+        }
         let op_cb = |labor_id: String, is_remove: bool| {
             let labor = match labor_tbl.get(&crypto::hash(labor_id.as_bytes())) {
                 Some(x) => x,
-                None => return,
+                None => return Ok(()),
             };
-            op_cb_impl(labor, is_remove);
+            op_cb_impl(labor, is_remove)
         };
-        index_and_rotate_mapindex(&mut idx, labor.created.timestamp(), &labor.id, &cutoff, op_cb);
+        index_and_rotate_mapindex(&mut idx, labor.created.timestamp(), &labor.id, &cutoff, op_cb)?;
         cost_agg.put(&String::from("labor.v1"), bucket_map_labor);
+        Ok(())
+    }
+
+    // -------------------------------------------------------------------------
+    // Labor corrections
+    // -------------------------------------------------------------------------
+    pub fn labor_corrections(&self) -> ProofMapIndex<T, Hash, LaborCorrection> {
+        ProofMapIndex::new("basis.labor_corrections.table", self.access.clone())
+    }
+
+    pub fn labor_corrections_history(&self, id: &str) -> ProofListIndex<T, Hash> {
+        ProofListIndex::new_in_family("basis.labor_corrections.history", &crypto::hash(id.as_bytes()), self.access.clone())
+    }
+
+    pub fn labor_corrections_idx_labor_id(&self, labor_id: &str) -> ListIndex<T, String> {
+        ListIndex::new_in_family("basis.labor_corrections.idx_labor_id", &crypto::hash(labor_id.as_bytes()), self.access.clone())
+    }
+
+    /// correction_id -> labor_id for each correction within a company that's
+    /// waiting on review
+    pub fn labor_corrections_idx_company_id_pending(&self, company_id: &str) -> MapIndex<T, String, String> {
+        MapIndex::new_in_family("basis.labor_corrections.idx_company_id_pending", &crypto::hash(company_id.as_bytes()), self.access.clone())
+    }
+
+    pub fn get_labor_correction(&self, id: &str) -> Option<LaborCorrection> {
+        self.labor_corrections().get(&crypto::hash(id.as_bytes()))
+    }
+
+    pub fn get_labor_corrections_by_labor_id(&self, labor_id: &str) -> Vec<LaborCorrection> {
+        self.labor_corrections_idx_labor_id(labor_id)
+            .iter()
+            .map(|id| self.get_labor_correction(&id))
+            .filter(|x| x.is_some())
+            .map(|x| x.unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    pub fn get_labor_corrections_pending(&self, company_id: &str) -> Vec<LaborCorrection> {
+        self.labor_corrections_idx_company_id_pending(company_id)
+            .keys()
+            .map(|id| self.get_labor_correction(&id))
+            .filter(|x| x.is_some())
+            .map(|x| x.unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    pub fn labor_corrections_create(&mut self, id: &str, labor: &Labor, user_id: &str, start: &DateTime<Utc>, end: &DateTime<Utc>, reason: &str, created: &DateTime<Utc>, transaction: &Hash) {
+        let correction = {
+            let mut history = self.labor_corrections_history(id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            LaborCorrection::new(id, &labor.id, &labor.company_id, user_id, start, end, reason, &CorrectionStatus::Pending, "", "", created, created, history.len(), &history_hash)
+        };
+        self.labor_corrections().put(&crypto::hash(id.as_bytes()), correction);
+        self.labor_corrections_idx_labor_id(&labor.id).push(id.to_owned());
+        self.labor_corrections_idx_company_id_pending(&labor.company_id).put(&id.to_owned(), labor.id.clone());
+    }
+
+    /// Approve or reject a correction. This only records the decision, the
+    /// caller is responsible for applying an approved correction to the labor
+    /// record.
+    pub fn labor_corrections_review(&mut self, correction: LaborCorrection, status: &CorrectionStatus, reviewer_id: &str, review_note: &str, updated: &DateTime<Utc>, transaction: &Hash) {
+        let correction = {
+            let mut history = self.labor_corrections_history(&correction.id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            correction.review(status, reviewer_id, review_note, updated, &history_hash)
+        };
+        self.labor_corrections().put(&crypto::hash(correction.id.as_bytes()), correction.clone());
+        if !correction.is_pending() {
+            self.labor_corrections_idx_company_id_pending(&correction.company_id).remove(&correction.id);
+        }
     }
 
     // -------------------------------------------------------------------------
@@ -533,6 +947,69 @@ impl<T> Schema<T>
         self.products_idx_company_active(&company_id).remove(&id);
     }
 
+    // -------------------------------------------------------------------------
+    // Product listings
+    // -------------------------------------------------------------------------
+    pub fn product_listings(&self) -> ProofMapIndex<T, Hash, ProductListing> {
+        ProofMapIndex::new("basis.product_listings.table", self.access.clone())
+    }
+
+    pub fn product_listings_idx_company_id(&self, company_id: &str) -> KeySetIndex<T, String> {
+        KeySetIndex::new_in_family("basis.product_listings.idx_company_id", &crypto::hash(company_id.as_bytes()), self.access.clone())
+    }
+
+    pub fn product_listings_history(&self, id: &str) -> ProofListIndex<T, Hash> {
+        ProofListIndex::new_in_family("basis.product_listings.history", &crypto::hash(id.as_bytes()), self.access.clone())
+    }
+
+    pub fn get_product_listing(&self, id: &str) -> Option<ProductListing> {
+        self.product_listings().get(&crypto::hash(id.as_bytes()))
+    }
+
+    pub fn get_product_listings_by_company_id(&self, company_id: &str) -> Vec<ProductListing> {
+        self.product_listings_idx_company_id(company_id)
+            .iter()
+            .map(|x| self.get_product_listing(&x))
+            .filter(|x| x.is_some())
+            .map(|x| x.unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    pub fn product_listings_create(&mut self, id: &str, company_id: &str, name: &str, options: &HashMap<String, String>, products: &HashMap<String, ProductListingEntry>, meta: &str, active: bool, created: &DateTime<Utc>, transaction: &Hash) {
+        let listing = {
+            let mut history = self.product_listings_history(id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            ProductListing::new(id, company_id, name, options, products, meta, active, created, created, None, history.len(), &history_hash)
+        };
+        self.product_listings().put(&crypto::hash(id.as_bytes()), listing);
+        self.product_listings_idx_company_id(company_id).insert(id.to_owned());
+    }
+
+    pub fn product_listings_update(&mut self, listing: ProductListing, name: Option<&str>, options: Option<&HashMap<String, String>>, products: Option<&HashMap<String, ProductListingEntry>>, meta: Option<&str>, active: Option<bool>, updated: &DateTime<Utc>, transaction: &Hash) {
+        let id = listing.id.clone();
+        let listing = {
+            let mut history = self.product_listings_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            listing.update(name, options, products, meta, active, updated, &history_hash)
+        };
+        self.product_listings().put(&crypto::hash(id.as_bytes()), listing);
+    }
+
+    pub fn product_listings_delete(&mut self, listing: ProductListing, deleted: &DateTime<Utc>, transaction: &Hash) {
+        let id = listing.id.clone();
+        let company_id = listing.company_id.clone();
+        let listing = {
+            let mut history = self.product_listings_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            listing.delete(deleted, &history_hash)
+        };
+        self.product_listings().put(&crypto::hash(id.as_bytes()), listing);
+        self.product_listings_idx_company_id(&company_id).remove(&id);
+    }
+
     // -------------------------------------------------------------------------
     // Resource tags
     // -------------------------------------------------------------------------
@@ -593,10 +1070,45 @@ impl<T> Schema<T>
         MapIndex::new_in_family("basis.costs_aggregate.table", &crypto::hash(company_id.as_bytes()), self.access.clone())
     }
 
+    pub fn product_costs_history(&self, product_id: &str) -> ListIndex<T, CostsHistoryEntry> {
+        ListIndex::new_in_family("basis.product_costs.history", &crypto::hash(product_id.as_bytes()), self.access.clone())
+    }
+
+    pub fn product_costs_baseline(&self) -> MapIndex<T, String, CostsHistoryEntry> {
+        MapIndex::new("basis.product_costs.baseline", self.access.clone())
+    }
+
+    /// Recalculate a company's cost aggregates from scratch using the records
+    /// currently in its rolling indexes. This is what the incrementally
+    /// updated buckets in `costs_aggregate()` *should* hold.
+    pub fn costs_aggregate_calculate(&self, company_id: &str) -> BResult<HashMap<String, CostsTallyMap>> {
+        costs_aggregate_from_records(&self.get_orders_outgoing_recent(company_id), &self.get_orders_incoming_recent(company_id), &self.get_labor_recent(company_id))
+    }
+
+    /// Overwrite a company's cost aggregates. Any buckets not in `aggregates`
+    /// are dropped.
+    pub fn costs_aggregate_set(&self, company_id: &str, aggregates: &HashMap<String, CostsTallyMap>) {
+        let mut cost_agg = self.costs_aggregate(company_id);
+        cost_agg.clear();
+        for (bucket, tally_map) in aggregates {
+            cost_agg.put(bucket, tally_map.clone());
+        }
+    }
+
     pub fn get_product_costs(&self, product_id: &str) -> Option<Costs> {
         self.product_costs().get(product_id)
     }
 
+    /// Grab a product's cost history, optionally limited to entries with
+    /// timestamps within the given (inclusive) range.
+    pub fn get_product_costs_history(&self, product_id: &str, from: Option<&DateTime<Utc>>, to: Option<&DateTime<Utc>>) -> Vec<CostsHistoryEntry> {
+        self.product_costs_history(product_id)
+            .iter()
+            .filter(|x| from.map(|from| &x.timestamp >= from).unwrap_or(true))
+            .filter(|x| to.map(|to| &x.timestamp <= to).unwrap_or(true))
+            .collect::<Vec<_>>()
+    }
+
     pub fn get_product_with_costs_tagged(&self, product_id: &str) -> (Option<Product>, Option<Costs>, Option<ResourceTag>) {
         let product = self.get_product(product_id);
         let (costs, tag) = if product.is_some() {
@@ -607,8 +1119,233 @@ impl<T> Schema<T>
         (product, costs, tag)
     }
 
+    /// Grab the costs a product's current baseline period started with. A
+    /// baseline lasts for one costing window, after which it rolls forward to
+    /// whatever costs the product has at that point (see
+    /// `product_costs_baseline_update()`).
+    pub fn get_product_costs_baseline(&self, product_id: &str) -> Option<Costs> {
+        match self.product_costs_baseline().get(product_id) {
+            Some(ref entry) if !self.is_product_costs_baseline_expired(entry) => Some(entry.costs.clone()),
+            _ => self.get_product_costs(product_id),
+        }
+    }
+
+    fn is_product_costs_baseline_expired(&self, entry: &CostsHistoryEntry) -> bool {
+        let (_, block_time) = self.get_block_info();
+        entry.timestamp.timestamp() + self.get_costing_config().window_secs <= block_time.timestamp()
+    }
+
+    /// If a product's baseline has expired (or it never had one), start a new
+    /// baseline period with its current costs. This needs to happen *before*
+    /// new costs are attached, so the baseline is the costs we calculated
+    /// from.
+    pub fn product_costs_baseline_update(&self, product_id: &str) {
+        let expired = self.product_costs_baseline().get(product_id)
+            .map(|x| self.is_product_costs_baseline_expired(&x))
+            .unwrap_or(true);
+        if !expired {
+            return;
+        }
+        if let Some(costs) = self.get_product_costs(product_id) {
+            let (height, timestamp) = self.get_block_info();
+            self.product_costs_baseline().put(&product_id.to_string(), CostsHistoryEntry::new(&costs, height, &timestamp));
+        }
+    }
+
+    /// Set a product's costs. If they changed, the new costs are recorded in
+    /// the product's cost history (one entry per block, so if the costs change
+    /// more than once in a block only the last change is kept).
     pub fn product_costs_attach(&self, product_id: &str, costs: &Costs) {
+        if self.get_product_costs(product_id).as_ref() == Some(costs) {
+            return;
+        }
         self.product_costs().put(&product_id.to_string(), costs.clone());
+        let (height, timestamp) = self.get_block_info();
+        let entry = CostsHistoryEntry::new(costs, height, &timestamp);
+        let mut history = self.product_costs_history(product_id);
+        match history.last() {
+            Some(ref last) if last.height == height => {
+                let idx = history.len() - 1;
+                history.set(idx, entry);
+            }
+            _ => history.push(entry),
+        }
+    }
+
+    /// Get the height of the block currently being built, along with the time
+    /// the previous block was committed (going by its first precommit). We
+    /// can't use the local clock for anything stored since every node has to
+    /// come up with the same value.
+    pub fn get_block_info(&self) -> (u64, DateTime<Utc>) {
+        let core_schema = blockchain::Schema::new(self.access.clone());
+        let block_hashes = core_schema.block_hashes_by_height();
+        let timestamp = block_hashes.last()
+            .and_then(|hash| core_schema.precommits(&hash).iter().next())
+            .map(|precommit| precommit.time())
+            .unwrap_or(util::time::default_time());
+        (block_hashes.len(), timestamp)
+    }
+
+    /// Grab the window of time a company's costing data currently covers. The
+    /// end of the window is the current block time (so a company that's gone
+    /// quiet doesn't keep reporting a stale window), and the start is the
+    /// configured window length before that. Records are allowed to be
+    /// stamped a bit ahead of the block time, so if the company's newest
+    /// record is later than the block time we end the window there instead.
+    pub fn get_costing_window(&self, company_id: &str) -> (DateTime<Utc>, DateTime<Utc>) {
+        let (_, block_time) = self.get_block_info();
+        let end = vec![
+                self.orders_idx_company_id_from_rolling(company_id).keys().last(),
+                self.orders_idx_company_id_to_rolling(company_id).keys().last(),
+                self.labor_idx_company_id_rolling(company_id).keys().last(),
+            ]
+            .into_iter()
+            .filter_map(|x| x)
+            .map(|x| key_to_datetime(&x))
+            .chain(vec![block_time])
+            .max()
+            .unwrap_or(util::time::default_time());
+        let start = util::time::from_timestamp(end.timestamp() - self.get_costing_config().window_secs);
+        (start, end)
+    }
+
+    // -------------------------------------------------------------------------
+    // Costing config
+    // -------------------------------------------------------------------------
+    pub fn costing_config(&self) -> Entry<T, CostingConfig> {
+        Entry::new("basis.costing_config", self.access.clone())
+    }
+
+    /// Get the costing config we're currently running under. This only changes
+    /// when a new config is applied via `costs::apply_costing_config()`.
+    pub fn get_costing_config(&self) -> CostingConfig {
+        self.costing_config().get().unwrap_or_default()
+    }
+
+    /// Get the costing config from the network's current configuration (as
+    /// managed by the exonum-configuration service). This might not be the one
+    /// we're running under yet.
+    pub fn get_costing_config_actual(&self) -> Option<CostingConfig> {
+        let core_schema = blockchain::Schema::new(self.access.clone());
+        let service_config = core_schema.actual_configuration().services.get(SERVICE_NAME)?.clone();
+        match serde_json::from_value::<ServiceConfig>(service_config) {
+            Ok(x) => Some(x.costing),
+            Err(e) => {
+                warn!("schema::get_costing_config_actual() -- bad service config: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn costing_config_set(&self, config: &CostingConfig) {
+        self.costing_config().set(config.clone());
+    }
+
+    // -------------------------------------------------------------------------
+    // Cost propagation
+    // -------------------------------------------------------------------------
+    /// company_id -> the company's position in the dirty queue
+    pub fn costs_dirty(&self) -> MapIndex<T, String, u64> {
+        MapIndex::new("basis.costs_dirty.table", self.access.clone())
+    }
+
+    /// position -> company_id. companies are recalculated in the order they
+    /// were queued.
+    pub fn costs_dirty_queue(&self) -> MapIndex<T, u64, String> {
+        MapIndex::new("basis.costs_dirty.queue", self.access.clone())
+    }
+
+    pub fn costs_dirty_seq(&self) -> Entry<T, u64> {
+        Entry::new("basis.costs_dirty.seq", self.access.clone())
+    }
+
+    /// Grab the queued (dirty) company ids, in the order they'll be processed
+    pub fn get_costs_dirty(&self) -> Vec<String> {
+        self.costs_dirty_queue()
+            .values()
+            .collect::<Vec<_>>()
+    }
+
+    /// Queue a company to have its costs recalculated. Does nothing if the
+    /// company is already queued.
+    pub fn costs_dirty_mark(&self, company_id: &str) {
+        let mut dirty = self.costs_dirty();
+        if dirty.contains(company_id) {
+            return;
+        }
+        let mut seq_entry = self.costs_dirty_seq();
+        let seq = seq_entry.get().unwrap_or(0) + 1;
+        seq_entry.set(seq);
+        dirty.put(&company_id.to_owned(), seq);
+        self.costs_dirty_queue().put(&seq, company_id.to_owned());
+    }
+
+    /// Take a company out of the dirty queue
+    pub fn costs_dirty_remove(&self, company_id: &str) {
+        let mut dirty = self.costs_dirty();
+        if let Some(seq) = dirty.get(company_id) {
+            dirty.remove(company_id);
+            self.costs_dirty_queue().remove(&seq);
+        }
+    }
+
+    /// Work out what a company's rolling indexes and cost aggregates should
+    /// hold under a costing window of `window_secs`, from its full order and
+    /// labor lists. This needs to happen when the costing window changes,
+    /// since records that were rotated out under the old window might belong
+    /// in the new one (and vice versa). The result matches replaying the
+    /// company's records oldest to newest, the same order they'd normally
+    /// come in.
+    ///
+    /// Nothing is written, so a failure here leaves the company as it was.
+    /// Pass the result to `costs_rebuild_rolling_set()` to apply it.
+    pub fn costs_rebuild_rolling_calculate(&self, company_id: &str, window_secs: i64) -> BResult<RollingRebuild> {
+        let order_key = |x: &Order| (x.created.timestamp(), x.id.clone());
+        let orders_from = self.orders_idx_company_id_from(company_id).iter()
+            .filter_map(|x| self.get_order(&x))
+            .collect::<Vec<_>>();
+        let orders_from = rotate_keep(orders_from, window_secs, order_key);
+        let orders_to = self.orders_idx_company_id_to(company_id).iter()
+            .filter_map(|x| self.get_order(&x))
+            .collect::<Vec<_>>();
+        let orders_to = rotate_keep(orders_to, window_secs, order_key);
+        let labor = self.labor_idx_company_id(company_id).iter()
+            .filter_map(|x| self.get_labor(&x))
+            .collect::<Vec<_>>();
+        let labor = rotate_keep(labor, window_secs, |x| (x.created.timestamp(), x.id.clone()));
+
+        let aggregates = {
+            let orders_outgoing = orders_from.iter().map(|(_, x)| x.clone()).collect::<Vec<_>>();
+            let orders_incoming = orders_to.iter().map(|(_, x)| x.clone()).collect::<Vec<_>>();
+            let labor_recent = labor.iter()
+                .map(|(_, x)| x.clone())
+                .filter(|x| x.is_finalized() && !x.is_voided())
+                .collect::<Vec<_>>();
+            costs_aggregate_from_records(&orders_outgoing, &orders_incoming, &labor_recent)?
+        };
+        Ok(RollingRebuild {
+            orders_from: orders_from.into_iter().map(|(key, x)| (key, x.id)).collect(),
+            orders_to: orders_to.into_iter().map(|(key, x)| (key, x.id)).collect(),
+            labor: labor.into_iter().map(|(key, x)| (key, x.id)).collect(),
+            aggregates,
+        })
+    }
+
+    /// Replace a company's rolling indexes and cost aggregates with the ones
+    /// from `costs_rebuild_rolling_calculate()`
+    pub fn costs_rebuild_rolling_set(&self, company_id: &str, rebuild: &RollingRebuild) {
+        let indexes = vec![
+            (self.orders_idx_company_id_from_rolling(company_id), &rebuild.orders_from),
+            (self.orders_idx_company_id_to_rolling(company_id), &rebuild.orders_to),
+            (self.labor_idx_company_id_rolling(company_id), &rebuild.labor),
+        ];
+        for (mut idx, entries) in indexes {
+            idx.clear();
+            for (key, id) in entries {
+                idx.put(key, id.clone());
+            }
+        }
+        self.costs_aggregate_set(company_id, &rebuild.aggregates);
     }
 
     // -------------------------------------------------------------------------
@@ -660,21 +1397,21 @@ impl<T> Schema<T>
             .collect::<Vec<_>>()
     }
 
-    pub fn orders_create(&self, id: &str, company_id_from: &str, company_id_to: &str, cost_tags: &Vec<CostTagEntry>, products: &Vec<ProductEntry>, created: &DateTime<Utc>, transaction: &Hash) {
+    pub fn orders_create(&self, id: &str, company_id_from: &str, company_id_to: &str, cost_tags: &Vec<CostTagEntry>, products: &Vec<ProductEntry>, created: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
         let order = {
             let mut history = self.orders_history(id);
             history.push(*transaction);
             let history_hash = history.object_hash();
-            Order::new(id, company_id_from, company_id_to, cost_tags, products, &ProcessStatus::New, &created, &created, history.len(), &history_hash)
+            Order::new(id, company_id_from, company_id_to, cost_tags, products, &ProcessStatus::New, "", "", &Shipping::default(), &created, &created, history.len(), &history_hash)
         };
         let id = order.id.clone();
         self.orders().put(&crypto::hash(id.as_bytes()), order.clone());
         self.orders_idx_company_id_from(company_id_from).push(id.clone());
         self.orders_idx_company_id_to(company_id_to).push(id.clone());
-        self.orders_update_rolling_index(&order, None);
+        self.orders_update_rolling_index(&order, None)
     }
 
-    pub fn orders_update_status(&self, order: Order, process_status: &ProcessStatus, updated: &DateTime<Utc>, transaction: &Hash) {
+    pub fn orders_update_status(&self, order: Order, process_status: &ProcessStatus, updated: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
         let id = order.id.clone();
         let order_original = order.clone();
         let order = {
@@ -684,10 +1421,47 @@ impl<T> Schema<T>
             order.update_status(process_status, updated, &history_hash)
         };
         self.orders().put(&crypto::hash(id.as_bytes()), order.clone());
-        self.orders_update_rolling_index(&order, Some(&order_original));
+        self.orders_update_rolling_index(&order, Some(&order_original))
+    }
+
+    pub fn orders_cancel(&self, order: Order, canceled_by: &str, cancel_reason: &str, updated: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
+        let id = order.id.clone();
+        let order_original = order.clone();
+        let order = {
+            let mut history = self.orders_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            order.cancel(canceled_by, cancel_reason, updated, &history_hash)
+        };
+        self.orders().put(&crypto::hash(id.as_bytes()), order.clone());
+        self.orders_update_rolling_index(&order, Some(&order_original))
+    }
+
+    pub fn orders_update_shipping(&self, order: Order, shipping: &Shipping, updated: &DateTime<Utc>, transaction: &Hash) {
+        let id = order.id.clone();
+        let order = {
+            let mut history = self.orders_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            order.update_shipping(shipping, updated, &history_hash)
+        };
+        self.orders().put(&crypto::hash(id.as_bytes()), order);
+    }
+
+    pub fn orders_update_fulfillment(&self, order: Order, line: usize, fulfill: Fixed, cancel: Fixed, updated: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
+        let id = order.id.clone();
+        let order_original = order.clone();
+        let order = {
+            let mut history = self.orders_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            order.update_fulfillment(line, fulfill, cancel, updated, &history_hash)?
+        };
+        self.orders().put(&crypto::hash(id.as_bytes()), order.clone());
+        self.orders_update_rolling_index(&order, Some(&order_original))
     }
 
-    pub fn orders_update_cost_tags(&self, order: Order, cost_tags: &Vec<CostTagEntry>, updated: &DateTime<Utc>, transaction: &Hash) {
+    pub fn orders_update_cost_tags(&self, order: Order, cost_tags: &Vec<CostTagEntry>, updated: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
         let id = order.id.clone();
         let order_original = order.clone();
         let order = {
@@ -697,17 +1471,51 @@ impl<T> Schema<T>
             order.update_cost_tags(cost_tags, updated, &history_hash)
         };
         self.orders().put(&crypto::hash(id.as_bytes()), order.clone());
-        self.orders_update_rolling_index(&order, Some(&order_original));
+        self.orders_update_rolling_index(&order, Some(&order_original))
+    }
+
+    /// Reprice the given orders with new per-unit product costs (product_id ->
+    /// costs), and work out what that does to each buyer's `costs.v1`
+    /// aggregate. Nothing is written, so a failure here can't leave anything
+    /// half-updated: hand the results to `orders_reprice_set()`.
+    pub fn orders_reprice_calculate(&self, orders: &Vec<Order>, product_costs: &HashMap<String, Costs>) -> BResult<(Vec<Order>, HashMap<String, CostsTallyMap>)> {
+        let mut repriced = Vec::new();
+        let mut aggregates: HashMap<String, CostsTallyMap> = HashMap::new();
+        for order in orders {
+            let order_new = order.reprice(product_costs);
+            // only finalized orders the buyer is still tracking count towards
+            // its aggregates (see orders_update_rolling_index())
+            let key = format!("{}:{}", order.created.timestamp(), order.id);
+            if order.process_status == ProcessStatus::Finalized && self.orders_idx_company_id_from_rolling(&order.company_id_from).contains(&key) {
+                let bucket_map_costs = aggregates.entry(order.company_id_from.clone())
+                    .or_insert_with(|| self.costs_aggregate(&order.company_id_from).get("costs.v1").unwrap_or_default());
+                bucket_map_costs.try_subtract_map(&order.try_get_tagged_costs()?)?;
+                bucket_map_costs.try_add_map(&order_new.try_get_tagged_costs()?)?;
+            }
+            repriced.push(order_new);
+        }
+        Ok((repriced, aggregates))
     }
 
-    fn orders_update_rolling_index(&self, order: &Order, original: Option<&Order>) {
+    /// Save the repriced orders and aggregates from
+    /// `orders_reprice_calculate()`
+    pub fn orders_reprice_set(&self, orders: &Vec<Order>, aggregates: &HashMap<String, CostsTallyMap>) {
+        let mut order_tbl = self.orders();
+        for order in orders {
+            order_tbl.put(&crypto::hash(order.id.as_bytes()), order.clone());
+        }
+        for (company_id, bucket_map_costs) in aggregates {
+            self.costs_aggregate(company_id).put(&String::from("costs.v1"), bucket_map_costs.clone());
+        }
+    }
+
+    fn orders_update_rolling_index(&self, order: &Order, original: Option<&Order>) -> BResult<()> {
         let mut idx_from = self.orders_idx_company_id_from_rolling(&order.company_id_from);
         let mut idx_to = self.orders_idx_company_id_to_rolling(&order.company_id_to);
         if is_rotate_record_obsolete(&mut idx_from, order.created.timestamp()) {
-            return;
+            return Ok(());
         }
-        // one year cutoff, hardcoded for now
-        let cutoff = util::time::from_timestamp(order.created.timestamp() - (3600 * 24 * 365));
+        let cutoff = util::time::from_timestamp(order.created.timestamp() - self.get_costing_config().window_secs);
         let order_tbl = self.orders();
 
         // company from (the company making the order) is going to track this
@@ -719,25 +1527,27 @@ impl<T> Schema<T>
         };
         let mut op_cb_impl = |order: Order, is_remove: bool| {
             if order.process_status != ProcessStatus::Finalized {
-                return;
+                return Ok(());
             }
             info!("orders::rolling::agg::{} -- company {}", if is_remove { "remove" } else { "add" }, order.company_id_from);
-            let tagged_costs = order.get_tagged_costs();
+            let tagged_costs = order.try_get_tagged_costs()?;
             if is_remove {
-                bucket_map_costs.subtract_map(&tagged_costs);
+                bucket_map_costs.try_subtract_map(&tagged_costs)
             } else {
-                bucket_map_costs.add_map(&tagged_costs);
+                bucket_map_costs.try_add_map(&tagged_costs)
             }
         };
-        original.map(|x| op_cb_impl(x.clone(), true));
+        if let Some(x) = original {
+            op_cb_impl(x.clone(), true)?;
+        }
         let op_cb = |order_id: String, is_remove: bool| {
             let order = match order_tbl.get(&crypto::hash(order_id.as_bytes())) {
                 Some(x) => x,
-                None => return,
+                None => return Ok(()),
             };
             op_cb_impl(order, is_remove)
         };
-        index_and_rotate_mapindex(&mut idx_from, order.created.timestamp(), &order.id, &cutoff, op_cb);
+        index_and_rotate_mapindex(&mut idx_from, order.created.timestamp(), &order.id, &cutoff, op_cb)?;
         cost_agg.put(&String::from("costs.v1"), bucket_map_costs);
 
         // company to (the receiver) is going to track this order as product
@@ -747,36 +1557,87 @@ impl<T> Schema<T>
             Some(x) => x,
             None => CostsTallyMap::new(),
         };
-        // one year cutoff, hardcoded for now
-        let cutoff = util::time::from_timestamp(order.created.timestamp() - (3600 * 24 * 365));
         let mut op_cb_impl = |order: Order, is_remove: bool| {
             if order.process_status != ProcessStatus::Finalized {
-                return;
+                return Ok(());
             }
             info!("orders::rolling::agg::{} -- company {}", if is_remove { "remove" } else { "add" }, order.company_id_from);
             let mut outputs = Costs::new();
             for entry in &order.products {
-                outputs.track(&entry.product_id, entry.quantity);
+                outputs.try_track(&entry.product_id, entry.net_fulfilled())?;
             }
             if is_remove {
-                bucket_map_outputs.subtract("outputs", &outputs);
+                bucket_map_outputs.try_subtract("outputs", &outputs)
             } else {
-                bucket_map_outputs.add("outputs", &outputs);
+                bucket_map_outputs.try_add("outputs", &outputs)
             }
         };
-        match original {
-            Some(x) => op_cb_impl(x.clone(), true),
-            None => {}
+        if let Some(x) = original {
+            op_cb_impl(x.clone(), true)?;
         }
         let op_cb = |order_id: String, is_remove: bool| {
             let order = match order_tbl.get(&crypto::hash(order_id.as_bytes())) {
                 Some(x) => x,
-                None => return,
+                None => return Ok(()),
             };
             op_cb_impl(order, is_remove)
         };
-        index_and_rotate_mapindex(&mut idx_to, order.created.timestamp(), &order.id, &cutoff, op_cb);
+        index_and_rotate_mapindex(&mut idx_to, order.created.timestamp(), &order.id, &cutoff, op_cb)?;
         cost_agg.put(&String::from("product_outputs.v1"), bucket_map_outputs);
+        Ok(())
+    }
+
+    // -------------------------------------------------------------------------
+    // Order returns
+    // -------------------------------------------------------------------------
+    pub fn order_returns(&self) -> ProofMapIndex<T, Hash, OrderReturn> {
+        ProofMapIndex::new("basis.order_returns.table", self.access.clone())
+    }
+
+    pub fn order_returns_history(&self, id: &str) -> ProofListIndex<T, Hash> {
+        ProofListIndex::new_in_family("basis.order_returns.history", &crypto::hash(id.as_bytes()), self.access.clone())
+    }
+
+    pub fn order_returns_idx_order_id(&self, order_id: &str) -> ListIndex<T, String> {
+        ListIndex::new_in_family("basis.order_returns.idx_order_id", &crypto::hash(order_id.as_bytes()), self.access.clone())
+    }
+
+    pub fn get_order_return(&self, id: &str) -> Option<OrderReturn> {
+        self.order_returns().get(&crypto::hash(id.as_bytes()))
+    }
+
+    pub fn get_order_returns_by_order_id(&self, order_id: &str) -> Vec<OrderReturn> {
+        self.order_returns_idx_order_id(order_id)
+            .iter()
+            .map(|id| self.get_order_return(&id))
+            .filter(|x| x.is_some())
+            .map(|x| x.unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    /// Create a return against an order. This also marks the returned
+    /// quantities on the original order, which re-tallies it in the rolling
+    /// aggregates for both the buyer and the supplier.
+    pub fn order_returns_create(&self, id: &str, order: Order, products: &Vec<ReturnEntry>, reason: &str, created: &DateTime<Utc>, transaction: &Hash) -> BResult<()> {
+        let order_return = {
+            let mut history = self.order_returns_history(id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            OrderReturn::new(id, &order.id, &order.company_id_from, &order.company_id_to, products, reason, created, created, history.len(), &history_hash)
+        };
+        self.order_returns().put(&crypto::hash(id.as_bytes()), order_return);
+        self.order_returns_idx_order_id(&order.id).push(id.to_owned());
+
+        let order_id = order.id.clone();
+        let order_original = order.clone();
+        let order = {
+            let mut history = self.orders_history(&order_id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            order.update_returned(products, created, &history_hash)
+        };
+        self.orders().put(&crypto::hash(order_id.as_bytes()), order.clone());
+        self.orders_update_rolling_index(&order, Some(&order_original))
     }
 
     // -------------------------------------------------------------------------
@@ -842,5 +1703,68 @@ impl<T> Schema<T>
         self.cost_tags().put(&crypto::hash(id.as_bytes()), cost_tag);
         self.cost_tags_idx_company_id(&company_id).remove(&id);
     }
+
+    // -------------------------------------------------------------------------
+    // Amortization
+    // -------------------------------------------------------------------------
+    pub fn amortizations(&self) -> ProofMapIndex<T, Hash, Amortization> {
+        ProofMapIndex::new("basis.amortizations.table", self.access.clone())
+    }
+
+    pub fn amortizations_history(&self, id: &str) -> ProofListIndex<T, Hash> {
+        ProofListIndex::new_in_family("basis.amortizations.history", &crypto::hash(id.as_bytes()), self.access.clone())
+    }
+
+    pub fn amortizations_idx_company_id(&self, company_id: &str) -> KeySetIndex<T, String> {
+        KeySetIndex::new_in_family("basis.amortizations.idx_company_id", &crypto::hash(company_id.as_bytes()), self.access.clone())
+    }
+
+    pub fn get_amortization(&self, id: &str) -> Option<Amortization> {
+        self.amortizations().get(&crypto::hash(id.as_bytes()))
+    }
+
+    pub fn get_amortizations_by_company_id(&self, company_id: &str) -> Vec<Amortization> {
+        self.amortizations_idx_company_id(company_id)
+            .iter()
+            .map(|id| self.get_amortization(&id))
+            .filter(|x| x.is_some())
+            .map(|x| x.unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    pub fn amortizations_create(&mut self, id: &str, company_id: &str, name: &str, costs: &Costs, cost_tags: &Vec<CostTagEntry>, start: &DateTime<Utc>, end: &DateTime<Utc>, meta: &str, created: &DateTime<Utc>, transaction: &Hash) {
+        let amortization = {
+            let mut history = self.amortizations_history(id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            Amortization::new(id, company_id, name, costs, cost_tags, start, end, meta, created, created, None, history.len(), &history_hash)
+        };
+        self.amortizations().put(&crypto::hash(id.as_bytes()), amortization);
+        self.amortizations_idx_company_id(company_id).insert(id.to_owned());
+    }
+
+    pub fn amortizations_update(&mut self, amortization: Amortization, name: Option<&str>, costs: Option<&Costs>, cost_tags: Option<&Vec<CostTagEntry>>, start: Option<&DateTime<Utc>>, end: Option<&DateTime<Utc>>, meta: Option<&str>, updated: &DateTime<Utc>, transaction: &Hash) {
+        let id = amortization.id.clone();
+        let amortization = {
+            let mut history = self.amortizations_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            amortization.update(name, costs, cost_tags, start, end, meta, updated, &history_hash)
+        };
+        self.amortizations().put(&crypto::hash(id.as_bytes()), amortization);
+    }
+
+    pub fn amortizations_delete(&mut self, amortization: Amortization, deleted: &DateTime<Utc>, transaction: &Hash) {
+        let id = amortization.id.clone();
+        let company_id = amortization.company_id.clone();
+        let amortization = {
+            let mut history = self.amortizations_history(&id);
+            history.push(*transaction);
+            let history_hash = history.object_hash();
+            amortization.delete(deleted, &history_hash)
+        };
+        self.amortizations().put(&crypto::hash(id.as_bytes()), amortization);
+        self.amortizations_idx_company_id(&company_id).remove(&id);
+    }
 }
 
@@ -23,3 +23,23 @@ pub fn check<T>(schema: &mut Schema<T>, pubkey: &PublicKey, permission: Permissi
     Err(CommonError::InsufficientPrivileges)
 }
 
+/// Like `check`, but also allows users who have been given the permission
+/// within a specific region (via their region roles) to act on things that
+/// live in that region. A blank region id only passes system-wide checks.
+pub fn check_region<T>(schema: &mut Schema<T>, pubkey: &PublicKey, region_id: &str, permission: Permission) -> Result<(), CommonError>
+    where T: IndexAccess
+{
+    if check(schema, pubkey, permission.clone()).is_ok() {
+        return Ok(());
+    }
+    if region_id != "" {
+        if let Some(user) = schema.get_user_by_pubkey(pubkey) {
+            for role in &user.roles_in_region(region_id) {
+                if role.is_regional() && role.can(&permission) {
+                    return Ok(())
+                }
+            }
+        }
+    }
+    Err(CommonError::InsufficientPrivileges)
+}
@@ -0,0 +1,359 @@
+use chrono::{DateTime, Utc};
+use validator::Validate;
+use exonum::{
+    blockchain::{ExecutionError, ExecutionResult, Transaction, TransactionContext},
+};
+use models::{
+    proto,
+    company::{Permission as CompanyPermission},
+    access::Permission,
+    costs::Costs,
+    cost_tag::CostTagEntry,
+};
+use util::{
+    self,
+    protobuf::empty_opt,
+};
+use crate::block::{
+    schema::Schema,
+    transactions::{company, access, costs, cost_tag},
+};
+use super::CommonError;
+
+#[derive(Debug, Fail)]
+#[repr(u8)]
+pub enum TransactionError {
+    #[fail(display = "Invalid ID")]
+    InvalidID = 0,
+
+    #[fail(display = "Company not found")]
+    CompanyNotFound = 1,
+
+    #[fail(display = "Amortization not found")]
+    AmortizationNotFound = 2,
+
+    #[fail(display = "Amortization is already deleted")]
+    AlreadyDeleted = 3,
+
+    #[fail(display = "Amortization must end after it starts")]
+    InvalidWindow = 4,
+}
+define_exec_error!(TransactionError);
+
+deftransaction! {
+    #[exonum(pb = "proto::amortization::TxCreate")]
+    pub struct TxCreate {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(custom = "super::validate_uuid")]
+        pub company_id: String,
+        #[validate(length(min = 1))]
+        pub name: String,
+        pub costs: Costs,
+        pub cost_tags: Vec<CostTagEntry>,
+        #[validate(custom = "super::validate_date")]
+        pub start: DateTime<Utc>,
+        #[validate(custom = "super::validate_date")]
+        pub end: DateTime<Utc>,
+        pub meta: String,
+        #[validate(custom = "super::validate_date")]
+        pub created: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxCreate {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::AmortizationCreate)?;
+        company::check(&mut schema, &self.company_id, pubkey, CompanyPermission::AmortizationCreate)?;
+
+        match schema.get_company(&self.company_id) {
+            Some(_) => {}
+            None => Err(TransactionError::CompanyNotFound)?,
+        }
+
+        if schema.get_amortization(&self.id).is_some() {
+            Err(CommonError::IDExists)?;
+        }
+
+        if self.end <= self.start {
+            Err(TransactionError::InvalidWindow)?;
+        }
+
+        if !util::time::is_current(&self.created) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let cost_tags = cost_tag::validate_cost_tags(&mut schema, &self.company_id, &self.cost_tags);
+        schema.amortizations_create(&self.id, &self.company_id, &self.name, &self.costs, &cost_tags, &self.start, &self.end, &self.meta, &self.created, &hash);
+        costs::calculate_product_costs(&mut schema, &self.company_id)?;
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::amortization::TxUpdate")]
+    pub struct TxUpdate {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub name: String,
+        pub costs: Costs,
+        pub cost_tags: Vec<CostTagEntry>,
+        pub start: DateTime<Utc>,
+        pub end: DateTime<Utc>,
+        pub meta: String,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxUpdate {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let amortization = schema.get_amortization(&self.id)
+            .ok_or_else(|| TransactionError::AmortizationNotFound)?;
+
+        access::check(&mut schema, pubkey, Permission::AmortizationUpdate)?;
+        company::check(&mut schema, &amortization.company_id, pubkey, CompanyPermission::AmortizationUpdate)?;
+
+        if amortization.is_deleted() {
+            Err(TransactionError::AlreadyDeleted)?;
+        }
+
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let empty_date = util::time::default_time();
+        let start = if self.start == empty_date { None } else { Some(&self.start) };
+        let end = if self.end == empty_date { None } else { Some(&self.end) };
+        if end.unwrap_or(&amortization.end) <= start.unwrap_or(&amortization.start) {
+            Err(TransactionError::InvalidWindow)?;
+        }
+
+        let company_id = amortization.company_id.clone();
+        let name = empty_opt(&self.name).map(|x| x.as_str());
+        let costs = empty_opt(&self.costs);
+        let cost_tags = empty_opt(&self.cost_tags)
+            .map(|cost_tags| cost_tag::validate_cost_tags(&mut schema, &company_id, cost_tags));
+        let meta = empty_opt(&self.meta).map(|x| x.as_str());
+
+        schema.amortizations_update(amortization, name, costs, cost_tags.as_ref(), start, end, meta, &self.updated, &hash);
+        costs::calculate_product_costs(&mut schema, &company_id)?;
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::amortization::TxDelete")]
+    pub struct TxDelete {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub memo: String,
+        #[validate(custom = "super::validate_date")]
+        pub deleted: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxDelete {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let amortization = schema.get_amortization(&self.id)
+            .ok_or_else(|| TransactionError::AmortizationNotFound)?;
+
+        access::check(&mut schema, pubkey, Permission::AmortizationDelete)?;
+        company::check(&mut schema, &amortization.company_id, pubkey, CompanyPermission::AmortizationDelete)?;
+
+        if amortization.is_deleted() {
+            Err(TransactionError::AlreadyDeleted)?;
+        }
+
+        if !util::time::is_current(&self.deleted) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let company_id = amortization.company_id.clone();
+        schema.amortizations_delete(amortization, &self.deleted, &hash);
+        costs::calculate_product_costs(&mut schema, &company_id)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use chrono::Duration;
+    use models::{
+        self,
+        cost_tag::CostTagEntry,
+        company,
+        costs::Costs,
+        fixed::Fixed,
+    };
+    use util;
+    use crate::block::{transactions, schema::Schema};
+    use crate::test::{self, gen_uuid};
+
+    #[test]
+    fn amortized_costs_land_in_products() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        testkit.create_block_with_transactions(txvec![tx_user]);
+
+        let co1_id = gen_uuid();
+        let co2_id = gen_uuid();
+        let ctag1_op_id = gen_uuid();
+        let ctag2_op_id = gen_uuid();
+        let co1_founder_id = gen_uuid();
+        let co2_founder_id = gen_uuid();
+        let tx_co1 = transactions::company::TxCreatePrivate::sign(
+            &co1_id,
+            &String::from("lasers@basis.org"),
+            &String::from("Lasers 'R' Us"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&co1_founder_id, "Laser builder", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        let tx_co2 = transactions::company::TxCreatePrivate::sign(
+            &co2_id,
+            &String::from("sharks@basis.org"),
+            &String::from("Sharks With Frickin Lasers LLC"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag2_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&co2_founder_id, "Shark wrangler", 1.0, &vec![CostTagEntry::new(&ctag2_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1, tx_co2]);
+
+        let prod_id = gen_uuid();
+        let tx_prod = transactions::product::TxCreate::sign(
+            &prod_id,
+            &co1_id,
+            &String::from("Laser beam"),
+            &models::product::Unit::WattHour,
+            &0.0,
+            &models::product::Dimensions::new(0.0, 0.0, 0.0),
+            &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+            &true,
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_prod]);
+
+        let ord_id = gen_uuid();
+        let now = util::time::now();
+        let tx_ord = transactions::order::TxCreate::sign(
+            &ord_id,
+            &co2_id,
+            &co1_id,
+            &vec![CostTagEntry::new(&ctag2_op_id, 1)],
+            &vec![models::order::ProductEntry::new(&prod_id, 100.0, &Costs::new(), false)],
+            &now,
+            &root_pub,
+            &root_sec
+        );
+        let tx_ord_fulfill = transactions::order::TxUpdateFulfillment::sign(
+            &ord_id,
+            &0,
+            &Fixed::from(100.0),
+            &Fixed::zero(),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_ord, tx_ord_fulfill]);
+
+        // a big ol' laser, paid off over four years. our costing window is a
+        // single year, so a quarter of the costs should be counted.
+        let amort_id = gen_uuid();
+        let tx_amort = transactions::amortization::TxCreate::sign(
+            &amort_id,
+            &co1_id,
+            &String::from("Laser cannon"),
+            &Costs::new_with_labor("Laser builder", 4000.0),
+            &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+            &(now - Duration::days(730)),
+            &(now + Duration::days(730)),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_amort]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let amortization = schema.get_amortization(&amort_id).unwrap();
+        assert_eq!(amortization.history_len, 1);
+        assert_eq!(schema.get_amortizations_by_company_id(&co1_id).len(), 1);
+        let costs = schema.get_product_costs(&prod_id).unwrap();
+        assert_eq!(costs.get_labor("Laser builder"), 1000.0 / 100.0);
+
+        let tx_amort_del = transactions::amortization::TxDelete::sign(
+            &amort_id,
+            &String::from("sold the laser to a competing shark operation"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_amort_del]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let amortization = schema.get_amortization(&amort_id).unwrap();
+        assert!(amortization.is_deleted());
+        assert_eq!(amortization.history_len, 2);
+        assert_eq!(schema.get_amortizations_by_company_id(&co1_id).len(), 0);
+        let costs = schema.get_product_costs(&prod_id).unwrap();
+        assert_eq!(costs.get_labor("Laser builder"), 0.0);
+
+        // negative costs can't be costed, so the whole transaction fails
+        // instead of panicking or producing negative product costs
+        let mut bad_costs = Costs::new();
+        bad_costs.labor.insert(String::from("Laser builder"), Fixed::from(-4000.0));
+        let amort_bad_id = gen_uuid();
+        let tx_amort_bad = transactions::amortization::TxCreate::sign(
+            &amort_bad_id,
+            &co1_id,
+            &String::from("Anti-laser"),
+            &bad_costs,
+            &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+            &(now - Duration::days(730)),
+            &(now + Duration::days(730)),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_amort_bad]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(schema.get_amortization(&amort_bad_id).is_none());
+        let costs = schema.get_product_costs(&prod_id).unwrap();
+        assert_eq!(costs.get_labor("Laser builder"), 0.0);
+    }
+}
+
@@ -3,7 +3,7 @@ use chrono::{DateTime, Utc};
 use validator::Validate;
 use exonum::{
     blockchain::{ExecutionError, ExecutionResult, Transaction, TransactionContext},
-    crypto::{PublicKey},
+    crypto::{self, Hash, PublicKey},
 };
 use exonum_merkledb::IndexAccess;
 use models::{
@@ -11,11 +11,14 @@ use models::{
     company::{
         TxCreatePrivateCostTag,
         TxCreatePrivateFounder,
+        TxCreateSyndicateMember,
         CompanyType,
+        ApprovalStatus,
         Permission as CompanyPermission,
         Role as CompanyRole,
     },
     access::Permission,
+    cost_tag::CostTagEntry,
 };
 use crate::block::{
     schema::Schema,
@@ -57,9 +60,114 @@ pub enum TransactionError {
 
     #[fail(display = "Company not found")]
     CompanyNotFound = 6,
+
+    #[fail(display = "Companies can only be approved or rejected")]
+    InvalidApprovalStatus = 7,
+
+    #[fail(display = "Region not found")]
+    RegionNotFound = 8,
+
+    #[fail(display = "A user can only be a founding member once")]
+    DuplicateMember = 9,
 }
 define_exec_error!(TransactionError);
 
+/// A member that gets added to a company as the company is created
+struct FoundingMember<'a> {
+    member_id: &'a str,
+    user_id: &'a str,
+    roles: Vec<CompanyRole>,
+    occupation: &'a str,
+    wage: f64,
+    default_cost_tags: &'a Vec<CostTagEntry>,
+}
+
+/// Derive a stable uuid-formatted id from a company id and a name, used for
+/// records we create on a company's behalf (like default cost tags).
+fn derive_id(company_id: &str, name: &str) -> String {
+    let hash_hex = hex::encode(crypto::hash(format!("{}:{}", company_id, name).as_bytes()).as_ref());
+    format!("{}-{}-{}-{}-{}", &hash_hex[0..8], &hash_hex[8..12], &hash_hex[12..16], &hash_hex[16..20], &hash_hex[20..32])
+}
+
+/// The cost tags a company gets if it doesn't give us any of its own
+fn default_cost_tags(company_id: &str) -> Vec<TxCreatePrivateCostTag> {
+    vec!["operating", "inventory"].into_iter()
+        .map(|name| TxCreatePrivateCostTag::new(&derive_id(company_id, name), name, ""))
+        .collect::<Vec<_>>()
+}
+
+/// Creates a company (of any type) along with its cost tags and founding
+/// members. If no cost tags are given and `use_defaults` is set, the company
+/// gets a set of defaults and any members without default cost tags are
+/// pointed at the first one.
+///
+/// Companies start out pending approval, unless they're created by someone
+/// who can approve them anyway (either everywhere or in the company's region).
+fn create_company<T>(schema: &mut Schema<T>, pubkey: &PublicKey, id: &str, ty: &CompanyType, region_id: Option<&str>, email: &str, name: &str, cost_tags: &Vec<TxCreatePrivateCostTag>, use_defaults: bool, members: &Vec<FoundingMember>, created: &DateTime<Utc>, hash: &Hash) -> ExecutionResult
+    where T: IndexAccess
+{
+    for member in members {
+        if schema.get_company_member(member.member_id).is_some() {
+            Err(CommonError::IDExists)?;
+        }
+        // members are indexed by user, so a user showing up twice would
+        // clobber their own index entry
+        if members.iter().filter(|x| x.user_id == member.user_id).count() > 1 {
+            Err(TransactionError::DuplicateMember)?;
+        }
+        if schema.get_user(member.user_id).is_none() {
+            Err(CommonError::UserNotFound)?;
+        }
+    }
+    if schema.get_company(id).is_some() {
+        Err(CommonError::IDExists)?;
+    }
+    if let Some(region_id) = region_id {
+        if schema.get_region(region_id).is_none() {
+            Err(TransactionError::RegionNotFound)?;
+        }
+    }
+    if !util::time::is_current(created) {
+        Err(CommonError::InvalidTime)?;
+    }
+    if !email.contains("@") {
+        Err(TransactionError::InvalidEmail)?;
+    }
+
+    let (cost_tags, use_default_tags) = if use_defaults && cost_tags.len() == 0 {
+        (default_cost_tags(id), true)
+    } else {
+        (cost_tags.clone(), false)
+    };
+    let approval_status = match access::check_region(schema, pubkey, region_id.unwrap_or(""), Permission::CompanySetApproved) {
+        Ok(_) => ApprovalStatus::Approved,
+        Err(_) => ApprovalStatus::Pending,
+    };
+    schema.companies_create(id, ty, region_id, email, name, &approval_status, created, hash);
+    // this map tracks the ids of our cost tags
+    let mut cost_tag_id_map = HashMap::new();
+    for cost_tag in &cost_tags {
+        if schema.get_cost_tag(&cost_tag.id).is_some() {
+            Err(CommonError::IDExists)?;
+        }
+        schema.cost_tags_create(&cost_tag.id, id, &cost_tag.name, true, &cost_tag.meta, created, hash);
+        // track this cost tag id as existing
+        cost_tag_id_map.insert(cost_tag.id.clone(), true);
+    }
+    for member in members {
+        // only allow default cost tags for our members if we know they exist
+        // (because we just created them above)
+        let mut default_cost_tag_entries = member.default_cost_tags.clone().into_iter()
+            .filter(|entry| cost_tag_id_map.contains_key(&entry.id))
+            .collect::<Vec<_>>();
+        if use_default_tags && default_cost_tag_entries.len() == 0 {
+            default_cost_tag_entries.push(CostTagEntry::new(&cost_tags[0].id, 1));
+        }
+        schema.companies_members_create(member.member_id, id, member.user_id, &member.roles, member.occupation, member.wage, &default_cost_tag_entries, created, hash);
+    }
+    Ok(())
+}
+
 deftransaction! {
     #[exonum(pb = "proto::company::TxCreatePrivate")]
     pub struct TxCreatePrivate {
@@ -72,6 +180,7 @@ deftransaction! {
         pub founder: TxCreatePrivateFounder,
         #[validate(custom = "super::validate_date")]
         pub created: DateTime<Utc>,
+        pub region_id: String,
     }
 }
 
@@ -89,36 +198,122 @@ impl Transaction for TxCreatePrivate {
             None => Err(CommonError::UserNotFound)?,
         };
 
-        if schema.get_company_member(&self.founder.member_id).is_some() {
-            Err(CommonError::IDExists)?;
-        }
+        let founder = FoundingMember {
+            member_id: &self.founder.member_id,
+            user_id: &user.id,
+            roles: vec![CompanyRole::Owner],
+            occupation: &self.founder.occupation,
+            wage: self.founder.wage,
+            default_cost_tags: &self.founder.default_cost_tags,
+        };
+        let region_id = empty_opt(&self.region_id).map(|x| x.as_str());
+        create_company(&mut schema, pubkey, &self.id, &CompanyType::Private, region_id, &self.email, &self.name, &self.cost_tags, false, &vec![founder], &self.created, &hash)
+    }
+}
 
-        if schema.get_company(&self.id).is_some() {
-            Err(CommonError::IDExists)?
-        } else if !util::time::is_current(&self.created) {
-            Err(CommonError::InvalidTime)?
-        } else if !self.email.contains("@") {
-            Err(TransactionError::InvalidEmail)?
-        } else {
-            schema.companies_create(&self.id, &CompanyType::Private, None, &self.email, &self.name, &self.created, &hash);
-            // this map tracks the ids of our cost tags
-            let mut cost_tag_id_map = HashMap::new();
-            for cost_tag in &self.cost_tags {
-                if schema.get_cost_tag(&cost_tag.id).is_some() {
-                    Err(CommonError::IDExists)?;
-                }
-                schema.cost_tags_create(&cost_tag.id, &self.id, &cost_tag.name, true, &cost_tag.meta, &self.created, &hash);
-                // track this cost tag id as existing
-                cost_tag_id_map.insert(cost_tag.id.clone(), true);
+deftransaction! {
+    #[exonum(pb = "proto::company::TxCreateSyndicate")]
+    pub struct TxCreateSyndicate {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(email(code = "email"))]
+        pub email: String,
+        pub name: String,
+        pub cost_tags: Vec<TxCreatePrivateCostTag>,
+        pub founder: TxCreatePrivateFounder,
+        pub members: Vec<TxCreateSyndicateMember>,
+        #[validate(custom = "super::validate_date")]
+        pub created: DateTime<Utc>,
+        pub region_id: String,
+    }
+}
+
+impl Transaction for TxCreateSyndicate {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::CompanyCreateSyndicate)?;
+        let user = match schema.get_user_by_pubkey(pubkey) {
+            Some(x) => x,
+            None => Err(CommonError::UserNotFound)?,
+        };
+
+        // a syndicate is owned by its workers, so every founding member gets
+        // the same (owner) say in how it's run
+        let mut members = vec![
+            FoundingMember {
+                member_id: &self.founder.member_id,
+                user_id: &user.id,
+                roles: vec![CompanyRole::Owner],
+                occupation: &self.founder.occupation,
+                wage: self.founder.wage,
+                default_cost_tags: &self.founder.default_cost_tags,
+            },
+        ];
+        for member in &self.members {
+            if members.iter().any(|x| x.member_id == member.member_id) {
+                Err(CommonError::IDExists)?;
             }
-            // only allow default cost tags for the founder if we know they
-            // exist (because we just created them above)
-            let default_cost_tag_entries = self.founder.default_cost_tags.clone().into_iter()
-                .filter(|entry| cost_tag_id_map.contains_key(&entry.id))
-                .collect::<Vec<_>>();
-            schema.companies_members_create(&self.founder.member_id, &self.id, &user.id, &vec![CompanyRole::Owner], &self.founder.occupation, self.founder.wage, &default_cost_tag_entries, &self.created, &hash);
-            Ok(())
+            members.push(FoundingMember {
+                member_id: &member.member_id,
+                user_id: &member.user_id,
+                roles: vec![CompanyRole::Owner],
+                occupation: &member.occupation,
+                wage: member.wage,
+                default_cost_tags: &member.default_cost_tags,
+            });
         }
+        let region_id = empty_opt(&self.region_id).map(|x| x.as_str());
+        create_company(&mut schema, pubkey, &self.id, &CompanyType::Syndicate, region_id, &self.email, &self.name, &self.cost_tags, true, &members, &self.created, &hash)
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::company::TxCreatePublic")]
+    pub struct TxCreatePublic {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(email(code = "email"))]
+        pub email: String,
+        pub name: String,
+        pub cost_tags: Vec<TxCreatePrivateCostTag>,
+        pub founder: TxCreatePrivateFounder,
+        #[validate(custom = "super::validate_date")]
+        pub created: DateTime<Utc>,
+        pub region_id: String,
+    }
+}
+
+impl Transaction for TxCreatePublic {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check_region(&mut schema, pubkey, &self.region_id, Permission::CompanyCreatePublic)?;
+        let user = match schema.get_user_by_pubkey(pubkey) {
+            Some(x) => x,
+            None => Err(CommonError::UserNotFound)?,
+        };
+
+        // public companies belong to the public, so whoever sets one up gets
+        // to run it but not to delete it
+        let founder = FoundingMember {
+            member_id: &self.founder.member_id,
+            user_id: &user.id,
+            roles: vec![CompanyRole::Admin],
+            occupation: &self.founder.occupation,
+            wage: self.founder.wage,
+            default_cost_tags: &self.founder.default_cost_tags,
+        };
+        let region_id = empty_opt(&self.region_id).map(|x| x.as_str());
+        create_company(&mut schema, pubkey, &self.id, &CompanyType::Public, region_id, &self.email, &self.name, &self.cost_tags, true, &vec![founder], &self.created, &hash)
     }
 }
 
@@ -194,7 +389,7 @@ impl Transaction for TxSetType {
             None => Err(TransactionError::CompanyNotFound)?,
         };
 
-        access::check(&mut schema, pubkey, Permission::CompanySetType)?;
+        access::check_region(&mut schema, pubkey, &company.region_id, Permission::CompanySetType)?;
 
         if !util::time::is_current(&self.updated) {
             Err(CommonError::InvalidTime)?
@@ -205,6 +400,91 @@ impl Transaction for TxSetType {
     }
 }
 
+deftransaction! {
+    #[exonum(pb = "proto::company::TxSetApproved")]
+    pub struct TxSetApproved {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(custom = "super::validate_enum")]
+        pub approval_status: ApprovalStatus,
+        pub reason: String,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxSetApproved {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let company = match schema.get_company(self.id.as_str()) {
+            Some(x) => x,
+            None => Err(TransactionError::CompanyNotFound)?,
+        };
+
+        access::check_region(&mut schema, pubkey, &company.region_id, Permission::CompanySetApproved)?;
+
+        match self.approval_status {
+            ApprovalStatus::Approved | ApprovalStatus::Rejected => {}
+            _ => Err(TransactionError::InvalidApprovalStatus)?,
+        }
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?
+        }
+
+        schema.companies_set_approval(company, &self.approval_status, &self.reason, &self.updated, &hash);
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::company::TxSetRegion")]
+    pub struct TxSetRegion {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub region_id: String,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxSetRegion {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let company = match schema.get_company(self.id.as_str()) {
+            Some(x) => x,
+            None => Err(TransactionError::CompanyNotFound)?,
+        };
+
+        // regional admins can move companies around within their own regions,
+        // but can't pull in companies from (or push them out to) someone else's
+        access::check_region(&mut schema, pubkey, &company.region_id, Permission::CompanySetRegion)?;
+        access::check_region(&mut schema, pubkey, &self.region_id, Permission::CompanySetRegion)?;
+
+        let region_id = empty_opt(&self.region_id).map(|x| x.as_str());
+        if let Some(region_id) = region_id {
+            if schema.get_region(region_id).is_none() {
+                Err(TransactionError::RegionNotFound)?;
+            }
+        }
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?
+        }
+
+        schema.companies_set_region(company, region_id, &self.updated, &hash);
+        Ok(())
+    }
+}
+
 deftransaction! {
     #[exonum(pb = "proto::company::TxDelete")]
     pub struct TxDelete {
@@ -243,3 +523,291 @@ impl Transaction for TxDelete {
     }
 }
 
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use models;
+    use util;
+    use crate::block::{transactions, schema::Schema};
+    use crate::test::{self, gen_uuid};
+
+    #[test]
+    fn creates_syndicates() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        let uid2 = gen_uuid();
+        let uid3 = gen_uuid();
+        let (user2_pub, _) = crypto::gen_keypair();
+        let (user3_pub, _) = crypto::gen_keypair();
+        let tx_user2 = transactions::user::TxCreate::sign(
+            &uid2,
+            &user2_pub,
+            &vec![models::access::Role::User],
+            &String::from("comrade.jill@basis.org"),
+            &String::from("Jill"),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_user3 = transactions::user::TxCreate::sign(
+            &uid3,
+            &user3_pub,
+            &vec![models::access::Role::User],
+            &String::from("comrade.jose@basis.org"),
+            &String::from("Jose"),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_user, tx_user2, tx_user3]);
+
+        let co_id = gen_uuid();
+        let founder_id = gen_uuid();
+        let member2_id = gen_uuid();
+        let member3_id = gen_uuid();
+        let tx_co = TxCreateSyndicate::sign(
+            &co_id,
+            &String::from("bikes@basis.org"),
+            &String::from("The People's Bicycle Collective"),
+            &vec![],
+            &TxCreatePrivateFounder::new(&founder_id, "Wheel truer", 1.0, &vec![]),
+            &vec![
+                TxCreateSyndicateMember::new(&member2_id, &uid2, "Frame welder", 1.0, &vec![]),
+                TxCreateSyndicateMember::new(&member3_id, &uid3, "Chain greaser", 1.0, &vec![]),
+            ],
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let company = schema.get_company(&co_id).expect("missing syndicate");
+        assert_eq!(company.ty, CompanyType::Syndicate);
+        // everyone's an owner
+        for (member_id, user_id) in vec![(&founder_id, &uid), (&member2_id, &uid2), (&member3_id, &uid3)] {
+            let member = schema.get_company_member(member_id).expect("missing member");
+            assert_eq!(&member.user_id, user_id);
+            assert_eq!(member.company_id, co_id);
+            assert_eq!(member.roles, vec![CompanyRole::Owner]);
+        }
+        // we didn't give any cost tags, so we should have the defaults and our
+        // members should be using them
+        let cost_tags = schema.get_cost_tags_by_company_id(&co_id);
+        assert_eq!(cost_tags.len(), 2);
+        let operating_id = derive_id(&co_id, "operating");
+        assert!(cost_tags.iter().any(|x| x.id == operating_id && x.name == "operating"));
+        assert!(cost_tags.iter().any(|x| x.id == derive_id(&co_id, "inventory") && x.name == "inventory"));
+        let member = schema.get_company_member(&member2_id).unwrap();
+        assert_eq!(member.default_cost_tags, vec![CostTagEntry::new(&operating_id, 1)]);
+
+        // the same user can't be listed twice
+        let co2_id = gen_uuid();
+        let member4_id = gen_uuid();
+        let member5_id = gen_uuid();
+        let tx_co2 = TxCreateSyndicate::sign(
+            &co2_id,
+            &String::from("trikes@basis.org"),
+            &String::from("The People's Tricycle Collective"),
+            &vec![],
+            &TxCreatePrivateFounder::new(&gen_uuid(), "Wheel truer", 1.0, &vec![]),
+            &vec![
+                TxCreateSyndicateMember::new(&member4_id, &uid2, "Frame welder", 1.0, &vec![]),
+                TxCreateSyndicateMember::new(&member5_id, &uid2, "Chain greaser", 1.0, &vec![]),
+            ],
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co2]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(schema.get_company(&co2_id).is_none());
+        assert!(schema.get_company_member(&member4_id).is_none());
+        assert!(schema.get_company_member(&member5_id).is_none());
+    }
+
+    #[test]
+    fn creates_public_companies() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        let uid2 = gen_uuid();
+        let (user2_pub, user2_sec) = crypto::gen_keypair();
+        let tx_user2 = transactions::user::TxCreate::sign(
+            &uid2,
+            &user2_pub,
+            &vec![models::access::Role::User],
+            &String::from("definitely.a.bank@basis.org"),
+            &String::from("Totally Legit Banker"),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_user, tx_user2]);
+
+        // regular users can't create public companies
+        let co1_id = gen_uuid();
+        let tx_co1 = TxCreatePublic::sign(
+            &co1_id,
+            &String::from("trains@basis.org"),
+            &String::from("My Very Own Public Railway"),
+            &vec![],
+            &TxCreatePrivateFounder::new(&gen_uuid(), "Conductor", 1.0, &vec![]),
+            &util::time::now(),
+            &String::from(""),
+            &user2_pub,
+            &user2_sec
+        );
+        let co2_id = gen_uuid();
+        let founder_id = gen_uuid();
+        let ctag_id = gen_uuid();
+        let tx_co2 = TxCreatePublic::sign(
+            &co2_id,
+            &String::from("transit@basis.org"),
+            &String::from("Regional Transit Authority"),
+            &vec![TxCreatePrivateCostTag::new(&ctag_id, "rail", "")],
+            &TxCreatePrivateFounder::new(&founder_id, "Transit planner", 1.0, &vec![CostTagEntry::new(&ctag_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1, tx_co2]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(schema.get_company(&co1_id).is_none());
+        let company = schema.get_company(&co2_id).expect("missing public company");
+        assert_eq!(company.ty, CompanyType::Public);
+        let member = schema.get_company_member(&founder_id).expect("missing founder");
+        assert_eq!(member.roles, vec![CompanyRole::Admin]);
+        assert_eq!(member.default_cost_tags, vec![CostTagEntry::new(&ctag_id, 1)]);
+        let cost_tags = schema.get_cost_tags_by_company_id(&co2_id);
+        assert_eq!(cost_tags.len(), 1);
+        assert_eq!(cost_tags[0].id, ctag_id);
+    }
+
+    #[test]
+    fn approves_companies() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        let uid2 = gen_uuid();
+        let (user2_pub, user2_sec) = crypto::gen_keypair();
+        let tx_user2 = transactions::user::TxCreate::sign(
+            &uid2,
+            &user2_pub,
+            &vec![models::access::Role::User],
+            &String::from("sandra@basis.org"),
+            &String::from("Sandra"),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_user, tx_user2]);
+
+        let co1_id = gen_uuid();
+        let co2_id = gen_uuid();
+        let tx_co1 = TxCreatePrivate::sign(
+            &co1_id,
+            &String::from("jerry@basis.org"),
+            &String::from("Jerry's Widgets"),
+            &vec![],
+            &TxCreatePrivateFounder::new(&gen_uuid(), "Widgeteer", 1.0, &vec![]),
+            &util::time::now(),
+            &String::from(""),
+            &user2_pub,
+            &user2_sec
+        );
+        let tx_co2 = TxCreatePrivate::sign(
+            &co2_id,
+            &String::from("admin@basis.org"),
+            &String::from("Widget Oversight Committee"),
+            &vec![],
+            &TxCreatePrivateFounder::new(&gen_uuid(), "Overseer", 1.0, &vec![]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1, tx_co2]);
+
+        // companies created by regular users wait for approval, companies
+        // created by admins are approved out of the gate
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.get_company(&co1_id).unwrap().approval_status, ApprovalStatus::Pending);
+        assert_eq!(schema.get_company(&co2_id).unwrap().approval_status, ApprovalStatus::Approved);
+        // private companies don't get default cost tags
+        assert_eq!(schema.get_cost_tags_by_company_id(&co1_id).len(), 0);
+
+        // the founder can't approve their own company, and "pending" isn't a
+        // decision
+        let tx_approve1 = TxSetApproved::sign(
+            &co1_id,
+            &ApprovalStatus::Approved,
+            &String::from("looks good to me"),
+            &util::time::now(),
+            &user2_pub,
+            &user2_sec
+        );
+        let tx_approve2 = TxSetApproved::sign(
+            &co1_id,
+            &ApprovalStatus::Pending,
+            &String::from("thinking about it"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_approve1, tx_approve2]);
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let company = schema.get_company(&co1_id).unwrap();
+        assert_eq!(company.approval_status, ApprovalStatus::Pending);
+        assert_eq!(company.history_len, 1);
+
+        let tx_reject = TxSetApproved::sign(
+            &co1_id,
+            &ApprovalStatus::Rejected,
+            &String::from("widgets are not a real product"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_reject]);
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let company = schema.get_company(&co1_id).unwrap();
+        assert_eq!(company.approval_status, ApprovalStatus::Rejected);
+        assert_eq!(company.approval_reason, "widgets are not a real product");
+        assert!(!company.is_approved());
+        assert_eq!(company.history_len, 2);
+
+        let tx_approve = TxSetApproved::sign(
+            &co1_id,
+            &ApprovalStatus::Approved,
+            &String::from("ok, widgets are real"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_approve]);
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let company = schema.get_company(&co1_id).unwrap();
+        assert_eq!(company.approval_status, ApprovalStatus::Approved);
+        assert!(company.is_approved());
+        assert_eq!(company.history_len, 3);
+    }
+}
+
@@ -1,18 +1,54 @@
 //! Defines logic for generating and assigning costs to products
 
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeSet};
+use chrono::{DateTime, Utc};
+use validator::Validate;
+use exonum::{
+    blockchain::{ExecutionError, ExecutionResult, Transaction, TransactionContext},
+};
 use exonum_merkledb::IndexAccess;
-use costs;
-use crate::block::schema::Schema;
+use error::{BResult, BError};
+use costs::{
+    self,
+    conservation::{self, ConservationReport},
+    explain::{self, CostExplanation, CostSource, SourceType, TagCosts},
+    strategy::{self, CostingInputs},
+};
+use crate::block::{
+    schema::Schema,
+    transactions::access,
+};
 use super::CommonError;
+use util;
 use models::{
+    proto,
+    access::Permission,
     product::Product,
-    order::{ProcessStatus},
-    costs::{Costs, CostsTallyMap},
+    order::{Order, ProcessStatus},
+    costs::{Costs, CostsTally, CostsTallyMap},
+    cost_tag::Costable,
+    fixed::Fixed,
+    amortization::Amortization,
 };
 
-static USE_AGGREGATE_COSTS: bool = true;
-static MIN_FINALIZED: u64 = 2;
+#[derive(Debug, Fail)]
+#[repr(u8)]
+pub enum TransactionError {
+    #[fail(display = "Invalid ID")]
+    InvalidID = 0,
+
+    #[fail(display = "Company not found")]
+    CompanyNotFound = 1,
+}
+define_exec_error!(TransactionError);
+
+/// Turn an error from the costing code into one a transaction can return
+pub fn cost_error(err: BError) -> CommonError {
+    match err {
+        BError::CostArithmetic(_) => CommonError::CostArithmetic,
+        _ => CommonError::CostError,
+    }
+}
 
 fn get_products<T>(schema: &mut Schema<T>, prod_ids: &Vec<String>) -> HashMap<String, Product>
     where T: IndexAccess
@@ -30,6 +66,24 @@ fn get_products<T>(schema: &mut Schema<T>, prod_ids: &Vec<String>) -> HashMap<St
     products
 }
 
+/// Grab the baseline costs for each of the given products
+fn get_baseline_costs<T>(schema: &mut Schema<T>, products: &HashMap<String, Product>) -> HashMap<String, Costs>
+    where T: IndexAccess
+{
+    products.keys()
+        .filter_map(|x| schema.get_product_costs_baseline(x).map(|costs| (x.clone(), costs)))
+        .collect::<HashMap<_, _>>()
+}
+
+fn get_amortization<T>(schema: &mut Schema<T>, company_id: &str) -> HashMap<String, Amortization>
+    where T: IndexAccess
+{
+    schema.get_amortizations_by_company_id(company_id)
+        .into_iter()
+        .map(|x| (x.id.clone(), x))
+        .collect::<HashMap<_, _>>()
+}
+
 /// Calculate product costs for a company by pulling out their raw order lists
 /// and using them in a direct cost calculation.
 ///
@@ -37,9 +91,16 @@ fn get_products<T>(schema: &mut Schema<T>, prod_ids: &Vec<String>) -> HashMap<St
 pub fn calculate_product_costs_with_raw<T>(schema: &mut Schema<T>, company_id: &str) -> Result<(HashMap<String, Costs>, usize), CommonError>
     where T: IndexAccess
 {
-    // grab incoming/outgoing orders
-    let orders_incoming = schema.get_orders_incoming_recent(company_id);
-    let orders_outgoing = schema.get_orders_outgoing_recent(company_id);
+    let min_finalized = schema.get_costing_config().min_finalized;
+
+    // grab incoming/outgoing orders. canceled orders never happened as far
+    // as costing is concerned, so drop them up front.
+    let orders_incoming = schema.get_orders_incoming_recent(company_id).into_iter()
+        .filter(|x| x.process_status != ProcessStatus::Canceled)
+        .collect::<Vec<_>>();
+    let orders_outgoing = schema.get_orders_outgoing_recent(company_id).into_iter()
+        .filter(|x| x.process_status != ProcessStatus::Canceled)
+        .collect::<Vec<_>>();
 
     // grab how many finalized incoming orders we have
     let num_orders_incoming_finalized = orders_incoming.iter()
@@ -49,8 +110,8 @@ pub fn calculate_product_costs_with_raw<T>(schema: &mut Schema<T>, company_id: &
     // if we have some threshold of finalized orders, then we'll use just
     // finalized orders for our calculations, otherwise use the raw order
     // lists
-    let (orders_incoming, orders_outgoing) = if (num_orders_incoming_finalized as u64) >= MIN_FINALIZED {
-        // we have more than 10 finalized orders, so only use finalized orders
+    let (orders_incoming, orders_outgoing) = if (num_orders_incoming_finalized as u64) >= min_finalized {
+        // we have enough finalized orders, so only use finalized orders
         // in our cAlCulAtIOnS BEEp bOOP
         let incoming = orders_incoming.into_iter()
             .filter(|x| x.process_status == ProcessStatus::Finalized)
@@ -80,32 +141,58 @@ pub fn calculate_product_costs_with_raw<T>(schema: &mut Schema<T>, company_id: &
     // grab our labor records
     let labor = schema.get_labor_recent(company_id);
 
-    // grab wamortization tables
-    let amortization = HashMap::new();
+    // grab our amortization records
+    let amortization = get_amortization(schema, company_id);
+    let (window_start, window_end) = schema.get_costing_window(company_id);
 
     // calculate our costs
-    let costs = match costs::calculate_costs(&orders_incoming, &orders_outgoing, &labor, &amortization, &products) {
+    let strategy = strategy::from_config(&schema.get_costing_config().strategy);
+    let baseline = get_baseline_costs(schema, &products);
+    let costs = match costs::calculate_costs(strategy.as_ref(), &orders_incoming, &orders_outgoing, &labor, &amortization, &products, &baseline, &window_start, &window_end) {
         Ok(x) => x,
         Err(e) => {
             warn!("transactions::costs::calculate_product_costs_with_raw() -- {}", e);
-            Err(CommonError::CostError)?
+            Err(cost_error(e))?
         }
     };
     Ok((costs, orders_incoming.len()))
 
 }
 
-/// Calculate product costs for a company by pulling out the aggregate costs,
-/// which are updated as orders are rotated in/out of the costing window. These
-/// aggregates are then fed directly into the second half of the costing algo,
-/// which expects aggregate values.
-///
-/// This is the alternative to calculate_product_costs_with_raw().
-pub fn calculate_product_costs_with_aggregate<T>(schema: &mut Schema<T>, company_id: &str) -> Result<(HashMap<String, Costs>, usize), CommonError>
+/// A company's costing data as pulled out of its cost aggregates
+struct AggregateData {
+    /// The products we're costing (both active products and products that have
+    /// been ordered from us)
+    products: HashMap<String, Product>,
+    /// cost_tag_id -> costs from finalized outgoing orders
+    order_costs: HashMap<String, Costs>,
+    /// cost_tag_id -> costs from finalized labor
+    labor_costs: HashMap<String, Costs>,
+    /// cost_tag_id -> amortized costs within the costing window
+    amortization_costs: HashMap<String, Costs>,
+    /// product_id -> number of units produced
+    sum_produced: HashMap<String, Fixed>,
+    /// How many finalized incoming orders went into the aggregates
+    num_incoming_orders: u64,
+}
+
+impl AggregateData {
+    /// Sum our costs across all sources, bucketed by cost tag
+    fn sum_costs(&self) -> BResult<HashMap<String, Costs>> {
+        let mut sum_costs = HashMap::new();
+        for costs_map in &[&self.order_costs, &self.labor_costs, &self.amortization_costs] {
+            for (cost_tag_id, costs) in costs_map.iter() {
+                let entry = sum_costs.entry(cost_tag_id.clone()).or_insert(Costs::new());
+                *entry = entry.checked_add(costs)?;
+            }
+        }
+        Ok(sum_costs)
+    }
+}
+
+fn get_aggregate_data<T>(schema: &mut Schema<T>, company_id: &str) -> BResult<AggregateData>
     where T: IndexAccess
 {
-    // pull out the products (both active products and products that have
-    // been ordered from us)
     let mut product_ids = schema.products_idx_company_active(company_id).iter().collect::<Vec<_>>();
     let cost_agg = schema.costs_aggregate(company_id);
     let bucket_map_outputs = match cost_agg.get("product_outputs.v1") {
@@ -113,77 +200,467 @@ pub fn calculate_product_costs_with_aggregate<T>(schema: &mut Schema<T>, company
         None => CostsTallyMap::new(),
     };
     let output_tally = bucket_map_outputs.get("outputs");
-    let num_incoming_orders = output_tally.len();
-    if num_incoming_orders < MIN_FINALIZED {
-        return calculate_product_costs_with_raw(schema, company_id);
-    }
     let output_tally_total = output_tally.total();
     for k in output_tally_total.products().keys() {
         product_ids.push(k.clone());
     }
-    // get the products
     let products = get_products(schema, &product_ids);
 
-    // sum our costs
-    let mut sum_costs = HashMap::new();
-    let costs_tally = match cost_agg.get("costs.v1") {
-        Some(x) => x,
-        None => CostsTallyMap::new(),
+    let tally_totals = |name: &str| {
+        let tally = match cost_agg.get(name) {
+            Some(x) => x,
+            None => CostsTallyMap::new(),
+        };
+        tally.into_map()
+            .into_iter()
+            .map(|(cost_tag_id, costs)| (cost_tag_id, costs.total()))
+            .collect::<HashMap<_, _>>()
     };
-    for (cost_tag_id, costs) in costs_tally.map_ref() {
-        let entry = sum_costs.entry(cost_tag_id.clone()).or_insert(Costs::new());
-        *entry = entry.clone() + costs.total();
-    }
-    let labor_tally = match cost_agg.get("labor.v1") {
-        Some(x) => x,
-        None => CostsTallyMap::new(),
+    let order_costs = tally_totals("costs.v1");
+    let labor_costs = tally_totals("labor.v1");
+    let mut amortization_costs = HashMap::new();
+    let amortization = get_amortization(schema, company_id);
+    let (window_start, window_end) = schema.get_costing_window(company_id);
+    costs::tally_amortized_costs(&amortization, &window_start, &window_end, &mut amortization_costs)?;
+
+    Ok(AggregateData {
+        products,
+        order_costs,
+        labor_costs,
+        amortization_costs,
+        sum_produced: output_tally_total.products().clone(),
+        num_incoming_orders: output_tally.len(),
+    })
+}
+
+/// Calculate product costs for a company by pulling out the aggregate costs,
+/// which are updated as orders are rotated in/out of the costing window. These
+/// aggregates are then fed directly into the second half of the costing algo,
+/// which expects aggregate values.
+///
+/// This is the alternative to calculate_product_costs_with_raw().
+pub fn calculate_product_costs_with_aggregate<T>(schema: &mut Schema<T>, company_id: &str) -> Result<(HashMap<String, Costs>, usize), CommonError>
+    where T: IndexAccess
+{
+    let data = match get_aggregate_data(schema, company_id) {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("transactions::costs::calculate_product_costs_with_aggregate() -- {}", e);
+            Err(cost_error(e))?
+        }
     };
-    for (cost_tag_id, costs) in labor_tally.map_ref() {
-        let entry = sum_costs.entry(cost_tag_id.clone()).or_insert(Costs::new());
-        *entry = entry.clone() + costs.total();
+    if data.num_incoming_orders < schema.get_costing_config().min_finalized {
+        return calculate_product_costs_with_raw(schema, company_id);
     }
-
-    // grab our production numbers
-    let sum_produced = output_tally_total.products();
+    let sum_costs = match data.sum_costs() {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("transactions::costs::calculate_product_costs_with_aggregate() -- {}", e);
+            Err(cost_error(e))?
+        }
+    };
 
     // calculate our costs
-    let costs = match costs::calculate_costs_with_aggregates(&products, &sum_costs, sum_produced) {
+    let strategy = strategy::from_config(&schema.get_costing_config().strategy);
+    let baseline = get_baseline_costs(schema, &data.products);
+    let inputs = CostingInputs {
+        products: &data.products,
+        sum_costs: &sum_costs,
+        sum_produced: &data.sum_produced,
+        baseline: &baseline,
+    };
+    let costs = match strategy.calculate(&inputs) {
         Ok(x) => x,
         Err(e) => {
             warn!("transactions::costs::calculate_product_costs_with_aggregate() -- {}", e);
-            Err(CommonError::CostError)?
+            Err(cost_error(e))?
+        }
+    };
+    Ok((costs, data.num_incoming_orders as usize))
+}
+
+/// Explain where a product's costs come from, using the same aggregate data
+/// that calculate_product_costs_with_aggregate() uses. Each cost tag's totals
+/// are broken out into orders, labor, and amortization, along with the
+/// individual records (still within the costing window) behind them.
+///
+/// Note that this always explains the tag-weighted algorithm, regardless of
+/// which strategy the network is configured to use. Returns None if the
+/// product doesn't exist.
+pub fn explain_product_costs<T>(schema: &mut Schema<T>, product_id: &str) -> Result<Option<CostExplanation>, CommonError>
+    where T: IndexAccess
+{
+    let company_id = match schema.get_product(product_id) {
+        Some(x) => x.company_id,
+        None => return Ok(None),
+    };
+    let data = match get_aggregate_data(schema, &company_id) {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("transactions::costs::explain_product_costs() -- {}", e);
+            Err(cost_error(e))?
+        }
+    };
+
+    let mut tag_costs: HashMap<String, TagCosts> = HashMap::new();
+    for (cost_tag_id, costs) in &data.order_costs {
+        tag_costs.entry(cost_tag_id.clone()).or_insert(Default::default()).orders = costs.clone();
+    }
+    for (cost_tag_id, costs) in &data.labor_costs {
+        tag_costs.entry(cost_tag_id.clone()).or_insert(Default::default()).labor = costs.clone();
+    }
+    for (cost_tag_id, costs) in &data.amortization_costs {
+        tag_costs.entry(cost_tag_id.clone()).or_insert(Default::default()).amortization = costs.clone();
+    }
+
+    // now find the records behind the totals. these are the same records the
+    // aggregates are built from.
+    let mut add_sources = |source_type: SourceType, id: &str, tagged_costs: HashMap<String, Costs>| {
+        for (cost_tag_id, costs) in tagged_costs {
+            if costs.is_zero() {
+                continue;
+            }
+            tag_costs.entry(cost_tag_id).or_insert(Default::default())
+                .sources.push(CostSource::new(source_type.clone(), id, &costs));
         }
     };
-    Ok((costs, num_incoming_orders as usize))
+    let mut find_sources = || -> BResult<()> {
+        for order in schema.get_orders_outgoing_recent(&company_id) {
+            if order.process_status != ProcessStatus::Finalized {
+                continue;
+            }
+            add_sources(SourceType::Order, &order.id, order.try_get_tagged_costs()?);
+        }
+        for labor in schema.get_labor_recent(&company_id) {
+            add_sources(SourceType::Labor, &labor.id, labor.try_get_tagged_costs()?);
+        }
+        let (window_start, window_end) = schema.get_costing_window(&company_id);
+        for amortization in schema.get_amortizations_by_company_id(&company_id) {
+            if amortization.is_deleted() {
+                continue;
+            }
+            let mut tagged_costs = HashMap::new();
+            amortization.tally_tagged_costs_within(&window_start, &window_end, &mut tagged_costs)?;
+            add_sources(SourceType::Amortization, &amortization.id, tagged_costs);
+        }
+        Ok(())
+    };
+    if let Err(e) = find_sources() {
+        warn!("transactions::costs::explain_product_costs() -- {}", e);
+        Err(cost_error(e))?
+    }
+
+    match explain::explain_costs_with_aggregates(product_id, &data.products, &tag_costs, &data.sum_produced) {
+        Ok(x) => Ok(x),
+        Err(e) => {
+            warn!("transactions::costs::explain_product_costs() -- {}", e);
+            Err(cost_error(e))?
+        }
+    }
+}
+
+/// Check that a company's aggregate costs are fully accounted for by the
+/// product costs the configured strategy calculates from them. Reports any
+/// costs that didn't land in a product, or that landed more than once, along
+/// with how much of a difference we'd expect from rounding alone.
+pub fn check_company_costs<T>(schema: &mut Schema<T>, company_id: &str) -> Result<(ConservationReport, Fixed), CommonError>
+    where T: IndexAccess
+{
+    let data = match get_aggregate_data(schema, company_id) {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("transactions::costs::check_company_costs() -- {}", e);
+            Err(cost_error(e))?
+        }
+    };
+    let sum_costs = match data.sum_costs() {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("transactions::costs::check_company_costs() -- {}", e);
+            Err(cost_error(e))?
+        }
+    };
+    let strategy = strategy::from_config(&schema.get_costing_config().strategy);
+    let baseline = get_baseline_costs(schema, &data.products);
+    let inputs = CostingInputs {
+        products: &data.products,
+        sum_costs: &sum_costs,
+        sum_produced: &data.sum_produced,
+        baseline: &baseline,
+    };
+    let costs = match strategy.calculate(&inputs) {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("transactions::costs::check_company_costs() -- {}", e);
+            Err(cost_error(e))?
+        }
+    };
+    let report = conservation::check_conservation(&sum_costs, &data.sum_produced, &costs);
+    let tolerance = conservation::rounding_tolerance(&data.products, &sum_costs, &data.sum_produced);
+    Ok((report, tolerance))
 }
 
 pub fn calculate_product_costs<T>(schema: &mut Schema<T>, company_id: &str) -> Result<(), CommonError>
     where T: IndexAccess
 {
-    let (product_costs, num_incoming_orders) = if USE_AGGREGATE_COSTS {
+    let (product_costs, num_incoming_orders) = if schema.get_costing_config().use_aggregate {
         calculate_product_costs_with_aggregate(schema, company_id)?
     } else {
         calculate_product_costs_with_raw(schema, company_id)?
     };
+    // work everything out before writing anything, so if something fails
+    // partway through we don't leave the company half-costed
     let empty_costs = Costs::new();
+    let mut final_costs = HashMap::new();
     for (product_id, costs) in product_costs.iter() {
         // if we have no incoming orders, effectively set costs to 0
-        let costs = if num_incoming_orders > 0 { costs } else { &empty_costs };
+        let costs = if num_incoming_orders > 0 {
+            // labor costs are keyed by occupation, so any occupations that
+            // have been merged get folded into the ones they were merged into
+            costs.rekey_labor(|k| schema.occupation_normalize(k)).map_err(cost_error)?
+        } else {
+            empty_costs.clone()
+        };
+        final_costs.insert(product_id.clone(), costs);
+    }
+    let changed = final_costs.iter()
+        .filter(|(product_id, costs)| schema.get_product_costs(product_id).as_ref() != Some(*costs))
+        .map(|(product_id, costs)| (product_id.clone(), costs.clone()))
+        .collect::<HashMap<_, _>>();
+    let buyer_orders = get_buyer_orders(schema, company_id, &changed);
+    let (repriced, buyer_aggregates) = schema.orders_reprice_calculate(&buyer_orders, &changed).map_err(cost_error)?;
+
+    for (product_id, costs) in &final_costs {
+        schema.product_costs_baseline_update(product_id);
         schema.product_costs_attach(product_id, costs);
     }
+    // the companies buying from us now carry our new costs in their orders,
+    // so queue them up to have their own costs recalculated in an upcoming
+    // block
+    schema.orders_reprice_set(&repriced, &buyer_aggregates);
+    // we're now up to date, so if we were queued for recalculation we can
+    // come off the queue
+    schema.costs_dirty_remove(company_id);
+    for order in &repriced {
+        schema.costs_dirty_mark(&order.company_id_from);
+    }
+    Ok(())
+}
+
+/// Grab the orders other companies have placed with us within the costing
+/// window for any of the given (changed) products. Canceled orders never
+/// count towards costs, so they're left out.
+fn get_buyer_orders<T>(schema: &mut Schema<T>, company_id: &str, changed_products: &HashMap<String, Costs>) -> Vec<Order>
+    where T: IndexAccess
+{
+    if changed_products.len() == 0 {
+        return Vec::new();
+    }
+    schema.get_orders_incoming_recent(company_id).into_iter()
+        .filter(|x| x.company_id_from != company_id)
+        .filter(|x| x.process_status != ProcessStatus::Canceled)
+        .filter(|x| x.products.iter().any(|entry| changed_products.contains_key(&entry.product_id)))
+        .collect::<Vec<_>>()
+}
+
+/// Recalculate the costs of the companies at the front of the dirty queue, up
+/// to the configured limit. Companies that get dirtied while we're doing this
+/// go to the back of the queue and wait for a later block, which keeps the
+/// work done per block bounded (and stops cycles in the supply chain from
+/// running forever).
+///
+/// This runs before each block is committed.
+pub fn process_dirty_companies<T>(schema: &mut Schema<T>)
+    where T: IndexAccess
+{
+    let limit = schema.get_costing_config().propagation_limit;
+    let company_ids = schema.costs_dirty_queue()
+        .values()
+        .take(limit as usize)
+        .collect::<Vec<_>>();
+    for company_id in &company_ids {
+        if let Err(e) = calculate_product_costs(schema, company_id) {
+            // calculate_product_costs() doesn't write anything unless it
+            // succeeds, so the company is still out of date. send it to the
+            // back of the queue so it doesn't hold up everyone else.
+            warn!("transactions::costs::process_dirty_companies() -- problem costing company {}, requeuing: {}", company_id, e);
+            schema.costs_dirty_remove(company_id);
+            schema.costs_dirty_mark(company_id);
+        }
+    }
+}
+
+/// A spot where a company's stored cost aggregates disagree with what the raw
+/// records say they should be
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AggregateDiscrepancy {
+    pub bucket: String,
+    pub key: String,
+    pub stored: CostsTally,
+    pub calculated: CostsTally,
+}
+
+/// Compare two tallies, treating missing costs and zeroed-out costs the same
+fn tallies_match(tally1: &CostsTally, tally2: &CostsTally) -> bool {
+    if tally1.len() != tally2.len() {
+        return false;
+    }
+    let diff = tally1.total() - tally2.total();
+    diff.labor().values()
+        .chain(diff.products().values())
+        .all(|x| x.is_zero())
+}
+
+/// Recalculate a company's cost aggregates from the raw order/labor records
+/// and report everywhere the stored aggregates have drifted. Results are
+/// sorted by bucket, then key.
+pub fn verify_aggregates<T>(schema: &mut Schema<T>, company_id: &str) -> Result<Vec<AggregateDiscrepancy>, CommonError>
+    where T: IndexAccess
+{
+    let calculated = schema.costs_aggregate_calculate(company_id).map_err(cost_error)?;
+    let cost_agg = schema.costs_aggregate(company_id);
+    let buckets = calculated.keys()
+        .cloned()
+        .chain(cost_agg.keys())
+        .collect::<BTreeSet<_>>();
+    let mut discrepancies = Vec::new();
+    for bucket in &buckets {
+        let stored_map = cost_agg.get(bucket).unwrap_or_default();
+        let calculated_map = calculated.get(bucket).cloned().unwrap_or_default();
+        let keys = stored_map.map_ref().keys()
+            .chain(calculated_map.map_ref().keys())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for key in &keys {
+            let stored = stored_map.get(key);
+            let calculated = calculated_map.get(key);
+            if !tallies_match(&stored, &calculated) {
+                discrepancies.push(AggregateDiscrepancy {
+                    bucket: bucket.clone(),
+                    key: key.clone(),
+                    stored,
+                    calculated,
+                });
+            }
+        }
+    }
+    Ok(discrepancies)
+}
+
+/// Replace a company's cost aggregates with ones recalculated from the raw
+/// records, then recalculate its product costs. Returns the number of
+/// discrepancies that were fixed.
+pub fn repair_aggregates<T>(schema: &mut Schema<T>, company_id: &str) -> Result<usize, CommonError>
+    where T: IndexAccess
+{
+    let discrepancies = verify_aggregates(schema, company_id)?;
+    if discrepancies.len() > 0 {
+        warn!("transactions::costs::repair_aggregates() -- company {} has {} aggregate discrepancies: {:?}", company_id, discrepancies.len(), discrepancies);
+    }
+    let aggregates = schema.costs_aggregate_calculate(company_id).map_err(cost_error)?;
+    schema.costs_aggregate_set(company_id, &aggregates);
+    calculate_product_costs(schema, company_id)?;
+    Ok(discrepancies.len())
+}
+
+/// Check if the costing config in the network's configuration differs from
+/// the one we're running under, and if so switch over to it. Because the new
+/// config might change the costing window, this re-rotates every company's
+/// rolling indexes, rebuilds their cost aggregates, and recalculates all
+/// product costs.
+///
+/// Companies are rebuilt one at a time. Every company's rebuild is worked out
+/// before any of them are written, so if one fails we stay on the old config
+/// entirely (and try again next block) instead of ending up half-switched.
+///
+/// This runs before each block is committed, so every node switches over at
+/// the same height.
+pub fn apply_costing_config<T>(schema: &mut Schema<T>) -> Result<(), CommonError>
+    where T: IndexAccess
+{
+    let config = match schema.get_costing_config_actual() {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+    if config == schema.get_costing_config() {
+        return Ok(());
+    }
+    if !config.is_valid() {
+        warn!("transactions::costs::apply_costing_config() -- ignoring invalid costing config: {:?}", config);
+        return Ok(());
+    }
+    info!("transactions::costs::apply_costing_config() -- applying costing config: {:?}", config);
+    let company_ids = schema.companies().values()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+    for company_id in &company_ids {
+        if let Err(e) = schema.costs_rebuild_rolling_calculate(company_id, config.window_secs) {
+            warn!("transactions::costs::apply_costing_config() -- problem rebuilding company {}: {}", company_id, e);
+            Err(cost_error(e))?;
+        }
+    }
+    for company_id in &company_ids {
+        let rebuild = schema.costs_rebuild_rolling_calculate(company_id, config.window_secs).map_err(cost_error)?;
+        schema.costs_rebuild_rolling_set(company_id, &rebuild);
+    }
+    schema.costing_config_set(&config);
+    for company_id in &company_ids {
+        if let Err(e) = calculate_product_costs(schema, company_id) {
+            // the company's aggregates are in order, it's just the product
+            // costs that are out of date. queue it up for another try.
+            warn!("transactions::costs::apply_costing_config() -- problem costing company {}: {}", company_id, e);
+            schema.costs_dirty_mark(company_id);
+        }
+    }
     Ok(())
 }
 
+deftransaction! {
+    #[exonum(pb = "proto::costs::TxRepairAggregates")]
+    pub struct TxRepairAggregates {
+        #[validate(custom = "super::validate_uuid")]
+        pub company_id: String,
+        #[validate(custom = "super::validate_date")]
+        pub created: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxRepairAggregates {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::CostsRepair)?;
+
+        if schema.get_company(&self.company_id).is_none() {
+            Err(TransactionError::CompanyNotFound)?;
+        }
+        if !util::time::is_current(&self.created) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        repair_aggregates(&mut schema, &self.company_id)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
-    use chrono::Duration;
+    use chrono::{DateTime, Utc, Duration};
+    use exonum_testkit::TestKit;
     use models::{
         self,
         cost_tag::CostTagEntry,
+        costing::{CostingConfig, Strategy},
         company,
+        fixed::Fixed,
     };
+    use costs::explain::SourceType;
+    use exonum::crypto::Hash;
     use util;
-    use crate::block::{transactions, schema::Schema};
+    use crate::block::{transactions, schema::Schema, ServiceConfig, SERVICE_NAME};
     use crate::test::{self, gen_uuid};
 
     #[test]
@@ -209,6 +686,7 @@ pub mod tests {
             &vec![company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
             &company::TxCreatePrivateFounder::new(&co1_founder_id, "Widget builder", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
             &util::time::now(),
+            &String::from(""),
             &root_pub,
             &root_sec
         );
@@ -219,6 +697,7 @@ pub mod tests {
             &vec![company::TxCreatePrivateCostTag::new(&ctag2_op_id, "operating", "")],
             &company::TxCreatePrivateFounder::new(&co2_founder_id, "Widget distributor", 1.0, &vec![CostTagEntry::new(&ctag2_op_id, 1)]),
             &util::time::now(),
+            &String::from(""),
             &root_pub,
             &root_sec
         );
@@ -229,6 +708,7 @@ pub mod tests {
             &vec![company::TxCreatePrivateCostTag::new(&ctag3_op_id, "operating", "")],
             &company::TxCreatePrivateFounder::new(&co3_founder_id, "Widget distributor", 1.0, &vec![CostTagEntry::new(&ctag3_op_id, 1)]),
             &util::time::now(),
+            &String::from(""),
             &root_pub,
             &root_sec
         );
@@ -251,24 +731,24 @@ pub mod tests {
         );
         testkit.create_block_with_transactions(txvec![tx_prod]);
 
+        let now = util::time::now();
+        let then = now - Duration::hours(8);
         let labor_id = gen_uuid();
         let tx_labor1 = transactions::labor::TxCreate::sign(
             &labor_id,
             &co1_id,
             &uid,
             &vec![],
-            &util::time::now(),
+            &then,
             &root_pub,
             &root_sec
         );
         testkit.create_block_with_transactions(txvec![tx_labor1]);
 
-        let now = util::time::now();
-        let then = now - Duration::hours(8);
         let tx_labor2 = transactions::labor::TxUpdate::sign(
             &labor_id,
             &vec![],
-            &then,
+            &util::time::default_time(),
             &now,
             &now,
             &root_pub,
@@ -306,5 +786,454 @@ pub mod tests {
         let costs = costs.unwrap();
         assert_eq!(costs.labor().get("Widget builder").unwrap().clone(), 8.0 / (10000.0 + 20334.0));
     }
+
+    #[test]
+    fn canceled_orders_drop_out_of_costs() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        testkit.create_block_with_transactions(txvec![tx_user]);
+
+        let co1_id = gen_uuid();
+        let co2_id = gen_uuid();
+        let co3_id = gen_uuid();
+        let ctag1_op_id = gen_uuid();
+        let ctag2_op_id = gen_uuid();
+        let ctag3_op_id = gen_uuid();
+        let tx_co1 = transactions::company::TxCreatePrivate::sign(
+            &co1_id,
+            &String::from("company1@basis.org"),
+            &String::from("Widget Builders Inc"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget builder", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        let tx_co2 = transactions::company::TxCreatePrivate::sign(
+            &co2_id,
+            &String::from("company2@basis.org"),
+            &String::from("Widget Painters Inc"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag2_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget painter", 1.0, &vec![CostTagEntry::new(&ctag2_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        let tx_co3 = transactions::company::TxCreatePrivate::sign(
+            &co3_id,
+            &String::from("company3@basis.org"),
+            &String::from("Widget Distributors Inc"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag3_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget distributor", 1.0, &vec![CostTagEntry::new(&ctag3_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1, tx_co2, tx_co3]);
+
+        let prod1_id = gen_uuid();
+        let prod2_id = gen_uuid();
+        let tx_prod1 = transactions::product::TxCreate::sign(
+            &prod1_id,
+            &co1_id,
+            &String::from("Red widget"),
+            &models::product::Unit::Millimeter,
+            &3.0,
+            &models::product::Dimensions::new(100.0, 100.0, 100.0),
+            &vec![CostTagEntry::new(&ctag1_op_id, 10)],
+            &true,
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_prod2 = transactions::product::TxCreate::sign(
+            &prod2_id,
+            &co2_id,
+            &String::from("Painted widget"),
+            &models::product::Unit::Millimeter,
+            &3.0,
+            &models::product::Dimensions::new(100.0, 100.0, 100.0),
+            &vec![CostTagEntry::new(&ctag2_op_id, 10)],
+            &true,
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_prod1, tx_prod2]);
+
+        let labor_id = gen_uuid();
+        let tx_labor = transactions::labor::TxCreate::sign(
+            &labor_id,
+            &co1_id,
+            &uid,
+            &vec![],
+            &(util::time::now() - Duration::hours(8)),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_labor]);
+        let tx_clock_out = transactions::labor::TxClockOut::sign(
+            &labor_id,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_clock_out]);
+
+        // give the red widget some costs
+        let ord1_id = gen_uuid();
+        let tx_ord1 = transactions::order::TxCreate::sign(
+            &ord1_id,
+            &co3_id,
+            &co1_id,
+            &vec![CostTagEntry::new(&ctag3_op_id, 1)],
+            &vec![models::order::ProductEntry::new(&prod1_id, 100.0, &models::costs::Costs::new(), false)],
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_ord1_fulfill = transactions::order::TxUpdateFulfillment::sign(&ord1_id, &0, &Fixed::from(100.0), &Fixed::zero(), &util::time::now(), &root_pub, &root_sec);
+        testkit.create_block_with_transactions(txvec![tx_ord1, tx_ord1_fulfill]);
+
+        // company 2 buys red widgets, paints them, and sells them on
+        let ord2_id = gen_uuid();
+        let tx_ord2 = transactions::order::TxCreate::sign(
+            &ord2_id,
+            &co2_id,
+            &co1_id,
+            &vec![CostTagEntry::new(&ctag2_op_id, 1)],
+            &vec![models::order::ProductEntry::new(&prod1_id, 50.0, &models::costs::Costs::new(), false)],
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_ord2_fulfill = transactions::order::TxUpdateFulfillment::sign(&ord2_id, &0, &Fixed::from(50.0), &Fixed::zero(), &util::time::now(), &root_pub, &root_sec);
+        let ord3_id = gen_uuid();
+        let tx_ord3 = transactions::order::TxCreate::sign(
+            &ord3_id,
+            &co3_id,
+            &co2_id,
+            &vec![CostTagEntry::new(&ctag3_op_id, 1)],
+            &vec![models::order::ProductEntry::new(&prod2_id, 10.0, &models::costs::Costs::new(), false)],
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_ord3_fulfill = transactions::order::TxUpdateFulfillment::sign(&ord3_id, &0, &Fixed::from(10.0), &Fixed::zero(), &util::time::now(), &root_pub, &root_sec);
+        testkit.create_block_with_transactions(txvec![tx_ord2, tx_ord2_fulfill, tx_ord3, tx_ord3_fulfill]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let (_, costs, _) = schema.get_product_with_costs_tagged(&prod2_id);
+        let labor_before = costs.unwrap().labor().get("Widget builder").cloned().unwrap_or(Fixed::zero());
+        assert!(labor_before > Fixed::zero());
+
+        let tx_cancel = transactions::order::TxCancel::sign(
+            &ord2_id,
+            &String::from("changed our minds"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_cancel]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.get_order(&ord2_id).unwrap().process_status, models::order::ProcessStatus::Canceled);
+        let (_, costs, _) = schema.get_product_with_costs_tagged(&prod2_id);
+        let labor_after = costs.unwrap().labor().get("Widget builder").cloned().unwrap_or(Fixed::zero());
+        assert!(labor_after < labor_before);
+    }
+
+    #[test]
+    fn costing_window_is_configurable() {
+        let mut testkit = test::init_testkit();
+        // two orders, 200 days apart
+        let ord1_date: DateTime<Utc> = "2018-01-01T04:00:00Z".parse().unwrap();
+        let ord2_date = ord1_date + Duration::days(200);
+        let test::WidgetFixture { co1_id, co2_id, prod_id, .. } = test::setup_widget_orders(&mut testkit, &vec![(10.0, ord1_date), (20.0, ord2_date)]);
+
+        let num_outputs = |testkit: &TestKit| {
+            let snapshot = testkit.snapshot();
+            let schema = Schema::new(&snapshot);
+            assert_eq!(schema.orders_idx_company_id_to_rolling(&co1_id).keys().count(), schema.orders_idx_company_id_from_rolling(&co2_id).keys().count());
+            schema.costs_aggregate(&co1_id).get("product_outputs.v1").unwrap().get("outputs").total().get(&prod_id)
+        };
+        let set_config = |testkit: &mut TestKit, config: CostingConfig| {
+            let mut proposal = testkit.configuration_change_proposal();
+            proposal.set_actual_from(testkit.height().next());
+            proposal.set_service_config(SERVICE_NAME, ServiceConfig { costing: config });
+            testkit.commit_configuration_change(proposal);
+            testkit.create_block();
+        };
+
+        // the default window is a year, so both orders count
+        assert_eq!(Schema::new(&testkit.snapshot()).get_costing_config(), CostingConfig::default());
+        assert_eq!(num_outputs(&testkit), 30.0);
+
+        // shrink the window so the first order gets rotated out
+        set_config(&mut testkit, CostingConfig::new(3600 * 24 * 100, 2, true, &Strategy::TagWeighted, 20));
+        assert_eq!(Schema::new(&testkit.snapshot()).get_costing_config().window_secs, 3600 * 24 * 100);
+        assert_eq!(num_outputs(&testkit), 20.0);
+
+        // and grow it again, which should bring the first order back
+        set_config(&mut testkit, CostingConfig::new(3600 * 24 * 300, 2, true, &Strategy::TagWeighted, 20));
+        assert_eq!(num_outputs(&testkit), 30.0);
+
+        // invalid configs are ignored
+        set_config(&mut testkit, CostingConfig::new(0, 2, true, &Strategy::TagWeighted, 20));
+        assert_eq!(Schema::new(&testkit.snapshot()).get_costing_config().window_secs, 3600 * 24 * 300);
+        assert_eq!(num_outputs(&testkit), 30.0);
+    }
+
+    #[test]
+    fn explains_product_costs() {
+        let mut testkit = test::init_testkit();
+        let now = util::time::now();
+        let test::WidgetFixture { root_pub, root_sec, co1_id, ctag1_op_id, prod_id, .. } = test::setup_widget_orders(&mut testkit, &vec![(100.0, now)]);
+
+        let amort_id = gen_uuid();
+        let tx_amort = transactions::amortization::TxCreate::sign(
+            &amort_id,
+            &co1_id,
+            &String::from("Widget press"),
+            &models::costs::Costs::new_with_labor("Widget builder", 4000.0),
+            &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+            &(now - Duration::days(730)),
+            &(now + Duration::days(730)),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_amort]);
+
+        let snapshot = testkit.snapshot();
+        let mut schema = Schema::new(&snapshot);
+        let explanation = super::explain_product_costs(&mut schema, &prod_id).unwrap().unwrap();
+        assert_eq!(explanation.units_produced, 100.0);
+        assert_eq!(explanation.tags.len(), 1);
+        let tag = &explanation.tags[0];
+        assert_eq!(tag.cost_tag_id, ctag1_op_id);
+        assert!(!tag.implicit);
+        assert_eq!(tag.costs.amortization.get_labor("Widget builder"), 1000.0);
+        assert_eq!(tag.costs.sources.len(), 1);
+        assert_eq!(tag.costs.sources[0].source_type, SourceType::Amortization);
+        assert_eq!(tag.costs.sources[0].id, amort_id);
+        assert_eq!(explanation.costs, schema.get_product_costs(&prod_id).unwrap());
+        assert!(super::explain_product_costs(&mut schema, &gen_uuid()).unwrap().is_none());
+    }
+
+    #[test]
+    fn records_product_costs_history() {
+        let mut testkit = test::init_testkit();
+        let now = util::time::now();
+        let test::WidgetFixture { root_pub, root_sec, co1_id, ctag1_op_id, prod_id, .. } = test::setup_widget_orders(&mut testkit, &vec![(100.0, now)]);
+
+        let amort_id = gen_uuid();
+        let tx_amort = transactions::amortization::TxCreate::sign(
+            &amort_id,
+            &co1_id,
+            &String::from("Widget press"),
+            &models::costs::Costs::new_with_labor("Widget builder", 4000.0),
+            &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+            &(now - Duration::days(730)),
+            &(now + Duration::days(730)),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_amort]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let history = schema.get_product_costs_history(&prod_id, None, None);
+        // once when the widget was first costed (no costs yet), once when the
+        // amortization came in
+        assert_eq!(history.len(), 2);
+        assert!(history[0].costs.is_zero());
+        assert!(history[0].height < history[1].height);
+        assert!(history[0].timestamp <= history[1].timestamp);
+        assert_eq!(history[1].costs, schema.get_product_costs(&prod_id).unwrap());
+        assert_eq!(history[1].costs.get_labor("Widget builder"), 10.0);
+        let future = util::time::now() + Duration::days(1);
+        assert_eq!(schema.get_product_costs_history(&prod_id, Some(&future), None).len(), 0);
+        assert_eq!(schema.get_product_costs_history(&prod_id, None, Some(&future)).len(), 2);
+        assert_eq!(schema.get_product_costs_history(&prod_id, Some(&history[1].timestamp), None).len(), 1);
+
+        let tx_amort_del = transactions::amortization::TxDelete::sign(
+            &amort_id,
+            &String::from("sold it"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_amort_del]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let history = schema.get_product_costs_history(&prod_id, None, None);
+        assert_eq!(history.len(), 3);
+        assert!(history[2].costs.is_zero());
+        assert!(history[1].height < history[2].height);
+    }
+
+    #[test]
+    fn propagates_cost_changes_to_buyers() {
+        let mut testkit = test::init_testkit();
+        let now = util::time::now();
+        let test::WidgetFixture { root_pub, root_sec, co1_id, co2_id, ctag1_op_id, ctag2_op_id, prod_id: prod1_id, ord_ids, .. } = test::setup_widget_orders(&mut testkit, &vec![(100.0, now)]);
+        let ord1_id = ord_ids[0].clone();
+
+        // co2 boxes up the widgets it buys and sells them on to co3
+        let co3_id = gen_uuid();
+        let ctag3_op_id = gen_uuid();
+        let tx_co3 = transactions::company::TxCreatePrivate::sign(
+            &co3_id,
+            &String::from("company3@basis.org"),
+            &String::from("Widget Retailers Inc"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag3_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget seller", 1.0, &vec![CostTagEntry::new(&ctag3_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        let prod2_id = gen_uuid();
+        let tx_prod2 = transactions::product::TxCreate::sign(
+            &prod2_id,
+            &co2_id,
+            &String::from("Boxed red widgets"),
+            &models::product::Unit::Millimeter,
+            &30.0,
+            &models::product::Dimensions::new(500.0, 500.0, 500.0),
+            &vec![CostTagEntry::new(&ctag2_op_id, 1)],
+            &true,
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co3, tx_prod2]);
+        // products can't be ordered until they've been costed
+        testkit.create_block_with_transactions(txvec![
+            super::TxRepairAggregates::sign(&co2_id, &util::time::now(), &root_pub, &root_sec),
+        ]);
+
+        let ord2_id = gen_uuid();
+        let tx_ord2 = transactions::order::TxCreate::sign(
+            &ord2_id,
+            &co3_id,
+            &co2_id,
+            &vec![CostTagEntry::new(&ctag3_op_id, 1)],
+            &vec![models::order::ProductEntry::new(&prod2_id, 10.0, &models::costs::Costs::new(), false)],
+            &now,
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_ord2]);
+        testkit.create_block_with_transactions(test::tx_finalize_order(&ord2_id, &now, &root_pub, &root_sec));
+
+        // nobody's waiting on anything yet
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.get_costs_dirty().len(), 0);
+        assert!(schema.get_product_costs(&prod2_id).unwrap().is_zero());
+
+        // work on a fork directly so we can see the queue before it's processed
+        let fork = testkit.blockchain_mut().fork();
+        let mut schema = Schema::new(&fork);
+        let now = util::time::now();
+        schema.amortizations_create(&gen_uuid(), &co1_id, "Widget press", &models::costs::Costs::new_with_labor("Widget builder", 4000.0), &vec![CostTagEntry::new(&ctag1_op_id, 1)], &(now - Duration::days(730)), &(now + Duration::days(730)), "{}", &now, &Hash::default());
+        super::calculate_product_costs(&mut schema, &co1_id).unwrap();
+        assert_eq!(schema.get_product_costs(&prod1_id).unwrap().get_labor("Widget builder"), 10.0);
+        // co2's order now carries the new widget costs, and its aggregates
+        // moved along with it
+        assert_eq!(schema.get_order(&ord1_id).unwrap().products[0].costs.get_labor("Widget builder"), 10.0);
+        assert_eq!(super::verify_aggregates(&mut schema, &co2_id).unwrap().len(), 0);
+        // co2 ordered widgets from us, so it's queued
+        assert_eq!(schema.get_costs_dirty(), vec![co2_id.clone()]);
+        // marking twice doesn't queue twice
+        schema.costs_dirty_mark(&co2_id);
+        assert_eq!(schema.get_costs_dirty().len(), 1);
+
+        // 100 widgets at 10 each, boxed up into 10 boxes
+        super::process_dirty_companies(&mut schema);
+        assert_eq!(schema.get_product_costs(&prod2_id).unwrap().get_labor("Widget builder"), 100.0);
+        // and now co3 gets its turn
+        assert_eq!(schema.get_costs_dirty(), vec![co3_id.clone()]);
+        assert_eq!(schema.get_order(&ord2_id).unwrap().products[0].costs.get_labor("Widget builder"), 100.0);
+        super::process_dirty_companies(&mut schema);
+        assert_eq!(schema.get_costs_dirty().len(), 0);
+
+        // recalculating our own costs again changes nothing, so nobody gets
+        // queued
+        super::calculate_product_costs(&mut schema, &co1_id).unwrap();
+        assert_eq!(schema.get_costs_dirty().len(), 0);
+    }
+
+    #[test]
+    fn verifies_and_repairs_aggregates() {
+        let mut testkit = test::init_testkit();
+        let test::WidgetFixture { root_pub, root_sec, co1_id, co2_id, ctag2_op_id, .. } = test::setup_widget_orders(&mut testkit, &vec![(100.0, util::time::now())]);
+
+        // the incremental updates should agree with a full rebuild
+        for company_id in &[&co1_id, &co2_id] {
+            let snapshot = testkit.snapshot();
+            let mut schema = Schema::new(&snapshot);
+            assert_eq!(super::verify_aggregates(&mut schema, company_id).unwrap().len(), 0);
+        }
+
+        // now mess with co2's aggregates behind its back
+        let fork = testkit.blockchain_mut().fork();
+        {
+            let schema = Schema::new(&fork);
+            let mut cost_agg = schema.costs_aggregate(&co2_id);
+            let mut bucket = cost_agg.get("costs.v1").unwrap();
+            bucket.add(&ctag2_op_id, &models::costs::Costs::new_with_labor("Widget distributor", 42.0));
+            bucket.add("ghost-tag", &models::costs::Costs::new_with_labor("Widget distributor", 1.0));
+            cost_agg.put(&String::from("costs.v1"), bucket);
+        }
+        testkit.blockchain_mut().merge(fork.into_patch()).unwrap();
+
+        let snapshot = testkit.snapshot();
+        let mut schema = Schema::new(&snapshot);
+        let discrepancies = super::verify_aggregates(&mut schema, &co2_id).unwrap();
+        assert_eq!(discrepancies.len(), 2);
+        assert_eq!(discrepancies.iter().map(|x| x.bucket.as_str()).collect::<Vec<_>>(), vec!["costs.v1", "costs.v1"]);
+        let mut keys = vec![ctag2_op_id.as_str(), "ghost-tag"];
+        keys.sort();
+        assert_eq!(discrepancies.iter().map(|x| x.key.as_str()).collect::<Vec<_>>(), keys);
+        let drifted = discrepancies.iter().find(|x| x.key == ctag2_op_id).unwrap();
+        assert_eq!(drifted.stored.len(), 2);
+        assert_eq!(drifted.calculated.len(), 1);
+        assert_eq!(drifted.stored.total().get_labor("Widget distributor"), 42.0);
+        // co1 is left alone
+        assert_eq!(super::verify_aggregates(&mut schema, &co1_id).unwrap().len(), 0);
+
+        // no company, no repair
+        testkit.create_block_with_transactions(txvec![
+            super::TxRepairAggregates::sign(&gen_uuid(), &util::time::now(), &root_pub, &root_sec),
+        ]);
+        let snapshot = testkit.snapshot();
+        let mut schema = Schema::new(&snapshot);
+        assert_eq!(super::verify_aggregates(&mut schema, &co2_id).unwrap().len(), 2);
+
+        testkit.create_block_with_transactions(txvec![
+            super::TxRepairAggregates::sign(&co2_id, &util::time::now(), &root_pub, &root_sec),
+        ]);
+        let snapshot = testkit.snapshot();
+        let mut schema = Schema::new(&snapshot);
+        assert_eq!(super::verify_aggregates(&mut schema, &co2_id).unwrap().len(), 0);
+        let cost_agg = schema.costs_aggregate(&co2_id).get("costs.v1").unwrap();
+        assert!(cost_agg.map_ref().get("ghost-tag").is_none());
+    }
 }
 
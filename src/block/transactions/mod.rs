@@ -36,6 +36,9 @@ pub enum CommonError {
     #[fail(display = "Cannot calculate costs")]
     CostError = 3,
 
+    #[fail(display = "Invalid cost arithmetic (negative costs, overflow, or division by zero)")]
+    CostArithmetic = 4,
+
     #[fail(display = "ID already exists")]
     IDExists = 5,
 
@@ -140,14 +143,18 @@ pub fn validate_date(date: &DateTime<Utc>) -> Result<(), ValidationError> {
 
 pub mod access;
 pub mod user;
+pub mod region;
+pub mod occupation;
 pub mod company;
 pub mod company_member;
 pub mod costs;
 pub mod labor;
 pub mod product;
+pub mod product_listing;
 pub mod resource_tag;
 pub mod order;
 pub mod cost_tag;
+pub mod amortization;
 
 #[derive(Serialize, Deserialize, Clone, Debug, TransactionSet)]
 pub enum TransactionGroup {
@@ -184,5 +191,43 @@ pub enum TransactionGroup {
     CostTagCreate(cost_tag::TxCreate),
     CostTagUpdate(cost_tag::TxUpdate),
     CostTagDelete(cost_tag::TxDelete),
+
+    AmortizationCreate(amortization::TxCreate),
+    AmortizationUpdate(amortization::TxUpdate),
+    AmortizationDelete(amortization::TxDelete),
+
+    CostsRepairAggregates(costs::TxRepairAggregates),
+
+    LaborClockIn(labor::TxClockIn),
+    LaborClockOut(labor::TxClockOut),
+    LaborCreateCorrection(labor::TxCreateCorrection),
+    LaborReviewCorrection(labor::TxReviewCorrection),
+    LaborVoid(labor::TxVoid),
+
+    OccupationCreate(occupation::TxCreate),
+    OccupationMerge(occupation::TxMerge),
+
+    ProductListingCreate(product_listing::TxCreate),
+    ProductListingUpdate(product_listing::TxUpdate),
+    ProductListingDelete(product_listing::TxDelete),
+
+    OrderCancel(order::TxCancel),
+
+    OrderUpdateShipping(order::TxUpdateShipping),
+    OrderUpdateShippingDates(order::TxUpdateShippingDates),
+
+    OrderUpdateFulfillment(order::TxUpdateFulfillment),
+
+    OrderCreateReturn(order::TxCreateReturn),
+
+    CompanyCreateSyndicate(company::TxCreateSyndicate),
+    CompanyCreatePublic(company::TxCreatePublic),
+
+    CompanySetApproved(company::TxSetApproved),
+
+    UserSetRegionRoles(user::TxSetRegionRoles),
+    RegionCreate(region::TxCreate),
+    RegionUpdate(region::TxUpdate),
+    CompanySetRegion(company::TxSetRegion),
 }
 
@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use validator::Validate;
+use exonum::{
+    blockchain::{ExecutionError, ExecutionResult, Transaction, TransactionContext},
+};
+use models::{
+    proto,
+    access::Permission,
+    occupation::Occupation,
+};
+use crate::block::{
+    schema::Schema,
+    transactions::access,
+};
+use util;
+use super::CommonError;
+
+#[derive(Debug, Fail)]
+#[repr(u8)]
+pub enum TransactionError {
+    #[fail(display = "Occupation not found")]
+    OccupationNotFound = 0,
+
+    #[fail(display = "An occupation with that label already exists")]
+    LabelExists = 1,
+
+    #[fail(display = "Invalid occupation label")]
+    InvalidLabel = 2,
+
+    #[fail(display = "Invalid merge (occupations must be different and not already merged)")]
+    InvalidMerge = 3,
+}
+define_exec_error!(TransactionError);
+
+deftransaction! {
+    #[exonum(pb = "proto::occupation::TxCreate")]
+    pub struct TxCreate {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(length(min = 1))]
+        pub label: String,
+        #[validate(custom = "super::validate_date")]
+        pub created: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxCreate {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::OccupationCreate)?;
+
+        if schema.get_occupation(&self.id).is_some() {
+            Err(CommonError::IDExists)?;
+        }
+        if Occupation::normalize_label(&self.label) == "" {
+            Err(TransactionError::InvalidLabel)?;
+        }
+        if schema.get_occupation_by_label(&self.label).is_some() {
+            Err(TransactionError::LabelExists)?;
+        }
+        if !util::time::is_current(&self.created) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        schema.occupations_create(&self.id, &self.label, &self.created, &hash);
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::occupation::TxMerge")]
+    pub struct TxMerge {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(custom = "super::validate_uuid")]
+        pub into_id: String,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxMerge {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::OccupationMerge)?;
+
+        let occupation = match schema.get_occupation(&self.id) {
+            Some(x) => x,
+            None => Err(TransactionError::OccupationNotFound)?,
+        };
+        let into = match schema.get_occupation(&self.into_id) {
+            Some(x) => x,
+            None => Err(TransactionError::OccupationNotFound)?,
+        };
+        // only ever merging unmerged occupations into each other keeps merge
+        // chains from looping back on themselves
+        if occupation.id == into.id || occupation.is_merged() || into.is_merged() {
+            Err(TransactionError::InvalidMerge)?;
+        }
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        schema.occupations_merge(occupation, &into.id, &self.updated, &hash);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use models::{
+        company,
+        cost_tag::CostTagEntry,
+    };
+    use util;
+    use crate::block::{transactions, schema::Schema};
+    use crate::test::{self, gen_uuid};
+
+    #[test]
+    fn normalizes_occupations() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        testkit.create_block_with_transactions(txvec![tx_user]);
+
+        let occ1_id = gen_uuid();
+        let occ2_id = gen_uuid();
+        let occ3_id = gen_uuid();
+        let tx_occ1 = transactions::occupation::TxCreate::sign(
+            &occ1_id,
+            &String::from("Widget builder"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        // same occupation, different spelling
+        let tx_occ2 = transactions::occupation::TxCreate::sign(
+            &occ2_id,
+            &String::from("widget-builder"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_occ3 = transactions::occupation::TxCreate::sign(
+            &occ3_id,
+            &String::from("Widget maker"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_occ1, tx_occ2, tx_occ3]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.get_occupation(&occ1_id).unwrap().label, "Widget builder");
+        assert!(schema.get_occupation(&occ2_id).is_none());
+        assert_eq!(schema.get_occupation_by_label("WIDGET BUILDER").unwrap().id, occ1_id);
+
+        // members referencing an occupation by label get its ID
+        let co1_id = gen_uuid();
+        let co1_founder_id = gen_uuid();
+        let ctag1_op_id = gen_uuid();
+        let tx_co1 = transactions::company::TxCreatePrivate::sign(
+            &co1_id,
+            &String::from("company1@basis.org"),
+            &String::from("Widget Builders Inc"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&co1_founder_id, "widget-builder", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1]);
+
+        let labor1_id = gen_uuid();
+        let tx_clock_in = transactions::labor::TxClockIn::sign(
+            &labor1_id,
+            &co1_id,
+            &uid,
+            &vec![],
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_clock_in]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.get_company_member(&co1_founder_id).unwrap().occupation, occ1_id);
+        assert_eq!(schema.get_labor(&labor1_id).unwrap().occupation, occ1_id);
+
+        let tx_clock_out = transactions::labor::TxClockOut::sign(
+            &labor1_id,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_merge = transactions::occupation::TxMerge::sign(
+            &occ1_id,
+            &occ3_id,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_clock_out, tx_merge]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let occ1 = schema.get_occupation(&occ1_id).unwrap();
+        assert!(occ1.is_merged());
+        assert_eq!(occ1.merged_into, occ3_id);
+        assert_eq!(occ1.history_len, 2);
+        assert_eq!(schema.get_occupation_resolved(&occ1_id).unwrap().id, occ3_id);
+        assert_eq!(schema.get_occupation_resolved("widget builder").unwrap().id, occ3_id);
+        assert_eq!(schema.occupation_normalize("Underwater basket weaver"), "Underwater basket weaver");
+
+        // merged occupations can't be merged (or merged into) again
+        let tx_merge1 = transactions::occupation::TxMerge::sign(
+            &occ3_id,
+            &occ1_id,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_merge2 = transactions::occupation::TxMerge::sign(
+            &occ3_id,
+            &occ3_id,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        // labor recorded after the merge uses the merged-into occupation
+        let labor2_id = gen_uuid();
+        let tx_clock_in = transactions::labor::TxClockIn::sign(
+            &labor2_id,
+            &co1_id,
+            &uid,
+            &vec![],
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_merge1, tx_merge2, tx_clock_in]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let occ3 = schema.get_occupation(&occ3_id).unwrap();
+        assert!(!occ3.is_merged());
+        assert_eq!(occ3.history_len, 1);
+        assert_eq!(schema.get_labor(&labor1_id).unwrap().occupation, occ1_id);
+        assert_eq!(schema.get_labor(&labor2_id).unwrap().occupation, occ3_id);
+    }
+}
+
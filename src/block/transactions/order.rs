@@ -7,9 +7,11 @@ use models::{
     proto,
     company::{Permission as CompanyPermission},
     access::Permission,
-    order::{ProductEntry, ProcessStatus},
+    order::{ProductEntry, ProcessStatus, OrderParty, ReturnEntry},
     cost_tag::CostTagEntry,
+    fixed::Fixed,
 };
+use util::protobuf::empty_opt;
 use crate::block::{
     schema::Schema,
     transactions::{company, access, costs, cost_tag},
@@ -37,6 +39,30 @@ pub enum TransactionError {
 
     #[fail(display = "Product is missing costs")]
     CostsNotFound = 6,
+
+    #[fail(display = "Cannot update a finalized order")]
+    OrderFinalized = 7,
+
+    #[fail(display = "Invalid order status transition")]
+    InvalidStatusTransition = 8,
+
+    #[fail(display = "Only the supplying company can make this status change")]
+    SupplierOnly = 9,
+
+    #[fail(display = "Only the buying company can make this status change")]
+    BuyerOnly = 10,
+
+    #[fail(display = "Order line not found")]
+    InvalidLine = 11,
+
+    #[fail(display = "Invalid quantity")]
+    InvalidQuantity = 12,
+
+    #[fail(display = "Only finalized orders can be returned")]
+    OrderNotFinalized = 13,
+
+    #[fail(display = "Company has not been approved")]
+    CompanyNotApproved = 14,
 }
 define_exec_error!(TransactionError);
 
@@ -67,17 +93,31 @@ impl Transaction for TxCreate {
         access::check(&mut schema, pubkey, Permission::OrderCreate)?;
         company::check(&mut schema, &self.company_id_from, pubkey, CompanyPermission::OrderCreate)?;
 
+        match schema.get_company(&self.company_id_from) {
+            Some(x) => {
+                if !x.is_approved() {
+                    Err(TransactionError::CompanyNotApproved)?;
+                }
+            }
+            None => Err(TransactionError::CompanyNotFound)?,
+        }
         match schema.get_company(&self.company_id_to) {
             Some(x) => {
                 if !x.is_active() {
                     Err(TransactionError::CompanyNotFound)?;
                 }
+                if !x.is_approved() {
+                    Err(TransactionError::CompanyNotApproved)?;
+                }
             }
             None => Err(TransactionError::CompanyNotFound)?,
         }
 
         let mut products = self.products.clone();
         for product in &mut products {
+            if product.quantity <= Fixed::zero() {
+                Err(TransactionError::InvalidQuantity)?;
+            }
             match schema.get_product_with_costs_tagged(&product.product_id) {
                 (Some(prod), Some(costs), tag) => {
                     if !prod.is_active() {
@@ -85,6 +125,9 @@ impl Transaction for TxCreate {
                     }
                     product.costs = costs;
                     product.resource = tag.is_some();
+                    product.fulfilled = Fixed::zero();
+                    product.canceled = Fixed::zero();
+                    product.returned = Fixed::zero();
                 }
                 (Some(_), None, _) => {
                     Err(TransactionError::CostsNotFound)?;
@@ -107,13 +150,22 @@ impl Transaction for TxCreate {
             }
         }
         let cost_tags = cost_tag::validate_cost_tags(&mut schema, &self.company_id_from, &self.cost_tags);
-        schema.orders_create(&self.id, &self.company_id_from, &self.company_id_to, &cost_tags, &products, &self.created, &hash);
+        schema.orders_create(&self.id, &self.company_id_from, &self.company_id_to, &cost_tags, &products, &self.created, &hash).map_err(costs::cost_error)?;
         costs::calculate_product_costs(&mut schema, &self.company_id_from)?;
         costs::calculate_product_costs(&mut schema, &self.company_id_to)?;
         Ok(())
     }
 }
 
+/// Swap a failed company permission check for a more specific error. Anything
+/// that isn't a permissions problem (unknown user, etc) is passed through.
+fn permission_error(err: CommonError, replacement: TransactionError) -> ExecutionError {
+    match err {
+        CommonError::InsufficientPrivileges => From::from(replacement),
+        _ => From::from(err),
+    }
+}
+
 deftransaction!{
     #[exonum(pb = "proto::order::TxUpdateStatus")]
     pub struct TxUpdateStatus {
@@ -142,9 +194,22 @@ impl Transaction for TxUpdateStatus {
         if order.process_status == ProcessStatus::Canceled {
             Err(TransactionError::OrderCanceled)?;
         }
+        if order.process_status == ProcessStatus::Finalized {
+            Err(TransactionError::OrderFinalized)?;
+        }
 
         access::check(&mut schema, pubkey, Permission::OrderUpdate)?;
-        company::check(&mut schema, &order.company_id_to, pubkey, CompanyPermission::OrderUpdateProcessStatus)?;
+        match order.process_status.transition_party(&self.process_status) {
+            Some(OrderParty::Supplier) => {
+                company::check(&mut schema, &order.company_id_to, pubkey, CompanyPermission::OrderUpdateProcessStatus)
+                    .map_err(|e| permission_error(e, TransactionError::SupplierOnly))?;
+            }
+            Some(OrderParty::Buyer) => {
+                company::check(&mut schema, &order.company_id_from, pubkey, CompanyPermission::OrderFinalize)
+                    .map_err(|e| permission_error(e, TransactionError::BuyerOnly))?;
+            }
+            None => Err(TransactionError::InvalidStatusTransition)?,
+        }
 
         if !util::time::is_current(&self.updated) {
             match access::check(&mut schema, pubkey, Permission::TimeTravel) {
@@ -156,7 +221,12 @@ impl Transaction for TxUpdateStatus {
         }
         let company_id_from = order.company_id_from.clone();
         let company_id_to = order.company_id_to.clone();
-        schema.orders_update_status(order, &self.process_status, &self.updated, &hash);
+        if self.process_status == ProcessStatus::Canceled {
+            let user = schema.get_user_by_pubkey(pubkey).ok_or_else(|| CommonError::UserNotFound)?;
+            schema.orders_cancel(order, &user.id, "", &self.updated, &hash).map_err(costs::cost_error)?;
+        } else {
+            schema.orders_update_status(order, &self.process_status, &self.updated, &hash).map_err(costs::cost_error)?;
+        }
         costs::calculate_product_costs(&mut schema, &company_id_from)?;
         costs::calculate_product_costs(&mut schema, &company_id_to)?;
         Ok(())
@@ -194,7 +264,280 @@ impl Transaction for TxUpdateCostTags {
         let company_id_from = order.company_id_from.clone();
         let company_id_to = order.company_id_to.clone();
         let cost_tags = cost_tag::validate_cost_tags(&mut schema, &company_id_from, &self.cost_tags);
-        schema.orders_update_cost_tags(order, &cost_tags, &self.updated, &hash);
+        schema.orders_update_cost_tags(order, &cost_tags, &self.updated, &hash).map_err(costs::cost_error)?;
+        costs::calculate_product_costs(&mut schema, &company_id_from)?;
+        costs::calculate_product_costs(&mut schema, &company_id_to)?;
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::order::TxUpdateFulfillment")]
+    pub struct TxUpdateFulfillment {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub line: u32,
+        pub fulfill: Fixed,
+        pub cancel: Fixed,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxUpdateFulfillment {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let order = schema.get_order(&self.id).ok_or_else(|| TransactionError::OrderNotFound)?;
+        if order.process_status == ProcessStatus::Canceled {
+            Err(TransactionError::OrderCanceled)?;
+        }
+        // once an order is completed, its lines are locked in
+        if !order.process_status.is_cancelable() {
+            Err(TransactionError::InvalidStatusTransition)?;
+        }
+
+        // the supplier fulfills (and can cancel), the buyer can only cancel
+        access::check(&mut schema, pubkey, Permission::OrderUpdate)?;
+        match company::check(&mut schema, &order.company_id_to, pubkey, CompanyPermission::OrderUpdateProcessStatus) {
+            Ok(_) => {}
+            Err(e) => {
+                if self.fulfill > Fixed::zero() {
+                    Err(e)?;
+                }
+                company::check(&mut schema, &order.company_id_from, pubkey, CompanyPermission::OrderCancel)?;
+            }
+        }
+
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let line = self.line as usize;
+        let entry = order.products.get(line).ok_or_else(|| TransactionError::InvalidLine)?;
+        if self.fulfill < Fixed::zero() || self.cancel < Fixed::zero() {
+            Err(TransactionError::InvalidQuantity)?;
+        }
+        let requested = self.fulfill.checked_add(self.cancel)
+            .ok_or_else(|| TransactionError::InvalidQuantity)?;
+        let remaining = entry.remaining()
+            .ok_or_else(|| TransactionError::InvalidQuantity)?;
+        if requested.is_zero() || requested > remaining {
+            Err(TransactionError::InvalidQuantity)?;
+        }
+
+        let company_id_from = order.company_id_from.clone();
+        let company_id_to = order.company_id_to.clone();
+        schema.orders_update_fulfillment(order, line, self.fulfill, self.cancel, &self.updated, &hash).map_err(costs::cost_error)?;
+        costs::calculate_product_costs(&mut schema, &company_id_from)?;
+        costs::calculate_product_costs(&mut schema, &company_id_to)?;
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::order::TxUpdateShipping")]
+    pub struct TxUpdateShipping {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub address: String,
+        pub carrier: String,
+        pub tracking: String,
+        pub meta: String,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxUpdateShipping {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let order = schema.get_order(&self.id).ok_or_else(|| TransactionError::OrderNotFound)?;
+        if order.process_status == ProcessStatus::Canceled {
+            Err(TransactionError::OrderCanceled)?;
+        }
+
+        // the buyer generally knows where things are going and the supplier
+        // knows how they're getting there, so either side can update this
+        access::check(&mut schema, pubkey, Permission::OrderUpdate)?;
+        company::check(&mut schema, &order.company_id_to, pubkey, CompanyPermission::OrderUpdateShipping)
+            .or_else(|_| company::check(&mut schema, &order.company_id_from, pubkey, CompanyPermission::OrderUpdateShipping))?;
+
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let address = empty_opt(&self.address).map(|x| x.as_str());
+        let carrier = empty_opt(&self.carrier).map(|x| x.as_str());
+        let tracking = empty_opt(&self.tracking).map(|x| x.as_str());
+        let meta = empty_opt(&self.meta).map(|x| x.as_str());
+        let shipping = order.shipping.update_info(address, carrier, tracking, meta);
+        schema.orders_update_shipping(order, &shipping, &self.updated, &hash);
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::order::TxUpdateShippingDates")]
+    pub struct TxUpdateShippingDates {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub date_ship_expected: DateTime<Utc>,
+        pub date_shipped: DateTime<Utc>,
+        pub date_delivery_expected: DateTime<Utc>,
+        pub date_delivered: DateTime<Utc>,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxUpdateShippingDates {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let order = schema.get_order(&self.id).ok_or_else(|| TransactionError::OrderNotFound)?;
+        if order.process_status == ProcessStatus::Canceled {
+            Err(TransactionError::OrderCanceled)?;
+        }
+
+        access::check(&mut schema, pubkey, Permission::OrderUpdate)?;
+        company::check(&mut schema, &order.company_id_to, pubkey, CompanyPermission::OrderUpdateShippingDates)?;
+
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let empty_date = util::time::default_time();
+        let date_ship_expected = if self.date_ship_expected == empty_date { None } else { Some(&self.date_ship_expected) };
+        let date_shipped = if self.date_shipped == empty_date { None } else { Some(&self.date_shipped) };
+        let date_delivery_expected = if self.date_delivery_expected == empty_date { None } else { Some(&self.date_delivery_expected) };
+        let date_delivered = if self.date_delivered == empty_date { None } else { Some(&self.date_delivered) };
+        let shipping = order.shipping.update_dates(date_ship_expected, date_shipped, date_delivery_expected, date_delivered);
+        schema.orders_update_shipping(order, &shipping, &self.updated, &hash);
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::order::TxCancel")]
+    pub struct TxCancel {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub reason: String,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxCancel {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let order = schema.get_order(&self.id).ok_or_else(|| TransactionError::OrderNotFound)?;
+        if order.process_status == ProcessStatus::Canceled {
+            Err(TransactionError::OrderCanceled)?;
+        }
+        if order.process_status == ProcessStatus::Finalized {
+            Err(TransactionError::OrderFinalized)?;
+        }
+        if !order.process_status.is_cancelable() {
+            Err(TransactionError::InvalidStatusTransition)?;
+        }
+
+        access::check(&mut schema, pubkey, Permission::OrderUpdate)?;
+        company::check(&mut schema, &order.company_id_from, pubkey, CompanyPermission::OrderCancel)?;
+
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let user = schema.get_user_by_pubkey(pubkey).ok_or_else(|| CommonError::UserNotFound)?;
+        let company_id_from = order.company_id_from.clone();
+        let company_id_to = order.company_id_to.clone();
+        schema.orders_cancel(order, &user.id, &self.reason, &self.updated, &hash).map_err(costs::cost_error)?;
+        costs::calculate_product_costs(&mut schema, &company_id_from)?;
+        costs::calculate_product_costs(&mut schema, &company_id_to)?;
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::order::TxCreateReturn")]
+    pub struct TxCreateReturn {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(custom = "super::validate_uuid")]
+        pub order_id: String,
+        pub products: Vec<ReturnEntry>,
+        pub reason: String,
+        #[validate(custom = "super::validate_date")]
+        pub created: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxCreateReturn {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let order = schema.get_order(&self.order_id).ok_or_else(|| TransactionError::OrderNotFound)?;
+
+        access::check(&mut schema, pubkey, Permission::OrderUpdate)?;
+        company::check(&mut schema, &order.company_id_from, pubkey, CompanyPermission::OrderCreateReturn)?;
+
+        if order.process_status != ProcessStatus::Finalized {
+            Err(TransactionError::OrderNotFinalized)?;
+        }
+        if schema.get_order_return(&self.id).is_some() {
+            Err(CommonError::IDExists)?;
+        }
+        if !util::time::is_current(&self.created) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        // make sure each line exists and we're not returning more than was
+        // fulfilled (counting previous returns, and earlier entries for the
+        // same line in this return)
+        if self.products.len() == 0 {
+            Err(TransactionError::InvalidQuantity)?;
+        }
+        let mut returnable = order.products.iter()
+            .map(|x| x.net_fulfilled())
+            .collect::<Vec<_>>();
+        let mut products = self.products.clone();
+        for entry in &mut products {
+            let line = entry.line as usize;
+            let order_entry = order.products.get(line).ok_or_else(|| TransactionError::InvalidLine)?;
+            if entry.quantity <= Fixed::zero() || entry.quantity > returnable[line] {
+                Err(TransactionError::InvalidQuantity)?;
+            }
+            returnable[line] -= entry.quantity;
+            entry.product_id = order_entry.product_id.clone();
+        }
+
+        let company_id_from = order.company_id_from.clone();
+        let company_id_to = order.company_id_to.clone();
+        schema.order_returns_create(&self.id, order, &products, &self.reason, &self.created, &hash).map_err(costs::cost_error)?;
         costs::calculate_product_costs(&mut schema, &company_id_from)?;
         costs::calculate_product_costs(&mut schema, &company_id_to)?;
         Ok(())
@@ -243,6 +586,7 @@ pub mod tests {
             ],
             &company::TxCreatePrivateFounder::new(&co0_founder_id, "Coal miner", 1.0, &vec![CostTagEntry::new(&ctag0_op_id, 1)]),
             &util::time::now(),
+            &String::from(""),
             &root_pub,
             &root_sec
         );
@@ -256,6 +600,7 @@ pub mod tests {
             ],
             &company::TxCreatePrivateFounder::new(&co1_founder_id, "Widget builder", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
             &util::time::now(),
+            &String::from(""),
             &root_pub,
             &root_sec
         );
@@ -269,6 +614,7 @@ pub mod tests {
             ],
             &company::TxCreatePrivateFounder::new(&co2_founder_id, "Widget builder", 1.0, &vec![CostTagEntry::new(&ctag2_op_id, 1)]),
             &util::time::now(),
+            &String::from(""),
             &root_pub,
             &root_sec
         );
@@ -327,6 +673,8 @@ pub mod tests {
         testkit.create_block_with_transactions(txvec![tx_prod]);
 
         // log some labor into the widget builder and miner
+        let now = util::time::now();
+        let then = now - Duration::hours(8);
         let labor1_id = gen_uuid();
         let labor2_id = gen_uuid();
         let tx_labor1 = transactions::labor::TxCreate::sign(
@@ -334,7 +682,7 @@ pub mod tests {
             &co1_id,
             &uid,
             &Default::default(),
-            &util::time::now(),
+            &then,
             &root_pub,
             &root_sec
         );
@@ -343,18 +691,16 @@ pub mod tests {
             &co0_id,
             &uid,
             &Default::default(),
-            &util::time::now(),
+            &then,
             &root_pub,
             &root_sec
         );
         testkit.create_block_with_transactions(txvec![tx_labor1, tx_labor2]);
 
-        let now = util::time::now();
-        let then = now - Duration::hours(8);
         let tx_labor_fin1 = transactions::labor::TxUpdate::sign(
             &labor1_id,
             &Default::default(),
-            &then,
+            &util::time::default_time(),
             &now,
             &now,
             &root_pub,
@@ -363,7 +709,7 @@ pub mod tests {
         let tx_labor_fin2 = transactions::labor::TxUpdate::sign(
             &labor2_id,
             &Default::default(),
-            &then,
+            &util::time::default_time(),
             &now,
             &now,
             &root_pub,
@@ -388,14 +734,7 @@ pub mod tests {
         );
         testkit.create_block_with_transactions(txvec![tx_ord1]);
         // finalize
-        let tx_ord1_stat = transactions::order::TxUpdateStatus::sign(
-            &ord1_id,
-            &models::order::ProcessStatus::Finalized,
-            &ord1_date,
-            &root_pub,
-            &root_sec
-        );
-        testkit.create_block_with_transactions(txvec![tx_ord1_stat]);
+        testkit.create_block_with_transactions(test::tx_finalize_order(&ord1_id, &ord1_date, &root_pub, &root_sec));
 
         // widget builder orders coal (again, gets costs of coal into widgets)
         let ord1_1_id = gen_uuid();
@@ -412,14 +751,7 @@ pub mod tests {
         );
         testkit.create_block_with_transactions(txvec![tx_ord1_1]);
         // finalize
-        let tx_ord1_1_stat = transactions::order::TxUpdateStatus::sign(
-            &ord1_1_id,
-            &models::order::ProcessStatus::Finalized,
-            &ord1_1_date,
-            &root_pub,
-            &root_sec
-        );
-        testkit.create_block_with_transactions(txvec![tx_ord1_1_stat]);
+        testkit.create_block_with_transactions(test::tx_finalize_order(&ord1_1_id, &ord1_1_date, &root_pub, &root_sec));
 
         let snapshot = testkit.snapshot();
         let schema = Schema::new(&snapshot);
@@ -430,7 +762,7 @@ pub mod tests {
         assert_eq!(idx_from.keys().count(), 2);
         assert_eq!(idx_to.keys().count(), 2);
         let coal_costs = schema.get_product_costs(&prod_coal_id).expect("missing coal product costs");
-        assert!(coal_costs.get_labor("Coal miner") - 0.05333333333333334 < 0.000000001);
+        assert!(coal_costs.get_labor("Coal miner").to_f64() - 0.05333333333333334 < 0.000000001);
 
         // widget distributor orders widgets
         let ord2_id = gen_uuid();
@@ -480,21 +812,9 @@ pub mod tests {
         assert_eq!(costs_map.map_ref().is_empty(), true);
 
         // finalize our widget orders, we should start seeing tracking now
-        let tx_ord2_stat = transactions::order::TxUpdateStatus::sign(
-            &ord2_id,
-            &models::order::ProcessStatus::Finalized,
-            &ord1_date,
-            &root_pub,
-            &root_sec
-        );
-        let tx_ord3_stat = transactions::order::TxUpdateStatus::sign(
-            &ord3_id,
-            &models::order::ProcessStatus::Finalized,
-            &ord1_date,
-            &root_pub,
-            &root_sec
-        );
-        testkit.create_block_with_transactions(txvec![tx_ord2_stat, tx_ord3_stat]);
+        let mut txs = test::tx_finalize_order(&ord2_id, &ord1_date, &root_pub, &root_sec);
+        txs.extend(test::tx_finalize_order(&ord3_id, &ord1_date, &root_pub, &root_sec));
+        testkit.create_block_with_transactions(txs);
 
         let snapshot = testkit.snapshot();
         let schema = Schema::new(&snapshot);
@@ -505,17 +825,17 @@ pub mod tests {
         assert_eq!(num_orders, 4);
         assert_eq!(idx_from.keys().count(), 2);
         assert_eq!(idx_to.keys().count(), 2);
-        assert_eq!(prod_costs.get(&prod_coal_id), 8.823529411764707);
-        assert_eq!(prod_costs.get_labor("Widget builder"), 0.47058823529411764);
-        assert_eq!(prod_costs.get_labor("Coal miner"), 0.23529411764705882);
+        assert!((prod_costs.get(&prod_coal_id).to_f64() - 8.823529411764707).abs() < 0.00000001);
+        assert!((prod_costs.get_labor("Widget builder").to_f64() - 0.47058823529411764).abs() < 0.00000001);
+        assert!((prod_costs.get_labor("Coal miner").to_f64() - 0.23529411764705882).abs() < 0.00000001);
 
         let costs_map = schema.costs_aggregate(&co2_id).get("costs.v1").expect("costs.v1 cost map doesn't exist");
         let op_costs_bucket = costs_map.map_ref().get(&ctag2_op_id).expect("costs.v1 cost map does not contain `Operating` costs");
         let op_costs = op_costs_bucket.total();
         assert_eq!(op_costs_bucket.len(), 2);
         assert_eq!(op_costs.get(&prod_coal_id), 62.5);
-        assert!(op_costs.get_labor("Coal miner") - (1.0 + (2.0 / 3.0)) < 0.000000001);
-        assert!(op_costs.get_labor("Widget builder") - (10.0 / 3.0) < 0.000000001);
+        assert!(op_costs.get_labor("Coal miner").to_f64() - (1.0 + (2.0 / 3.0)) < 0.000000001);
+        assert!(op_costs.get_labor("Widget builder").to_f64() - (10.0 / 3.0) < 0.000000001);
 
         // distrib orders more widgets, should cycle out ord2
         let ord4_id = gen_uuid();
@@ -539,17 +859,10 @@ pub mod tests {
         let op_costs = op_costs_bucket.total();
         assert_eq!(op_costs_bucket.len(), 1);
         assert_eq!(op_costs.get(&prod_coal_id), 62.5);
-        assert!(op_costs.get_labor("Coal miner") - (1.0 + (2.0 / 3.0)) < 0.000000001);
-        assert!(op_costs.get_labor("Widget builder") - (10.0 / 3.0) < 0.000000001);
+        assert!(op_costs.get_labor("Coal miner").to_f64() - (1.0 + (2.0 / 3.0)) < 0.000000001);
+        assert!(op_costs.get_labor("Widget builder").to_f64() - (10.0 / 3.0) < 0.000000001);
 
-        let tx_ord4_stat = transactions::order::TxUpdateStatus::sign(
-            &ord4_id,
-            &models::order::ProcessStatus::Finalized,
-            &ord4_date,
-            &root_pub,
-            &root_sec
-        );
-        testkit.create_block_with_transactions(txvec![tx_ord4_stat]);
+        testkit.create_block_with_transactions(test::tx_finalize_order(&ord4_id, &ord4_date, &root_pub, &root_sec));
         // test for resource tagging
         let order = Schema::new(&snapshot).get_order(&ord1_id).unwrap();
         assert!(order.products[0].is_resource());
@@ -564,9 +877,231 @@ pub mod tests {
         let op_costs_bucket = costs_map.map_ref().get(&ctag2_op_id).expect("costs.v1 cost map does not contain `Operating` costs");
         let op_costs = op_costs_bucket.total();
         assert_eq!(op_costs_bucket.len(), 2);
-        assert_eq!(op_costs.get(&prod_coal_id), 88.97058823529412);
-        assert!(op_costs.get_labor("Coal miner") - 2.3725490196078427 < 0.000000001);
-        assert!(op_costs.get_labor("Widget builder") - 4.745098039215685 < 0.000000001);
+        assert!((op_costs.get(&prod_coal_id).to_f64() - 88.97058823529412).abs() < 0.00000001);
+        assert!(op_costs.get_labor("Coal miner").to_f64() - 2.3725490196078427 < 0.000000001);
+        assert!(op_costs.get_labor("Widget builder").to_f64() - 4.745098039215685 < 0.000000001);
+    }
+
+    #[test]
+    fn returns_come_off_the_aggregates() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        testkit.create_block_with_transactions(txvec![tx_user]);
+
+        let co1_id = gen_uuid();
+        let co2_id = gen_uuid();
+        let ctag1_op_id = gen_uuid();
+        let ctag2_op_id = gen_uuid();
+        let tx_co1 = transactions::company::TxCreatePrivate::sign(
+            &co1_id,
+            &String::from("returns@basis.org"),
+            &String::from("Widgets Of Questionable Quality LLC"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget builder", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        let tx_co2 = transactions::company::TxCreatePrivate::sign(
+            &co2_id,
+            &String::from("complaints@basis.org"),
+            &String::from("Disappointed Widget Enthusiasts Co"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag2_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget inspector", 1.0, &vec![CostTagEntry::new(&ctag2_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1, tx_co2]);
+
+        let prod_id = gen_uuid();
+        let tx_prod = transactions::product::TxCreate::sign(
+            &prod_id,
+            &co1_id,
+            &String::from("Widget (slightly used)"),
+            &models::product::Unit::Millimeter,
+            &1.0,
+            &models::product::Dimensions::new(10.0, 10.0, 10.0),
+            &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+            &true,
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_prod]);
+
+        let ord_id = gen_uuid();
+        let now = util::time::now();
+        let tx_ord = transactions::order::TxCreate::sign(
+            &ord_id,
+            &co2_id,
+            &co1_id,
+            &vec![CostTagEntry::new(&ctag2_op_id, 1)],
+            &vec![ProductEntry::new(&prod_id, 100.0, &models::costs::Costs::new(), false)],
+            &now,
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_ord]);
+
+        // can't return things that haven't been finalized
+        let ret1_id = gen_uuid();
+        let tx_ret1 = TxCreateReturn::sign(
+            &ret1_id,
+            &ord_id,
+            &vec![ReturnEntry::new(0, "", 10.0)],
+            &String::from("these widgets are only questionable, not bad"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_ret1]);
+        testkit.create_block_with_transactions(test::tx_finalize_order(&ord_id, &now, &root_pub, &root_sec));
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(schema.get_order_return(&ret1_id).is_none());
+        let outputs = schema.costs_aggregate(&co1_id).get("product_outputs.v1").expect("missing outputs").get("outputs").total();
+        assert_eq!(outputs.get(&prod_id), 100.0);
+
+        // returning more than we got should fail
+        let ret2_id = gen_uuid();
+        let tx_ret2 = TxCreateReturn::sign(
+            &ret2_id,
+            &ord_id,
+            &vec![ReturnEntry::new(0, "", 60.0), ReturnEntry::new(0, "", 60.0)],
+            &String::from("i want to return more widgets than i bought, for profit"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let ret3_id = gen_uuid();
+        let tx_ret3 = TxCreateReturn::sign(
+            &ret3_id,
+            &ord_id,
+            &vec![ReturnEntry::new(0, "", 40.0)],
+            &String::from("these ones are actually bad"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_ret2, tx_ret3]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(schema.get_order_return(&ret2_id).is_none());
+        let ret = schema.get_order_return(&ret3_id).expect("missing return");
+        assert_eq!(ret.order_id, ord_id);
+        assert_eq!(ret.company_id_from, co2_id);
+        assert_eq!(ret.company_id_to, co1_id);
+        assert_eq!(ret.products[0].product_id, prod_id);
+        assert_eq!(ret.history_len, 1);
+        assert_eq!(schema.get_order_returns_by_order_id(&ord_id).len(), 1);
+        let order = schema.get_order(&ord_id).unwrap();
+        assert_eq!(order.process_status, ProcessStatus::Finalized);
+        assert_eq!(order.products[0].fulfilled, 100.0);
+        assert_eq!(order.products[0].returned, 40.0);
+        let outputs = schema.costs_aggregate(&co1_id).get("product_outputs.v1").expect("missing outputs").get("outputs").total();
+        assert_eq!(outputs.get(&prod_id), 60.0);
+    }
+
+    #[test]
+    fn rejects_invalid_quantities() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        testkit.create_block_with_transactions(txvec![tx_user]);
+
+        let co1_id = gen_uuid();
+        let co2_id = gen_uuid();
+        let ctag1_op_id = gen_uuid();
+        let ctag2_op_id = gen_uuid();
+        let tx_co1 = transactions::company::TxCreatePrivate::sign(
+            &co1_id,
+            &String::from("company1@basis.org"),
+            &String::from("Widget Builders Inc"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget builder", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        let tx_co2 = transactions::company::TxCreatePrivate::sign(
+            &co2_id,
+            &String::from("company2@basis.org"),
+            &String::from("Widget Distributors Inc"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag2_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget distributor", 1.0, &vec![CostTagEntry::new(&ctag2_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1, tx_co2]);
+
+        let prod_id = gen_uuid();
+        let tx_prod = transactions::product::TxCreate::sign(
+            &prod_id,
+            &co1_id,
+            &String::from("Red widget"),
+            &models::product::Unit::Millimeter,
+            &1.0,
+            &models::product::Dimensions::new(10.0, 10.0, 10.0),
+            &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+            &true,
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_prod]);
+
+        // zero or negative quantities would end up as zero or negative costs
+        // for the buyer
+        let make_order = |id: &str, quantity: f64| {
+            TxCreate::sign(
+                &id.to_owned(),
+                &co2_id,
+                &co1_id,
+                &vec![CostTagEntry::new(&ctag2_op_id, 1)],
+                &vec![ProductEntry::new(&prod_id, quantity, &models::costs::Costs::new(), false)],
+                &util::time::now(),
+                &root_pub,
+                &root_sec
+            )
+        };
+        let ord1_id = gen_uuid();
+        let ord2_id = gen_uuid();
+        let ord3_id = gen_uuid();
+        testkit.create_block_with_transactions(txvec![
+            make_order(&ord1_id, 0.0),
+            make_order(&ord2_id, -12.0),
+            make_order(&ord3_id, 12.0),
+        ]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(schema.get_order(&ord1_id).is_none());
+        assert!(schema.get_order(&ord2_id).is_none());
+        assert!(schema.get_order(&ord3_id).is_some());
+
+        // quantities big enough to wrap around when added together don't
+        // sneak past the remaining check
+        let huge = Fixed::from_raw(i128::max_value());
+        let tx_fulfill = TxUpdateFulfillment::sign(&ord3_id, &0, &huge, &huge, &util::time::now(), &root_pub, &root_sec);
+        testkit.create_block_with_transactions(txvec![tx_fulfill]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let order = schema.get_order(&ord3_id).unwrap();
+        assert_eq!(order.products[0].fulfilled, 0.0);
+        assert_eq!(order.products[0].canceled, 0.0);
+        assert_eq!(order.history_len, 1);
     }
 }
 
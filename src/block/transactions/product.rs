@@ -31,6 +31,9 @@ pub enum TransactionError {
 
     #[fail(display = "Product already deleted")]
     AlreadyDeleted = 4,
+
+    #[fail(display = "Company has not been approved")]
+    CompanyNotApproved = 5,
 }
 define_exec_error!(TransactionError);
 
@@ -64,6 +67,14 @@ impl Transaction for TxCreate {
 
         access::check(&mut schema, pubkey, Permission::ProductCreate)?;
         company::check(&mut schema, &self.company_id, pubkey, CompanyPermission::ProductCreate)?;
+        match schema.get_company(&self.company_id) {
+            Some(x) => {
+                if !x.is_approved() {
+                    Err(TransactionError::CompanyNotApproved)?;
+                }
+            }
+            None => Err(TransactionError::CompanyNotFound)?,
+        }
         let cost_tags = match company::check(&mut schema, &self.company_id, pubkey, CompanyPermission::ProductTagCost) {
             Ok(_) => self.cost_tags.clone(),
             Err(_) => vec![],
@@ -205,6 +216,7 @@ pub mod tests {
             &vec![company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
             &company::TxCreatePrivateFounder::new(&co_founder_id, "Widgets, Builder of", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
             &util::time::now(),
+            &String::from(""),
             &root_pub,
             &root_sec
         );
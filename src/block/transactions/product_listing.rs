@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+use exonum::{
+    blockchain::{ExecutionError, ExecutionResult, Transaction, TransactionContext},
+};
+use exonum_merkledb::IndexAccess;
+use models::{
+    proto,
+    company::{Permission as CompanyPermission},
+    access::Permission,
+    product_listing::ProductEntry,
+};
+use crate::block::{
+    schema::Schema,
+    transactions::{company, access},
+};
+use util::{self, protobuf::empty_opt};
+use super::CommonError;
+
+#[derive(Debug, Fail)]
+#[repr(u8)]
+pub enum TransactionError {
+    #[fail(display = "Invalid ID")]
+    InvalidID = 0,
+
+    #[fail(display = "Product listing not found")]
+    ProductListingNotFound = 1,
+
+    #[fail(display = "Company not found")]
+    CompanyNotFound = 2,
+
+    #[fail(display = "Product not found")]
+    ProductNotFound = 3,
+
+    #[fail(display = "Product listing already deleted")]
+    AlreadyDeleted = 4,
+
+    #[fail(display = "Product entry uses an option not defined by the listing")]
+    InvalidOption = 5,
+}
+define_exec_error!(TransactionError);
+
+/// Make sure every product in a listing belongs to the listing's company and
+/// only uses options the listing defines.
+fn validate_products<T>(schema: &mut Schema<T>, company_id: &str, options: &HashMap<String, String>, products: &HashMap<String, ProductEntry>) -> Result<(), TransactionError>
+    where T: IndexAccess
+{
+    for entry in products.values() {
+        match schema.get_product(&entry.product_id) {
+            Some(product) => {
+                if product.company_id != company_id || product.is_deleted() {
+                    Err(TransactionError::ProductNotFound)?;
+                }
+            }
+            None => Err(TransactionError::ProductNotFound)?,
+        }
+        for option in entry.options.keys() {
+            if !options.contains_key(option) {
+                Err(TransactionError::InvalidOption)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::product_listing::TxCreate")]
+    pub struct TxCreate {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(custom = "super::validate_uuid")]
+        pub company_id: String,
+        #[validate(length(min = 1))]
+        pub name: String,
+        pub options: HashMap<String, String>,
+        pub products: HashMap<String, ProductEntry>,
+        pub meta: String,
+        pub active: bool,
+        #[validate(custom = "super::validate_date")]
+        pub created: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxCreate {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::ProductCreate)?;
+        company::check(&mut schema, &self.company_id, pubkey, CompanyPermission::ProductCreate)?;
+
+        match schema.get_company(&self.company_id) {
+            Some(_) => {}
+            None => Err(TransactionError::CompanyNotFound)?,
+        }
+
+        if schema.get_product_listing(&self.id).is_some() {
+            Err(CommonError::IDExists)?;
+        }
+        if !util::time::is_current(&self.created) {
+            Err(CommonError::InvalidTime)?;
+        }
+        validate_products(&mut schema, &self.company_id, &self.options, &self.products)?;
+        schema.product_listings_create(&self.id, &self.company_id, &self.name, &self.options, &self.products, &self.meta, self.active, &self.created, &hash);
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::product_listing::TxUpdate")]
+    pub struct TxUpdate {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub name: String,
+        pub options: HashMap<String, String>,
+        pub products: HashMap<String, ProductEntry>,
+        pub meta: String,
+        pub active: bool,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxUpdate {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        let listing = schema.get_product_listing(&self.id)
+            .ok_or_else(|| TransactionError::ProductListingNotFound)?;
+
+        access::check(&mut schema, pubkey, Permission::ProductUpdate)?;
+        company::check(&mut schema, &listing.company_id, pubkey, CompanyPermission::ProductUpdate)?;
+
+        if listing.is_deleted() {
+            Err(TransactionError::AlreadyDeleted)?;
+        }
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let name = empty_opt(&self.name).map(|x| x.as_str());
+        let options = empty_opt(&self.options);
+        let products = empty_opt(&self.products);
+        let meta = empty_opt(&self.meta).map(|x| x.as_str());
+        let active = Some(self.active);
+        let company_id = listing.company_id.clone();
+        validate_products(&mut schema, &company_id, options.unwrap_or(&listing.options), products.unwrap_or(&listing.products))?;
+        schema.product_listings_update(listing, name, options, products, meta, active, &self.updated, &hash);
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::product_listing::TxDelete")]
+    pub struct TxDelete {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(custom = "super::validate_date")]
+        pub deleted: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxDelete {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        if !util::time::is_current(&self.deleted) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let listing = schema.get_product_listing(&self.id)
+            .ok_or_else(|| TransactionError::ProductListingNotFound)?;
+
+        access::check(&mut schema, pubkey, Permission::ProductDelete)?;
+        company::check(&mut schema, &listing.company_id, pubkey, CompanyPermission::ProductDelete)?;
+
+        if listing.is_deleted() {
+            Err(TransactionError::AlreadyDeleted)?;
+        }
+        schema.product_listings_delete(listing, &self.deleted, &hash);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+    use models::{
+        self,
+        cost_tag::CostTagEntry,
+        company,
+        product_listing::ProductEntry,
+    };
+    use util;
+    use crate::block::{transactions, schema::Schema};
+    use crate::test::{self, gen_uuid};
+
+    #[test]
+    fn listings_group_products() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        testkit.create_block_with_transactions(txvec![tx_user]);
+
+        let co1_id = gen_uuid();
+        let co2_id = gen_uuid();
+        let ctag1_op_id = gen_uuid();
+        let ctag2_op_id = gen_uuid();
+        let co1_founder_id = gen_uuid();
+        let co2_founder_id = gen_uuid();
+        let tx_co1 = transactions::company::TxCreatePrivate::sign(
+            &co1_id,
+            &String::from("shirts@basis.org"),
+            &String::from("Big Red Shirt Co"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&co1_founder_id, "Shirt sewer", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        let tx_co2 = transactions::company::TxCreatePrivate::sign(
+            &co2_id,
+            &String::from("counterfeit@basis.org"),
+            &String::from("Totally Legitimate Shirts"),
+            &vec![company::TxCreatePrivateCostTag::new(&ctag2_op_id, "operating", "")],
+            &company::TxCreatePrivateFounder::new(&co2_founder_id, "Shirt forger", 1.0, &vec![CostTagEntry::new(&ctag2_op_id, 1)]),
+            &util::time::now(),
+            &String::from(""),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1, tx_co2]);
+
+        let make_product = |id: &str, company_id: &str, name: &str| {
+            transactions::product::TxCreate::sign(
+                &id.to_owned(),
+                &company_id.to_owned(),
+                &name.to_owned(),
+                &models::product::Unit::Each,
+                &200.0,
+                &models::product::Dimensions::new(500.0, 700.0, 5.0),
+                &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+                &true,
+                &String::from("{}"),
+                &util::time::now(),
+                &root_pub,
+                &root_sec
+            )
+        };
+        let prod_small_id = gen_uuid();
+        let prod_large_id = gen_uuid();
+        let prod_fake_id = gen_uuid();
+        testkit.create_block_with_transactions(txvec![
+            make_product(&prod_small_id, &co1_id, "Small red shirt"),
+            make_product(&prod_large_id, &co1_id, "Large red shirt"),
+            make_product(&prod_fake_id, &co2_id, "Large red shirt (authentic)")
+        ]);
+
+        let mut options = HashMap::new();
+        options.insert(String::from("size"), String::from("Shirt size"));
+        let make_entry = |product_id: &str, size: &str| {
+            let mut opts = HashMap::new();
+            opts.insert(String::from("size"), size.to_owned());
+            (product_id.to_owned(), ProductEntry::new(product_id, &opts))
+        };
+        let mut products = HashMap::new();
+        products.extend(vec![make_entry(&prod_small_id, "S"), make_entry(&prod_large_id, "L")]);
+
+        let listing_id = gen_uuid();
+        let tx_listing = transactions::product_listing::TxCreate::sign(
+            &listing_id,
+            &co1_id,
+            &String::from("Red shirts"),
+            &options,
+            &products,
+            &String::from("{}"),
+            &true,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        // try to sneak another company's product into the listing
+        let mut products_bad = products.clone();
+        products_bad.extend(vec![make_entry(&prod_fake_id, "L")]);
+        let listing_bad_id = gen_uuid();
+        let tx_listing_bad = transactions::product_listing::TxCreate::sign(
+            &listing_bad_id,
+            &co1_id,
+            &String::from("Red shirts (some are fake)"),
+            &options,
+            &products_bad,
+            &String::from("{}"),
+            &true,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_listing, tx_listing_bad]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let listing = schema.get_product_listing(&listing_id).unwrap();
+        assert_eq!(listing.products.len(), 2);
+        assert_eq!(listing.products.get(&prod_large_id).unwrap().options.get("size").unwrap(), "L");
+        assert_eq!(listing.history_len, 1);
+        assert!(schema.get_product_listing(&listing_bad_id).is_none());
+        assert_eq!(schema.get_product_listings_by_company_id(&co1_id).len(), 1);
+
+        let tx_update = transactions::product_listing::TxUpdate::sign(
+            &listing_id,
+            &String::from("Red shirts, now in Small"),
+            &Default::default(),
+            &vec![make_entry(&prod_small_id, "S")].into_iter().collect::<HashMap<_, _>>(),
+            &Default::default(),
+            &true,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_update]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let listing = schema.get_product_listing(&listing_id).unwrap();
+        assert_eq!(listing.name, "Red shirts, now in Small");
+        assert_eq!(listing.options.len(), 1);
+        assert_eq!(listing.products.len(), 1);
+        assert_eq!(listing.history_len, 2);
+
+        let tx_delete = transactions::product_listing::TxDelete::sign(
+            &listing_id,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_delete]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let listing = schema.get_product_listing(&listing_id).unwrap();
+        assert!(listing.is_deleted());
+        assert_eq!(listing.history_len, 3);
+        assert_eq!(schema.get_product_listings_by_company_id(&co1_id).len(), 0);
+    }
+}
+
@@ -0,0 +1,313 @@
+use chrono::{DateTime, Utc};
+use validator::Validate;
+use exonum::{
+    blockchain::{ExecutionError, ExecutionResult, Transaction, TransactionContext},
+};
+use models::{
+    proto,
+    access::Permission,
+};
+use crate::block::{
+    schema::Schema,
+    transactions::access,
+};
+use util::{
+    self,
+    protobuf::empty_opt,
+};
+use super::CommonError;
+
+#[derive(Debug, Fail)]
+#[repr(u8)]
+pub enum TransactionError {
+    #[fail(display = "Invalid ID")]
+    InvalidID = 0,
+
+    #[fail(display = "Region not found")]
+    RegionNotFound = 1,
+}
+define_exec_error!(TransactionError);
+
+deftransaction! {
+    #[exonum(pb = "proto::region::TxCreate")]
+    pub struct TxCreate {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        #[validate(length(min = 1))]
+        pub name: String,
+        pub meta: String,
+        #[validate(custom = "super::validate_date")]
+        pub created: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxCreate {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::RegionCreate)?;
+
+        if schema.get_region(&self.id).is_some() {
+            Err(CommonError::IDExists)?;
+        }
+        if !util::time::is_current(&self.created) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        schema.regions_create(&self.id, &self.name, &self.meta, &self.created, &hash);
+        Ok(())
+    }
+}
+
+deftransaction! {
+    #[exonum(pb = "proto::region::TxUpdate")]
+    pub struct TxUpdate {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub name: String,
+        pub meta: String,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxUpdate {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::RegionUpdate)?;
+
+        let region = match schema.get_region(&self.id) {
+            Some(x) => x,
+            None => Err(TransactionError::RegionNotFound)?,
+        };
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?;
+        }
+
+        let name = empty_opt(&self.name).map(|x| x.as_str());
+        let meta = empty_opt(&self.meta).map(|x| x.as_str());
+        schema.regions_update(region, name, meta, &self.updated, &hash);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use exonum::crypto;
+    use models::{
+        self,
+        access::Role,
+        company::ApprovalStatus,
+        user::RegionRole,
+    };
+    use util;
+    use crate::block::{transactions, schema::Schema};
+    use crate::test::{self, gen_uuid};
+
+    #[test]
+    fn regional_admins_stay_in_their_region() {
+        let mut testkit = test::init_testkit();
+        let uid = gen_uuid();
+        let (tx_user, root_pub, root_sec) = test::tx_superuser(&uid);
+        let admin_id = gen_uuid();
+        let founder_id = gen_uuid();
+        let (admin_pub, admin_sec) = crypto::gen_keypair();
+        let (founder_pub, founder_sec) = crypto::gen_keypair();
+        let tx_admin = transactions::user::TxCreate::sign(
+            &admin_id,
+            &admin_pub,
+            &vec![Role::User],
+            &String::from("regional.admin@basis.org"),
+            &String::from("Regional Administrator"),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_founder = transactions::user::TxCreate::sign(
+            &founder_id,
+            &founder_pub,
+            &vec![Role::User],
+            &String::from("founder@basis.org"),
+            &String::from("Serial Entrepreneur"),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_user, tx_admin, tx_founder]);
+
+        let region1_id = gen_uuid();
+        let region2_id = gen_uuid();
+        let tx_region1 = TxCreate::sign(
+            &region1_id,
+            &String::from("Northern Hills"),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        let tx_region2 = TxCreate::sign(
+            &region2_id,
+            &String::from("Southern Marshes"),
+            &String::from("{}"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        // regular users can't make regions
+        let tx_region3 = TxCreate::sign(
+            &gen_uuid(),
+            &String::from("My Own Personal Fiefdom"),
+            &String::from("{}"),
+            &util::time::now(),
+            &founder_pub,
+            &founder_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_region1, tx_region2, tx_region3]);
+
+        let tx_update = TxUpdate::sign(
+            &region1_id,
+            &String::from("Northern Highlands"),
+            &String::from(""),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        // only some roles can be regional
+        let tx_roles_bad = transactions::user::TxSetRegionRoles::sign(
+            &admin_id,
+            &vec![RegionRole::new(&region1_id, &vec![Role::SuperAdmin])],
+            &String::from("king of the north"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_update, tx_roles_bad]);
+        let tx_roles = transactions::user::TxSetRegionRoles::sign(
+            &admin_id,
+            &vec![RegionRole::new(&region1_id, &vec![Role::CompanyAdmin])],
+            &String::from("warden of the north"),
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_roles]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let region = schema.get_region(&region1_id).unwrap();
+        assert_eq!(region.name, "Northern Highlands");
+        assert_eq!(region.meta, "{}");
+        assert_eq!(region.history_len, 2);
+        assert_eq!(schema.regions().keys().count(), 2);
+        let admin = schema.get_user(&admin_id).unwrap();
+        assert_eq!(admin.roles_in_region(&region1_id), vec![Role::CompanyAdmin]);
+        assert_eq!(admin.history_len, 2);
+
+        let co1_id = gen_uuid();
+        let co2_id = gen_uuid();
+        let tx_co1 = transactions::company::TxCreatePrivate::sign(
+            &co1_id,
+            &String::from("hills@basis.org"),
+            &String::from("Hill Goat Dairy"),
+            &vec![],
+            &models::company::TxCreatePrivateFounder::new(&gen_uuid(), "Goat milker", 1.0, &vec![]),
+            &util::time::now(),
+            &region1_id,
+            &founder_pub,
+            &founder_sec
+        );
+        let tx_co2 = transactions::company::TxCreatePrivate::sign(
+            &co2_id,
+            &String::from("marsh@basis.org"),
+            &String::from("Marsh Rice Cooperative"),
+            &vec![],
+            &models::company::TxCreatePrivateFounder::new(&gen_uuid(), "Rice planter", 1.0, &vec![]),
+            &util::time::now(),
+            &region2_id,
+            &founder_pub,
+            &founder_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_co1, tx_co2]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let companies = schema.get_companies_by_region_id(&region1_id);
+        assert_eq!(companies.len(), 1);
+        assert_eq!(companies[0].id, co1_id);
+        assert_eq!(companies[0].region_id, region1_id);
+        assert_eq!(companies[0].approval_status, ApprovalStatus::Pending);
+        assert_eq!(schema.get_companies_by_region_id(&region2_id).len(), 1);
+
+        // our admin can approve companies in their region, but not elsewhere,
+        // and they can't drag companies into their region either
+        let tx_approve1 = transactions::company::TxSetApproved::sign(
+            &co1_id,
+            &ApprovalStatus::Approved,
+            &String::from("goats are good"),
+            &util::time::now(),
+            &admin_pub,
+            &admin_sec
+        );
+        let tx_approve2 = transactions::company::TxSetApproved::sign(
+            &co2_id,
+            &ApprovalStatus::Approved,
+            &String::from("rice is nice"),
+            &util::time::now(),
+            &admin_pub,
+            &admin_sec
+        );
+        let tx_move = transactions::company::TxSetRegion::sign(
+            &co2_id,
+            &region1_id,
+            &util::time::now(),
+            &admin_pub,
+            &admin_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_approve1, tx_approve2, tx_move]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.get_company(&co1_id).unwrap().approval_status, ApprovalStatus::Approved);
+        let co2 = schema.get_company(&co2_id).unwrap();
+        assert_eq!(co2.approval_status, ApprovalStatus::Pending);
+        assert_eq!(co2.region_id, region2_id);
+
+        // once a system-wide admin moves the company over, it's fair game
+        let tx_move = transactions::company::TxSetRegion::sign(
+            &co2_id,
+            &region1_id,
+            &util::time::now(),
+            &root_pub,
+            &root_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_move]);
+        let tx_approve2 = transactions::company::TxSetApproved::sign(
+            &co2_id,
+            &ApprovalStatus::Approved,
+            &String::from("rice is nice, now that it's ours"),
+            &util::time::now(),
+            &admin_pub,
+            &admin_sec
+        );
+        testkit.create_block_with_transactions(txvec![tx_approve2]);
+
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        let co2 = schema.get_company(&co2_id).unwrap();
+        assert_eq!(co2.region_id, region1_id);
+        assert_eq!(co2.approval_status, ApprovalStatus::Approved);
+        assert_eq!(schema.get_companies_by_region_id(&region1_id).len(), 2);
+        assert_eq!(schema.get_companies_by_region_id(&region2_id).len(), 0);
+    }
+}
@@ -3,6 +3,7 @@ use validator::Validate;
 use exonum::{
     blockchain::{ExecutionError, ExecutionResult, Transaction, TransactionContext},
 };
+use exonum_merkledb::IndexAccess;
 use models::{
     proto,
     access::Permission,
@@ -28,6 +29,18 @@ pub enum TransactionError {
 }
 define_exec_error!(TransactionError);
 
+/// Find the region of the company that makes the given product (blank if the
+/// company isn't in a region). Tag admins can be scoped to a region, so this
+/// tells us whose tag admins get a say over the product.
+fn product_region_id<T>(schema: &Schema<T>, product_id: &str) -> String
+    where T: IndexAccess
+{
+    schema.get_product(product_id)
+        .and_then(|product| schema.get_company(&product.company_id))
+        .map(|company| company.region_id)
+        .unwrap_or(String::from(""))
+}
+
 deftransaction! {
     #[exonum(pb = "proto::resource_tag::TxCreate")]
     pub struct TxCreate {
@@ -48,7 +61,8 @@ impl Transaction for TxCreate {
 
         let mut schema = Schema::new(context.fork());
 
-        access::check(&mut schema, pubkey, Permission::ResourceTagCreate)?;
+        let region_id = product_region_id(&schema, &self.product_id);
+        access::check_region(&mut schema, pubkey, &region_id, Permission::ResourceTagCreate)?;
 
         match schema.get_product(&self.product_id) {
             Some(_) => {}
@@ -86,14 +100,14 @@ impl Transaction for TxDelete {
 
         let mut schema = Schema::new(context.fork());
 
-        access::check(&mut schema, pubkey, Permission::ResourceTagDelete)?;
-
         let tag = schema.get_resource_tag(&self.id);
         if tag.is_none() {
             Err(TransactionError::ResourceTagNotFound)?;
         }
 
         let resource_tag = tag.unwrap();
+        let region_id = product_region_id(&schema, &resource_tag.product_id);
+        access::check_region(&mut schema, pubkey, &region_id, Permission::ResourceTagDelete)?;
 
         if resource_tag.is_deleted() {
             Err(TransactionError::AlreadyDeleted)?;
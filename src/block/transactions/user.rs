@@ -8,6 +8,7 @@ use exonum::{
 use models::{
     proto,
     access::{Permission, Role},
+    user::RegionRole,
 };
 use crate::block::{
     schema::Schema,
@@ -34,6 +35,12 @@ pub enum TransactionError {
 
     #[fail(display = "User not found")]
     UserNotFound = 6,
+
+    #[fail(display = "Region not found")]
+    RegionNotFound = 7,
+
+    #[fail(display = "Role cannot be granted within a region")]
+    InvalidRegionRole = 8,
 }
 define_exec_error!(TransactionError);
 
@@ -228,6 +235,49 @@ impl Transaction for TxSetRoles {
     }
 }
 
+deftransaction! {
+    #[exonum(pb = "proto::user::TxSetRegionRoles")]
+    pub struct TxSetRegionRoles {
+        #[validate(custom = "super::validate_uuid")]
+        pub id: String,
+        pub region_roles: Vec<RegionRole>,
+        pub memo: String,
+        #[validate(custom = "super::validate_date")]
+        pub updated: DateTime<Utc>,
+    }
+}
+
+impl Transaction for TxSetRegionRoles {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        validate_transaction!(self);
+        let pubkey = &context.author();
+        let hash = context.tx_hash();
+
+        let mut schema = Schema::new(context.fork());
+
+        access::check(&mut schema, pubkey, Permission::UserAdminUpdate)?;
+
+        let user = match schema.get_user(self.id.as_str()) {
+            Some(x) => x,
+            None => Err(TransactionError::UserNotFound)?,
+        };
+        for region_role in &self.region_roles {
+            if schema.get_region(&region_role.region_id).is_none() {
+                Err(TransactionError::RegionNotFound)?;
+            }
+            if region_role.roles.iter().any(|x| !x.is_regional()) {
+                Err(TransactionError::InvalidRegionRole)?;
+            }
+        }
+        if !util::time::is_current(&self.updated) {
+            Err(CommonError::InvalidTime)?
+        }
+
+        schema.users_set_region_roles(user, &self.id, &self.region_roles, &self.updated, &hash);
+        Ok(())
+    }
+}
+
 deftransaction! {
     #[exonum(pb = "proto::user::TxDelete")]
     pub struct TxDelete {
@@ -7,6 +7,8 @@ use exonum::{
     messages::{Signed, RawTransaction},
 };
 use hex::FromHex;
+use chrono::{DateTime, Utc};
+use models::cost_tag::CostTagEntry;
 use crate::{
     config,
     block::{transactions, Service},
@@ -44,3 +46,130 @@ pub fn tx_superuser(uid: &str) -> (Signed<RawTransaction>, PublicKey, SecretKey)
     (txuser, root_pub, root_sec)
 }
 
+/// create the transactions that walk an order through each of its process
+/// statuses, from New all the way to Finalized
+pub fn tx_finalize_order(order_id: &str, updated: &DateTime<Utc>, pubkey: &PublicKey, seckey: &SecretKey) -> Vec<Signed<RawTransaction>> {
+    vec![
+        models::order::ProcessStatus::Accepted,
+        models::order::ProcessStatus::Processing,
+        models::order::ProcessStatus::Completed,
+        models::order::ProcessStatus::Finalized,
+    ].into_iter()
+        .map(|status| {
+            transactions::order::TxUpdateStatus::sign(
+                &order_id.to_owned(),
+                &status,
+                updated,
+                pubkey,
+                seckey
+            )
+        })
+        .collect::<Vec<_>>()
+}
+
+
+/// The ids (and keys) behind the costing setup from `setup_widget_orders()`
+pub struct WidgetFixture {
+    pub root_pub: PublicKey,
+    pub root_sec: SecretKey,
+    /// builds red widgets
+    pub co1_id: String,
+    /// buys red widgets
+    pub co2_id: String,
+    pub ctag1_op_id: String,
+    pub ctag2_op_id: String,
+    pub prod_id: String,
+    /// one per (quantity, date) passed in, in the same order
+    pub ord_ids: Vec<String>,
+}
+
+/// set up the usual costing scenario: a superuser, one company that builds red
+/// widgets and another that buys them. the buyer orders widgets once for each
+/// (quantity, date) given, and each order is walked through to Finalized.
+pub fn setup_widget_orders(testkit: &mut TestKit, orders: &Vec<(f64, DateTime<Utc>)>) -> WidgetFixture {
+    let uid = gen_uuid();
+    let (tx_user, root_pub, root_sec) = tx_superuser(&uid);
+    testkit.create_block_with_transactions(txvec![tx_user]);
+
+    let co1_id = gen_uuid();
+    let co2_id = gen_uuid();
+    let ctag1_op_id = gen_uuid();
+    let ctag2_op_id = gen_uuid();
+    let tx_co1 = transactions::company::TxCreatePrivate::sign(
+        &co1_id,
+        &String::from("company1@basis.org"),
+        &String::from("Widget Builders Inc"),
+        &vec![models::company::TxCreatePrivateCostTag::new(&ctag1_op_id, "operating", "")],
+        &models::company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget builder", 1.0, &vec![CostTagEntry::new(&ctag1_op_id, 1)]),
+        &util::time::now(),
+        &String::from(""),
+        &root_pub,
+        &root_sec
+    );
+    let tx_co2 = transactions::company::TxCreatePrivate::sign(
+        &co2_id,
+        &String::from("company2@basis.org"),
+        &String::from("Widget Distributors Inc"),
+        &vec![models::company::TxCreatePrivateCostTag::new(&ctag2_op_id, "operating", "")],
+        &models::company::TxCreatePrivateFounder::new(&gen_uuid(), "Widget distributor", 1.0, &vec![CostTagEntry::new(&ctag2_op_id, 1)]),
+        &util::time::now(),
+        &String::from(""),
+        &root_pub,
+        &root_sec
+    );
+    testkit.create_block_with_transactions(txvec![tx_co1, tx_co2]);
+
+    let prod_id = gen_uuid();
+    let tx_prod = transactions::product::TxCreate::sign(
+        &prod_id,
+        &co1_id,
+        &String::from("Red widget"),
+        &models::product::Unit::Millimeter,
+        &3.0,
+        &models::product::Dimensions::new(100.0, 100.0, 100.0),
+        &vec![CostTagEntry::new(&ctag1_op_id, 1)],
+        &true,
+        &String::from("{}"),
+        &util::time::now(),
+        &root_pub,
+        &root_sec
+    );
+    testkit.create_block_with_transactions(txvec![tx_prod]);
+    // a product can't be ordered until it's been costed. repairing the
+    // widget builder's (empty) aggregates costs it without adding anything.
+    testkit.create_block_with_transactions(txvec![
+        transactions::costs::TxRepairAggregates::sign(&co1_id, &util::time::now(), &root_pub, &root_sec),
+    ]);
+
+    let ord_ids = orders.iter().map(|_| gen_uuid()).collect::<Vec<_>>();
+    let tx_ords = orders.iter().zip(ord_ids.iter())
+        .map(|((quantity, created), ord_id)| {
+            transactions::order::TxCreate::sign(
+                ord_id,
+                &co2_id,
+                &co1_id,
+                &vec![CostTagEntry::new(&ctag2_op_id, 1)],
+                &vec![models::order::ProductEntry::new(&prod_id, *quantity, &models::costs::Costs::new(), false)],
+                created,
+                &root_pub,
+                &root_sec
+            )
+        })
+        .collect::<Vec<_>>();
+    testkit.create_block_with_transactions(tx_ords);
+    let tx_finalize = orders.iter().zip(ord_ids.iter())
+        .flat_map(|((_, created), ord_id)| tx_finalize_order(ord_id, created, &root_pub, &root_sec))
+        .collect::<Vec<_>>();
+    testkit.create_block_with_transactions(tx_finalize);
+
+    WidgetFixture {
+        root_pub,
+        root_sec,
+        co1_id,
+        co2_id,
+        ctag1_op_id,
+        ctag2_op_id,
+        prod_id,
+        ord_ids,
+    }
+}